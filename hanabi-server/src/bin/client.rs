@@ -1,5 +1,9 @@
 #[tokio::main]
 async fn main() {
     let args = hanabi_server::Args::parse();
-    turnbased_game_server::start_client::<hanabi::Game>(args.client_address()).await;
+    turnbased_game_server::start_client::<hanabi_server::Games>(
+        args.client_address(),
+        args.full_screen(),
+    )
+    .await;
 }
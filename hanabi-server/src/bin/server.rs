@@ -1,5 +1,14 @@
 #[tokio::main]
 async fn main() {
     let args = hanabi_server::Args::parse();
-    turnbased_game_server::start_server::<hanabi::Game>(args.server_address()).await;
+    turnbased_game_server::logging::init(args.log_level(), args.log_format());
+    turnbased_game_server::start_server::<hanabi_server::Games>(
+        args.server_address(),
+        args.limits(),
+        args.notify_command(),
+        args.demo(),
+        args.state_file(),
+        args.tls(),
+    )
+    .await;
 }
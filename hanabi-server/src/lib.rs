@@ -1,11 +1,98 @@
 use clap::Parser;
 
+turnbased_game_server::game_registry! {
+    /// The games hosted by the server.
+    pub enum Games {
+        settings: GameSettings,
+        moves: GameMove,
+        client_actions: GameClientAction,
+        Hanabi(hanabi::Game) = "hanabi",
+    }
+}
+
 #[derive(Parser)]
 pub struct Args {
     address: Option<String>,
 
     #[arg(long, short)]
     local: bool,
+
+    /// Maximum number of open rooms.
+    #[arg(long)]
+    max_rooms: Option<usize>,
+    /// Maximum number of open rooms created by one user.
+    #[arg(long)]
+    max_rooms_per_user: Option<usize>,
+    /// Maximum number of connections from one IP address.
+    #[arg(long)]
+    max_connections_per_ip: Option<usize>,
+    /// Maximum number of spectators of one room.
+    #[arg(long)]
+    max_spectators_per_room: Option<usize>,
+    /// Minutes without moves after which a game is abandoned.
+    #[arg(long)]
+    abandon_after_minutes: Option<u64>,
+    /// Minutes after which a game is abandoned when one of its players lost
+    /// their connection and did not come back.
+    #[arg(long)]
+    abandon_disconnected_after_minutes: Option<u64>,
+    /// Maximum number of actions one connection may send per minute.
+    /// Connections that send twice as many get their IP address banned.
+    #[arg(long)]
+    max_actions_per_minute: Option<usize>,
+    /// Minutes for which a banned IP address is refused.
+    #[arg(long)]
+    ban_minutes: Option<u64>,
+    /// Hours without activity after which a room waiting for players is
+    /// removed.
+    #[arg(long)]
+    expire_pending_after_hours: Option<u64>,
+    /// Days after the end of its game after which a room is removed.
+    #[arg(long)]
+    keep_ended_days: Option<u64>,
+    /// Seconds between pings to each connection.
+    #[arg(long)]
+    ping_seconds: Option<u64>,
+    /// Seconds without any message, including answers to pings, after which
+    /// a connection is closed.
+    #[arg(long)]
+    connection_timeout_seconds: Option<u64>,
+    /// Shell command that notifies offline players of their turn in
+    /// correspondence rooms, e.g. by email. It is run with the environment
+    /// variables TURNBASED_USER, TURNBASED_CONTACT, TURNBASED_ROOM and
+    /// TURNBASED_MESSAGE.
+    #[arg(long)]
+    notify_command: Option<String>,
+    /// Host a room in which bots play games with these settings on a loop,
+    /// which anyone can watch without logging in.
+    #[arg(long)]
+    demo: Option<String>,
+    /// Number of bots in the demo room.
+    #[arg(long, default_value_t = 3)]
+    demo_players: usize,
+    /// Seconds between the moves of the demo bots.
+    #[arg(long, default_value_t = 2)]
+    demo_move_seconds: u64,
+    /// File in which users and rooms are kept, so games survive a restart.
+    #[arg(long)]
+    state_file: Option<std::path::PathBuf>,
+    /// PEM certificate with which the server accepts `wss://` connections
+    /// itself, instead of behind a reverse proxy.
+    #[arg(long, requires = "tls_key")]
+    tls_cert: Option<std::path::PathBuf>,
+    /// PEM private key of the certificate, in PKCS#8, PKCS#1 or SEC1 format.
+    #[arg(long, requires = "tls_cert")]
+    tls_key: Option<std::path::PathBuf>,
+    /// Least severe level of the server log: error, warn, info, debug or
+    /// trace.
+    #[arg(long, default_value = "info")]
+    log_level: turnbased_game_server::logging::Level,
+    /// Format of the server log: text, or json for log collectors.
+    #[arg(long, default_value = "text")]
+    log_format: turnbased_game_server::logging::LogFormat,
+    /// Use the line-based client instead of the full-screen one.
+    #[arg(long)]
+    plain: bool,
 }
 
 impl Args {
@@ -14,21 +101,90 @@ impl Args {
     }
     pub fn server_address(&self) -> &str {
         if self.local {
-            "127.0.0.1:38271".into()
+            "127.0.0.1:38271"
         } else {
             if let Some(address) = self.address.as_ref() {
-                &address
+                address
             } else {
                 "127.0.0.1:38271"
             }
         }
     }
+    pub fn limits(&self) -> turnbased_game_server::Limits {
+        let default = turnbased_game_server::Limits::default();
+        turnbased_game_server::Limits {
+            max_rooms: self.max_rooms.unwrap_or(default.max_rooms),
+            max_rooms_per_user: self
+                .max_rooms_per_user
+                .unwrap_or(default.max_rooms_per_user),
+            max_connections_per_ip: self
+                .max_connections_per_ip
+                .unwrap_or(default.max_connections_per_ip),
+            max_spectators_per_room: self
+                .max_spectators_per_room
+                .unwrap_or(default.max_spectators_per_room),
+            abandon_after: self
+                .abandon_after_minutes
+                .map_or(default.abandon_after, |minutes| minutes * 60),
+            abandon_disconnected_after: self
+                .abandon_disconnected_after_minutes
+                .map_or(default.abandon_disconnected_after, |minutes| minutes * 60),
+            max_actions_per_minute: self
+                .max_actions_per_minute
+                .unwrap_or(default.max_actions_per_minute),
+            ban_secs: self
+                .ban_minutes
+                .map_or(default.ban_secs, |minutes| minutes * 60),
+            expire_pending_after: self
+                .expire_pending_after_hours
+                .map_or(default.expire_pending_after, |hours| hours * 60 * 60),
+            keep_ended_for: self
+                .keep_ended_days
+                .map_or(default.keep_ended_for, |days| days * 24 * 60 * 60),
+            ping_interval: self.ping_seconds.unwrap_or(default.ping_interval),
+            connection_timeout: self
+                .connection_timeout_seconds
+                .unwrap_or(default.connection_timeout),
+        }
+    }
+    pub fn notify_command(&self) -> Option<String> {
+        self.notify_command.clone()
+    }
+    pub fn demo(&self) -> Option<turnbased_game_server::Demo<GameSettings>> {
+        let settings = self.demo.as_ref()?.parse().unwrap_or_else(|err| {
+            eprintln!("Invalid demo settings: {err}");
+            std::process::exit(1);
+        });
+        Some(turnbased_game_server::Demo {
+            players: self.demo_players,
+            settings,
+            move_secs: self.demo_move_seconds,
+        })
+    }
+    pub fn state_file(&self) -> Option<std::path::PathBuf> {
+        self.state_file.clone()
+    }
+    pub fn tls(&self) -> Option<turnbased_game_server::Tls> {
+        Some(turnbased_game_server::Tls {
+            cert: self.tls_cert.clone()?,
+            key: self.tls_key.clone()?,
+        })
+    }
+    pub fn log_level(&self) -> turnbased_game_server::logging::Level {
+        self.log_level
+    }
+    pub fn log_format(&self) -> turnbased_game_server::logging::LogFormat {
+        self.log_format
+    }
+    pub fn full_screen(&self) -> bool {
+        !self.plain
+    }
     pub fn client_address(&self) -> &str {
         if self.local {
-            "ws://127.0.0.1:38271".into()
+            "ws://127.0.0.1:38271"
         } else {
             if let Some(address) = self.address.as_ref() {
-                &address
+                address
             } else {
                 "ws://hanabi.ragnargrootkoerkamp.nl/websocket/"
             }
@@ -0,0 +1,600 @@
+//! Estimate how hard a deal is by letting a simple bot play it many times.
+//!
+//! The bot plays cards it knows to be playable, hints playable cards of other
+//! players, and otherwise discards its oldest unhinted card. Rollouts differ
+//! only in the random choices of the bot, so the spread of the scores says
+//! something about the deal itself.
+//!
+//! `simulate` plays many deals with any bot, to see how changes to the rules
+//! shift the scores. `simulate_bots` seats a chosen bot at every seat and
+//! also times them, to compare strategies.
+//!
+//! `analyze_move` compares a move of a recorded game with the moves the
+//! search bot would consider, like a chess engine annotating a game.
+use std::{
+    fmt::Display,
+    ops::Range,
+    time::{Duration, Instant},
+};
+
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bot::{BotKind, SearchBot},
+    replay::Replay,
+    CardIdx, CardKnowledge, CardWithKnowledge, Game, GameOptions, GameState, Hand, Hint, Move,
+    MoveLog, Player, PlayerMoveLogWithNames, MAX_VALUE,
+};
+use turnbased_game_server::style::OwoColorize;
+
+/// Rough difficulty of a deal, based on the average score of the bot compared
+/// to other deals of the same configuration.
+#[derive(
+    Debug,
+    Serialize,
+    Deserialize,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    strum_macros::Display,
+    strum_macros::EnumString,
+)]
+#[strum(ascii_case_insensitive)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+}
+
+/// Mean scores, as a fraction of the maximum score, from which a deal counts
+/// as easy or as medium. They depend on the number of players and the
+/// settings, so they are calibrated for each configuration.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct DifficultyThresholds {
+    pub easy: f64,
+    pub medium: f64,
+}
+
+/// Seeds whose deals are estimated to calibrate the difficulty thresholds.
+pub const CALIBRATION_SEEDS: Range<u64> = 0..100;
+
+impl DifficultyThresholds {
+    /// Thresholds that split the deals of `seeds` into thirds, estimated with
+    /// `rollouts` rollouts each.
+    pub fn calibrate(
+        num_players: usize,
+        options: &GameOptions,
+        rollouts: usize,
+        seeds: Range<u64>,
+    ) -> Result<Self, &'static str> {
+        let mut fractions = seeds
+            .map(|seed| {
+                let options = GameOptions {
+                    seed: Some(seed),
+                    ..options.clone()
+                };
+                Ok(estimate_deal(num_players, &options, rollouts)?.fraction())
+            })
+            .collect::<Result<Vec<f64>, &'static str>>()?;
+        if fractions.is_empty() {
+            return Err("No seeds to calibrate the difficulty with");
+        }
+        fractions.sort_by(f64::total_cmp);
+        let quantile = |q: f64| fractions[((fractions.len() - 1) as f64 * q).round() as usize];
+        Ok(Self {
+            easy: quantile(2. / 3.),
+            medium: quantile(1. / 3.),
+        })
+    }
+}
+
+impl Display for DifficultyThresholds {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "easy from {:.1}%, medium from {:.1}% of the maximum score",
+            100. * self.easy,
+            100. * self.medium
+        )
+    }
+}
+
+/// Scores of all rollouts of one deal.
+#[derive(Debug, Clone)]
+pub struct DealEstimate {
+    pub scores: Vec<usize>,
+    pub max_score: usize,
+}
+
+impl DealEstimate {
+    pub fn mean(&self) -> f64 {
+        self.scores.iter().sum::<usize>() as f64 / self.scores.len().max(1) as f64
+    }
+
+    /// The mean score as a fraction of the maximum score.
+    pub fn fraction(&self) -> f64 {
+        self.mean() / self.max_score as f64
+    }
+
+    /// Number of rollouts that reached each score, from 0 to the maximum score.
+    pub fn histogram(&self) -> Vec<usize> {
+        let mut histogram = vec![0; self.max_score + 1];
+        for &score in &self.scores {
+            histogram[score] += 1;
+        }
+        histogram
+    }
+
+    /// The difficulty of the deal, with thresholds calibrated for its
+    /// configuration.
+    pub fn difficulty(&self, thresholds: &DifficultyThresholds) -> Difficulty {
+        let fraction = self.fraction();
+        if fraction >= thresholds.easy {
+            Difficulty::Easy
+        } else if fraction >= thresholds.medium {
+            Difficulty::Medium
+        } else {
+            Difficulty::Hard
+        }
+    }
+}
+
+impl Display for DealEstimate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "mean score {:.1} / {} over {} rollouts",
+            self.mean(),
+            self.max_score,
+            self.scores.len()
+        )?;
+        for (score, count) in self.histogram().into_iter().enumerate() {
+            if count > 0 {
+                writeln!(f, " {score:2}: {count}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Seeds of deals of similar difficulty, so that several groups can play the
+/// same games, e.g. in a tournament.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SeedPack {
+    pub num_players: usize,
+    /// Settings of the games, without a seed.
+    pub options: GameOptions,
+    pub difficulty: Difficulty,
+    /// The thresholds with which the difficulty was determined.
+    pub thresholds: DifficultyThresholds,
+    pub seeds: Vec<u64>,
+}
+
+impl SeedPack {
+    /// Settings of each game of the pack.
+    pub fn games(&self) -> impl Iterator<Item = GameOptions> + '_ {
+        self.seeds.iter().map(|&seed| GameOptions {
+            seed: Some(seed),
+            ..self.options.clone()
+        })
+    }
+}
+
+/// Outcome of one simulated game.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct SimulatedGame {
+    pub seed: u64,
+    pub score: usize,
+    /// How the game ended: won, died, ended after the last round, unwinnable
+    /// in a perfect-score-or-bust game, or abandoned because a bot made no
+    /// allowed move. Never a state of a game in progress, like its final
+    /// round.
+    pub state: GameState,
+    pub lives_lost: usize,
+    #[serde(default)]
+    pub hints_used: usize,
+}
+
+impl SimulatedGame {
+    fn new(seed: u64, game: &Game) -> Self {
+        SimulatedGame {
+            seed,
+            score: game.played.score(),
+            state: game.game_state,
+            lives_lost: game.options.starting_lives() - game.lives,
+            hints_used: game
+                .move_log
+                .iter()
+                .filter(|mov| matches!(mov.mov, MoveLog::Hint { .. }))
+                .count(),
+        }
+    }
+}
+
+/// Time a bot spent choosing its moves, over all its seats and games.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct BotTiming {
+    pub bot: BotKind,
+    pub moves: usize,
+    pub time: Duration,
+}
+
+impl BotTiming {
+    pub fn per_move(&self) -> Duration {
+        self.time / self.moves.max(1) as u32
+    }
+}
+
+/// Outcomes of all games of a `simulate` run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimulationReport {
+    pub games: Vec<SimulatedGame>,
+    pub max_score: usize,
+    /// Only filled by `simulate_bots`.
+    #[serde(default)]
+    pub timings: Vec<BotTiming>,
+}
+
+impl SimulationReport {
+    pub fn mean(&self) -> f64 {
+        self.games.iter().map(|g| g.score).sum::<usize>() as f64 / self.games.len().max(1) as f64
+    }
+
+    /// Number of games that reached each score, from 0 to the maximum score.
+    pub fn histogram(&self) -> Vec<usize> {
+        let mut histogram = vec![0; self.max_score + 1];
+        for game in &self.games {
+            histogram[game.score] += 1;
+        }
+        histogram
+    }
+
+    /// Fraction of the games that ended in the given state.
+    pub fn fraction(&self, state: GameState) -> f64 {
+        self.games.iter().filter(|g| g.state == state).count() as f64
+            / self.games.len().max(1) as f64
+    }
+
+    pub fn mean_hints_used(&self) -> f64 {
+        self.games.iter().map(|g| g.hints_used).sum::<usize>() as f64
+            / self.games.len().max(1) as f64
+    }
+
+    pub fn mean_lives_lost(&self) -> f64 {
+        self.games.iter().map(|g| g.lives_lost).sum::<usize>() as f64
+            / self.games.len().max(1) as f64
+    }
+}
+
+impl Display for SimulationReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "mean score {:.2} / {} over {} games; won {:.1}%, died {:.1}%",
+            self.mean(),
+            self.max_score,
+            self.games.len(),
+            100. * self.fraction(GameState::Won),
+            100. * self.fraction(GameState::Died),
+        )?;
+        writeln!(
+            f,
+            "hints used {:.2}, lives lost {:.2} per game",
+            self.mean_hints_used(),
+            self.mean_lives_lost(),
+        )?;
+        for (score, count) in self.histogram().into_iter().enumerate() {
+            if count > 0 {
+                writeln!(f, " {score:2}: {count}")?;
+            }
+        }
+        for timing in &self.timings {
+            writeln!(
+                f,
+                "{}: {} moves, {:?} per move",
+                timing.bot,
+                timing.moves,
+                timing.per_move()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Play the deal of every seed in `seeds` `games_per_seed` times with `num_players`
+/// copies of `bot`, e.g. to compare the scores of variants or rule changes.
+///
+/// The bot returns the moves it would like to make, most preferred first,
+/// like `candidate_moves`. The first allowed move is made; when none of them
+/// is allowed, the game is abandoned. The randomness of the bot is derived
+/// from the seed, so reports are reproducible.
+pub fn simulate(
+    num_players: usize,
+    options: &GameOptions,
+    mut bot: impl FnMut(&Game, Player, &mut StdRng) -> Vec<Move>,
+    games_per_seed: usize,
+    seeds: Range<u64>,
+) -> Result<SimulationReport, &'static str> {
+    let players: Vec<String> = (1..=num_players).map(|id| format!("Bot{id}")).collect();
+    let mut games = vec![];
+    for seed in seeds {
+        let deal = Game::new_with_seed(players.clone(), options.clone(), seed)?;
+        let mut rng = StdRng::seed_from_u64(seed);
+        for _ in 0..games_per_seed {
+            let game = rollout(deal.clone(), &mut bot, &mut rng);
+            games.push(SimulatedGame::new(seed, &game));
+        }
+    }
+    Ok(SimulationReport {
+        games,
+        max_score: options.variant.max_score(),
+        timings: vec![],
+    })
+}
+
+/// Play the deal of every seed in `seeds` once, with `bots[i]` in seat `i`.
+///
+/// Each bot only sees the game from its own seat. When a bot makes a move
+/// that is not allowed, the game is abandoned. The randomness of the bots is
+/// derived from the seed, so scores are reproducible; timings are not.
+pub fn simulate_bots(
+    options: &GameOptions,
+    bots: &[BotKind],
+    seeds: Range<u64>,
+) -> Result<SimulationReport, &'static str> {
+    let players: Vec<String> = (1..=bots.len()).map(|id| format!("Bot{id}")).collect();
+    let mut games = vec![];
+    let mut timings: Vec<BotTiming> = vec![];
+    for seed in seeds {
+        let mut game = Game::new_with_seed(players.clone(), options.clone(), seed)?;
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut seats: Vec<_> = bots.iter().map(|bot| bot.new_bot(rng.gen())).collect();
+        let mut times = vec![Duration::ZERO; bots.len()];
+        let mut moves = vec![0; bots.len()];
+        while let Some(player) = game.game_state.next_player() {
+            let view = game.to_view(player);
+            let start = Instant::now();
+            let mov = seats[player].choose_move(&view, player);
+            times[player] += start.elapsed();
+            moves[player] += 1;
+            if game.make_move(player, mov).is_err() {
+                game.game_state = GameState::Abandoned;
+            }
+        }
+        games.push(SimulatedGame::new(seed, &game));
+        for (player, &bot) in bots.iter().enumerate() {
+            let timing = match timings.iter_mut().find(|t| t.bot == bot) {
+                Some(timing) => timing,
+                None => {
+                    timings.push(BotTiming {
+                        bot,
+                        moves: 0,
+                        time: Duration::ZERO,
+                    });
+                    timings.last_mut().unwrap()
+                }
+            };
+            timing.moves += moves[player];
+            timing.time += times[player];
+        }
+    }
+    Ok(SimulationReport {
+        games,
+        max_score: options.variant.max_score(),
+        timings,
+    })
+}
+
+/// Play the deal of `options.seed` `rollouts` times with the bot.
+pub fn estimate_deal(
+    num_players: usize,
+    options: &GameOptions,
+    rollouts: usize,
+) -> Result<DealEstimate, &'static str> {
+    let players: Vec<String> = (1..=num_players).map(|id| format!("Bot{id}")).collect();
+    let deal = Game::new(players, options.clone())?;
+    // Derive the bot's randomness from the seed, so estimates are reproducible.
+    let mut rng = StdRng::seed_from_u64(options.seed.unwrap_or_default());
+    let scores = (0..rollouts)
+        .map(|_| {
+            rollout(deal.clone(), candidate_moves, &mut rng)
+                .played
+                .score()
+        })
+        .collect();
+    Ok(DealEstimate {
+        scores,
+        max_score: options.variant.max_score(),
+    })
+}
+
+/// How a move of a recorded game compares to the moves the search bot would
+/// consider, judged by what the mover knew at the time.
+#[derive(Debug, Clone)]
+pub struct MoveAnalysis {
+    /// Description of the move that was made.
+    pub made: String,
+    /// Average score after the move over deals that fit what the mover knew.
+    pub expected_score: f64,
+    /// The move with the best average score on the same deals and its
+    /// average score, if it is not the move that was made.
+    pub suggestion: Option<(String, f64)>,
+}
+
+impl Display for MoveAnalysis {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} expected score {:.2}",
+            "analysis:".bold(),
+            self.expected_score
+        )?;
+        match &self.suggestion {
+            Some((suggestion, score)) => write!(
+                f,
+                " ({:+.2}); suggested: {suggestion} Expected score {score:.2}",
+                self.expected_score - score
+            ),
+            None => write!(f, ", as good as any move the bot considers"),
+        }
+    }
+}
+
+/// Compare move `turn` of the replay, counting from 1, with the moves the bot
+/// would consider in its place.
+pub fn analyze_move(
+    replay: &Replay,
+    turn: usize,
+    bot: &mut SearchBot,
+) -> Result<MoveAnalysis, &'static str> {
+    if turn == 0 || turn > replay.len() {
+        return Err("The game does not have that move.");
+    }
+    let before = replay.game_at(turn - 1)?;
+    let (player, made) = replay.moves[turn - 1].clone();
+    // Moves are told apart by what they do, since the same move can be given
+    // in different ways.
+    let describe = |mov: &Move| {
+        let mut game = before.clone();
+        game.make_move(player, mov.clone()).ok()?;
+        game.move_log.last().map(|mov| {
+            PlayerMoveLogWithNames {
+                mov,
+                players: &game.players,
+                colors: &game.options.color_names,
+            }
+            .to_string()
+        })
+    };
+    let mut moves = vec![(describe(&made).ok_or("The move was not allowed.")?, made)];
+    let view = before.to_view(player);
+    for mov in bot.candidates(&view, player) {
+        if let Some(description) = describe(&mov) {
+            if moves.iter().all(|(other, _)| *other != description) {
+                moves.push((description, mov));
+            }
+        }
+    }
+    let (descriptions, moves): (Vec<String>, Vec<Move>) = moves.into_iter().unzip();
+    let scores = bot
+        .expected_scores(&view, player, &moves)
+        .ok_or("No deal fits what the player knew.")?;
+    // The first best move, so the move that was made wins ties.
+    let best = (0..moves.len())
+        .rev()
+        .max_by(|&i, &j| scores[i].total_cmp(&scores[j]))
+        .unwrap();
+    Ok(MoveAnalysis {
+        made: descriptions[0].clone(),
+        expected_score: scores[0],
+        suggestion: (best != 0).then(|| (descriptions[best].clone(), scores[best])),
+    })
+}
+
+/// Play the game to the end with the bot and return the finished game.
+pub(crate) fn rollout<R: Rng>(
+    mut game: Game,
+    mut bot: impl FnMut(&Game, Player, &mut R) -> Vec<Move>,
+    rng: &mut R,
+) -> Game {
+    while let Some(player) = game.game_state.next_player() {
+        let made = bot(&game, player, rng)
+            .into_iter()
+            .any(|mov| game.make_move(player, mov).is_ok());
+        if !made {
+            game.game_state = GameState::Abandoned;
+        }
+    }
+    game
+}
+
+/// Moves the bot would like to make, most preferred first. The last move,
+/// playing the first card, is always allowed.
+pub fn candidate_moves<R: Rng>(game: &Game, player: Player, rng: &mut R) -> Vec<Move> {
+    let own = knowledge(&game.hands[player]);
+    let mut moves = vec![];
+
+    // Play a card that is known to be playable.
+    let mut playable: Vec<_> = own
+        .iter()
+        .enumerate()
+        .filter(|(_, know)| is_surely_playable(game, know))
+        .map(|(idx, _)| CardIdx(idx + 1))
+        .collect();
+    playable.shuffle(rng);
+    moves.extend(playable.into_iter().map(|card_idx| Move::Play {
+        card_idx,
+        card_id: None,
+    }));
+
+    // Hint a playable card of another player that does not know it yet.
+    if game.hints > 0 {
+        let mut hints = vec![];
+        for (hinted_player, hand) in game.hands.iter().enumerate() {
+            let Hand::Visible(cards) = hand else {
+                continue;
+            };
+            if hinted_player == player {
+                continue;
+            }
+            for CardWithKnowledge(card, know) in cards {
+                if game.played.next(card.c) != Some(card.v) || is_surely_playable(game, know) {
+                    continue;
+                }
+                hints.push((hinted_player, Hint::ValueHint(card.v)));
+                if card.c.is_hintable() {
+                    hints.push((hinted_player, Hint::ColorHint(card.c)));
+                }
+            }
+        }
+        hints.shuffle(rng);
+        moves.extend(hints.into_iter().map(|(hinted_player, hint)| Move::Hint {
+            hinted_player,
+            hint,
+        }));
+    }
+
+    // Discard the oldest card without hints, or else the oldest card.
+    let oldest = own
+        .iter()
+        .enumerate()
+        .filter(|(_, know)| !know.is_hinted())
+        .min_by_key(|(_, know)| know.picked_up)
+        .map_or(0, |(idx, _)| idx);
+    moves.push(Move::Discard {
+        card_idx: CardIdx(oldest + 1),
+        force: true,
+        card_id: None,
+    });
+
+    // At the maximum number of hints, any hint at all.
+    let next = (player + 1) % game.players.len();
+    moves.extend((1..=MAX_VALUE).map(|v| Move::Hint {
+        hinted_player: next,
+        hint: Hint::ValueHint(v),
+    }));
+
+    moves.push(Move::Play {
+        card_idx: CardIdx(1),
+        card_id: None,
+    });
+    moves
+}
+
+fn knowledge(hand: &Hand) -> Vec<CardKnowledge> {
+    match hand {
+        Hand::Visible(cards) => cards.iter().map(|ck| ck.1.clone()).collect(),
+        Hand::Hidden(knows) => knows.clone(),
+    }
+}
+
+/// Whether every card the owner thinks this may be is playable.
+fn is_surely_playable(game: &Game, know: &CardKnowledge) -> bool {
+    game.options.variant.colors().into_iter().all(|c| {
+        !know.packed.may_be_color(c)
+            || (1..=MAX_VALUE)
+                .all(|v| !know.packed.may_be_value(v) || game.played.next(c) == Some(v))
+    })
+}
@@ -0,0 +1,54 @@
+//! Estimate the difficulty of the deals of a range of seeds.
+//!
+//! Usage: `classify-seed <seed>[..<end>] [players] [rollouts] [settings]`
+//!
+//! The difficulty thresholds are first calibrated on `CALIBRATION_SEEDS`.
+use hanabi::{
+    analysis::{estimate_deal, DifficultyThresholds, CALIBRATION_SEEDS},
+    GameOptions,
+};
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let Some(seeds) = args.first() else {
+        eprintln!("Usage: classify-seed <seed>[..<end>] [players] [rollouts] [settings]");
+        std::process::exit(1);
+    };
+    let seeds: std::ops::Range<u64> = match seeds.split_once("..") {
+        Some((start, end)) => parse(start, "seed")..parse(end, "seed"),
+        None => {
+            let seed = parse(seeds, "seed");
+            seed..seed + 1
+        }
+    };
+    let num_players = args.get(1).map_or(3, |s| parse(s, "number of players"));
+    let rollouts = args.get(2).map_or(100, |s| parse(s, "number of rollouts"));
+    let mut options: GameOptions = args[3.min(args.len())..]
+        .join(" ")
+        .parse()
+        .unwrap_or_else(|err| exit(err));
+
+    let thresholds =
+        DifficultyThresholds::calibrate(num_players, &options, rollouts, CALIBRATION_SEEDS)
+            .unwrap_or_else(|err| exit(err));
+    println!("{thresholds}");
+    for seed in seeds {
+        options.seed = Some(seed);
+        let estimate =
+            estimate_deal(num_players, &options, rollouts).unwrap_or_else(|err| exit(err));
+        print!(
+            "seed {seed}: {}: {estimate}",
+            estimate.difficulty(&thresholds)
+        );
+    }
+}
+
+fn parse<T: std::str::FromStr>(s: &str, what: &str) -> T {
+    s.parse()
+        .unwrap_or_else(|_| exit(&format!("Could not parse {what}: {s}")))
+}
+
+fn exit(err: &str) -> ! {
+    eprintln!("{err}");
+    std::process::exit(1);
+}
@@ -0,0 +1,46 @@
+//! Let bots play the deals of a range of seeds and report how they did.
+//!
+//! Usage: `hanabi-sim <seed>[..<end>] [bot,bot,...] [settings]`
+//!
+//! There is one bot per player, `baseline` or `search`; the default is three
+//! baseline bots. The variant and rules are given by the settings, as for a
+//! new room.
+use hanabi::{analysis::simulate_bots, bot::BotKind, GameOptions};
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let Some(seeds) = args.first() else {
+        eprintln!("Usage: hanabi-sim <seed>[..<end>] [bot,bot,...] [settings]");
+        std::process::exit(1);
+    };
+    let seeds: std::ops::Range<u64> = match seeds.split_once("..") {
+        Some((start, end)) => parse(start, "seed")..parse(end, "seed"),
+        None => {
+            let seed = parse(seeds, "seed");
+            seed..seed + 1
+        }
+    };
+    let bots: Vec<BotKind> = args
+        .get(1)
+        .map_or("baseline,baseline,baseline", |s| s)
+        .split(',')
+        .map(|bot| parse(bot, "bot"))
+        .collect();
+    let options: GameOptions = args[2.min(args.len())..]
+        .join(" ")
+        .parse()
+        .unwrap_or_else(|err| exit(err));
+
+    let report = simulate_bots(&options, &bots, seeds).unwrap_or_else(|err| exit(err));
+    print!("{report}");
+}
+
+fn parse<T: std::str::FromStr>(s: &str, what: &str) -> T {
+    s.parse()
+        .unwrap_or_else(|_| exit(&format!("Could not parse {what}: {s}")))
+}
+
+fn exit(err: &str) -> ! {
+    eprintln!("{err}");
+    std::process::exit(1);
+}
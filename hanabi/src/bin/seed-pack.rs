@@ -0,0 +1,64 @@
+//! Collect seeds of a given difficulty into a pack file.
+//!
+//! Usage: `seed-pack <count> <easy|medium|hard> [players] [rollouts] [settings]`
+//!
+//! The difficulty thresholds are first calibrated on `CALIBRATION_SEEDS`.
+//! Seeds are then tried from 0 upwards. The pack is written to stdout as JSON.
+use hanabi::{
+    analysis::{estimate_deal, Difficulty, DifficultyThresholds, SeedPack, CALIBRATION_SEEDS},
+    GameOptions,
+};
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.len() < 2 {
+        eprintln!("Usage: seed-pack <count> <easy|medium|hard> [players] [rollouts] [settings]");
+        std::process::exit(1);
+    }
+    let count: usize = parse(&args[0], "count");
+    let difficulty: Difficulty = parse(&args[1], "difficulty");
+    let num_players = args.get(2).map_or(3, |s| parse(s, "number of players"));
+    let rollouts = args.get(3).map_or(100, |s| parse(s, "number of rollouts"));
+    let mut options: GameOptions = args[4.min(args.len())..]
+        .join(" ")
+        .parse()
+        .unwrap_or_else(|err| exit(err));
+
+    let thresholds =
+        DifficultyThresholds::calibrate(num_players, &options, rollouts, CALIBRATION_SEEDS)
+            .unwrap_or_else(|err| exit(err));
+    eprintln!("{thresholds}");
+    let mut pack = SeedPack {
+        num_players,
+        options: GameOptions {
+            seed: None,
+            ..options.clone()
+        },
+        difficulty,
+        thresholds,
+        seeds: vec![],
+    };
+    for seed in 0.. {
+        if pack.seeds.len() == count {
+            break;
+        }
+        options.seed = Some(seed);
+        let estimate =
+            estimate_deal(num_players, &options, rollouts).unwrap_or_else(|err| exit(err));
+        if estimate.difficulty(&thresholds) == difficulty {
+            pack.seeds.push(seed);
+            eprintln!("{}/{count}: seed {seed}", pack.seeds.len());
+        }
+    }
+    println!("{}", serde_json::to_string_pretty(&pack).unwrap());
+}
+
+fn parse<T: std::str::FromStr>(s: &str, what: &str) -> T {
+    s.parse()
+        .unwrap_or_else(|_| exit(&format!("Could not parse {what}: {s}")))
+}
+
+fn exit(err: &str) -> ! {
+    eprintln!("{err}");
+    std::process::exit(1);
+}
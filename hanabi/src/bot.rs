@@ -0,0 +1,160 @@
+//! Computer players, which choose moves from the view of their own seat.
+use rand::{rngs::StdRng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    analysis::{candidate_moves, rollout},
+    Game, Move, Player,
+};
+
+pub trait Bot {
+    /// The move of `player` in `view`, the game as seen from their seat.
+    /// The move must be allowed.
+    fn choose_move(&mut self, view: &Game, player: Player) -> Move;
+}
+
+/// The bots that can be chosen by name, e.g. to compare them with `hanabi-sim`.
+#[derive(
+    Debug,
+    Serialize,
+    Deserialize,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    strum_macros::Display,
+    strum_macros::EnumString,
+)]
+#[strum(ascii_case_insensitive, serialize_all = "kebab-case")]
+pub enum BotKind {
+    Baseline,
+    Search,
+}
+
+impl BotKind {
+    /// A bot of this kind whose random choices follow the seed.
+    pub fn new_bot(self, seed: u64) -> Box<dyn Bot> {
+        match self {
+            BotKind::Baseline => Box::new(BaselineBot::new(seed)),
+            BotKind::Search => Box::new(SearchBot::new(seed)),
+        }
+    }
+}
+
+/// Plays cards it knows to be playable, hints playable cards of other
+/// players, and otherwise discards its oldest unhinted card.
+pub struct BaselineBot {
+    rng: StdRng,
+}
+
+impl BaselineBot {
+    /// A bot whose random choices follow the seed.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+#[cfg(feature = "entropy")]
+impl Default for BaselineBot {
+    fn default() -> Self {
+        Self {
+            rng: StdRng::from_entropy(),
+        }
+    }
+}
+
+impl Bot for BaselineBot {
+    fn choose_move(&mut self, view: &Game, player: Player) -> Move {
+        candidate_moves(view, player, &mut self.rng)
+            .into_iter()
+            .find(|mov| view.check_move(player, mov).is_ok())
+            .expect("players who are skipped cannot move, so some move is allowed")
+    }
+}
+
+/// Tries the first few moves the baseline bot would consider on deals of the
+/// cards it cannot see that fit what it knows, plays each deal out with
+/// baseline bots, and makes the move with the best average score.
+pub struct SearchBot {
+    rng: StdRng,
+    /// Number of deals each move is tried on.
+    pub deals: usize,
+    /// Number of moves that are tried.
+    pub candidates: usize,
+}
+
+impl SearchBot {
+    /// A bot whose random choices follow the seed.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+            deals: 20,
+            candidates: 4,
+        }
+    }
+}
+
+impl SearchBot {
+    /// The allowed moves the bot considers, most preferred first.
+    pub fn candidates(&mut self, view: &Game, player: Player) -> Vec<Move> {
+        candidate_moves(view, player, &mut self.rng)
+            .into_iter()
+            .filter(|mov| view.check_move(player, mov).is_ok())
+            .take(self.candidates)
+            .collect()
+    }
+
+    /// The average score after each of the `moves` of `player`, over deals
+    /// that fit what they know in `view`. `None` when no deal fits.
+    pub fn expected_scores(
+        &mut self,
+        view: &Game,
+        player: Player,
+        moves: &[Move],
+    ) -> Option<Vec<f64>> {
+        let deals: Vec<Game> = (0..self.deals)
+            .filter_map(|_| view.sample_deal(player, &mut self.rng))
+            .collect();
+        if deals.is_empty() {
+            return None;
+        }
+        // Every move is tried on the same deals, so that luck of the draw
+        // does not decide between them.
+        let mut expected_score = |mov: &Move| -> f64 {
+            let total: usize = deals
+                .iter()
+                .map(|deal| {
+                    let mut game = deal.clone();
+                    if game.make_move(player, mov.clone()).is_err() {
+                        return 0;
+                    }
+                    rollout(game, candidate_moves, &mut self.rng).played.score()
+                })
+                .sum();
+            total as f64 / deals.len() as f64
+        };
+        Some(moves.iter().map(&mut expected_score).collect())
+    }
+}
+
+impl Bot for SearchBot {
+    fn choose_move(&mut self, view: &Game, player: Player) -> Move {
+        let mut moves = self.candidates(view, player);
+        if moves.len() > 1 {
+            if let Some(scores) = self.expected_scores(view, player, &moves) {
+                // The first best move, so ties go to the baseline's preference.
+                let best = (0..moves.len())
+                    .rev()
+                    .max_by(|&i, &j| scores[i].total_cmp(&scores[j]))
+                    .unwrap();
+                return moves.swap_remove(best);
+            }
+        }
+        moves
+            .into_iter()
+            .next()
+            .expect("players who are skipped cannot move, so some move is allowed")
+    }
+}
@@ -0,0 +1,295 @@
+//! Games in the JSON format of hanab.live, so that games played here can be
+//! analyzed with the tools of its community and vice versa.
+//!
+//! A game is given by its players in seat order, the deck in dealing order,
+//! the actions and the options. Plays and discards name their card by its
+//! position in the deck, hints name the hinted player by seat.
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Card, CardId, CardIdx, Color, ColorHint, Deck, EndgameRule, Game, GameOptions, GameVariant,
+    Hint, HintEconomy, Move, StackOrder, ValueHint, MAX_HINTS, MAX_LIVES, MAX_VALUE,
+};
+
+/// Suits in the order of hanab.live, which calls white purple.
+const SUITS: [Color; 5] = [
+    Color::Red,
+    Color::Yellow,
+    Color::Green,
+    Color::Blue,
+    Color::White,
+];
+
+const PLAY: usize = 0;
+const DISCARD: usize = 1;
+const COLOR_CLUE: usize = 2;
+const RANK_CLUE: usize = 3;
+const GAME_OVER: usize = 4;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HanabLiveGame {
+    pub players: Vec<String>,
+    pub deck: Vec<HanabLiveCard>,
+    pub actions: Vec<HanabLiveAction>,
+    #[serde(default)]
+    pub options: HanabLiveOptions,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct HanabLiveCard {
+    pub suit_index: usize,
+    pub rank: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HanabLiveAction {
+    /// 0 for plays, 1 for discards, 2 for color clues, 3 for rank clues and
+    /// 4 for the end of the game.
+    #[serde(rename = "type")]
+    pub kind: usize,
+    /// The position of the card in the deck, or the seat of the clued player.
+    pub target: usize,
+    /// The index of the clued color, or the clued rank.
+    #[serde(default)]
+    pub value: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct HanabLiveOptions {
+    /// Name of the variant; `None` is "No Variant".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub variant: Option<String>,
+    #[serde(default)]
+    pub starting_player: usize,
+    #[serde(default)]
+    pub one_extra_card: bool,
+    #[serde(default)]
+    pub one_less_card: bool,
+}
+
+/// Name of the variant on hanab.live and its suits in the order used there.
+fn variant_suits(variant: GameVariant) -> Result<(&'static str, Vec<Color>), &'static str> {
+    let (name, extra) = match variant {
+        GameVariant::Base => ("No Variant", None),
+        GameVariant::Multi => ("Rainbow (6 Suits)", Some(Color::Multi)),
+        GameVariant::MultiHard => ("Dark Rainbow (6 Suits)", Some(Color::Multi)),
+        GameVariant::Purple => ("6 Suits", Some(Color::Purple)),
+        // Black can be clued in "Black (6 Suits)" of hanab.live, so its games
+        // cannot be played here.
+        GameVariant::Dark | GameVariant::MultiDark | GameVariant::Gray => {
+            return Err("This variant is not available on hanab.live.")
+        }
+    };
+    Ok((name, SUITS.into_iter().chain(extra).collect()))
+}
+
+impl Game {
+    /// The game in the JSON format of hanab.live, once its deal is known.
+    pub fn export_hanab_live(&self) -> Result<String, &'static str> {
+        let (name, suits) = variant_suits(self.options.variant)?;
+        let options = &self.options;
+        if options.hint_economy != HintEconomy::default()
+            || options.stacks != StackOrder::default()
+            || options.max_hints() != MAX_HINTS
+            || options.starting_hints() != MAX_HINTS
+            || options.starting_lives() != MAX_LIVES
+            || options.endgame != EndgameRule::default()
+            || options.clue_starved
+        {
+            return Err("These options are not available on hanab.live.");
+        }
+        let usual_cards = GameOptions {
+            variant: options.variant,
+            ..Default::default()
+        }
+        .cards_per_player(self.players.len())?;
+        if self.cards_per_player.abs_diff(usual_cards) > 1 {
+            return Err("hanab.live only deals one card more or less than usual.");
+        }
+
+        // Clue colors are numbered like the suits: the suits that cannot be
+        // clued come last.
+        let suit_index = |c: Color| {
+            suits
+                .iter()
+                .position(|&s| s == c)
+                .ok_or("This color is not available on hanab.live.")
+        };
+        let replay = self.to_replay()?;
+        if replay.hidden > 0 {
+            return Err("The deck of this game is hidden.");
+        }
+        let deck = replay
+            .deck
+            .iter()
+            .map(|card| {
+                Ok(HanabLiveCard {
+                    suit_index: suit_index(card.c)?,
+                    rank: card.v,
+                })
+            })
+            .collect::<Result<_, &'static str>>()?;
+        // Find the position in the deck of each played card by replaying.
+        let mut game = replay.game_at(0)?;
+        let mut actions = vec![];
+        for (player, mov) in &replay.moves {
+            let card_order = |card_idx| {
+                let know = game.hands[*player].knowledge(card_idx).unwrap();
+                replay.deck.len() - 1 - know.id.0
+            };
+            let action = match mov {
+                Move::Play { card_idx, .. } => HanabLiveAction {
+                    kind: PLAY,
+                    target: card_order(*card_idx),
+                    value: 0,
+                },
+                Move::Discard { card_idx, .. } => HanabLiveAction {
+                    kind: DISCARD,
+                    target: card_order(*card_idx),
+                    value: 0,
+                },
+                Move::Hint {
+                    hinted_player,
+                    hint: ColorHint(c),
+                } => HanabLiveAction {
+                    kind: COLOR_CLUE,
+                    target: *hinted_player,
+                    value: suit_index(*c)?,
+                },
+                Move::Hint {
+                    hinted_player,
+                    hint: ValueHint(v),
+                } => HanabLiveAction {
+                    kind: RANK_CLUE,
+                    target: *hinted_player,
+                    value: *v,
+                },
+                Move::HintOtherPlayer { .. } => unreachable!("The move log resolves players."),
+                Move::Pass => return Err("hanab.live does not have passed turns."),
+            };
+            actions.push(action);
+            game.make_move(*player, mov.clone())?;
+        }
+
+        let hanab_live = HanabLiveGame {
+            players: self.players.clone(),
+            deck,
+            actions,
+            options: HanabLiveOptions {
+                variant: (self.options.variant != GameVariant::Base).then(|| name.to_string()),
+                starting_player: self.start_player,
+                one_extra_card: self.cards_per_player == usual_cards + 1,
+                one_less_card: self.cards_per_player + 1 == usual_cards,
+            },
+        };
+        Ok(serde_json::to_string(&hanab_live).unwrap())
+    }
+
+    /// Play the game given in the JSON format of hanab.live up to its last
+    /// action.
+    pub fn import_hanab_live(json: &str) -> Result<Self, &'static str> {
+        let hanab_live: HanabLiveGame =
+            serde_json::from_str(json).map_err(|_| "Could not parse the hanab.live game.")?;
+        let name = hanab_live
+            .options
+            .variant
+            .as_deref()
+            .unwrap_or("No Variant");
+        let variant = [
+            GameVariant::Base,
+            GameVariant::Multi,
+            GameVariant::MultiHard,
+            GameVariant::Purple,
+        ]
+        .into_iter()
+        .find(|&variant| variant_suits(variant).is_ok_and(|(n, _)| n == name))
+        .ok_or("This variant of hanab.live is not supported.")?;
+        let (_, suits) = variant_suits(variant)?;
+
+        let mut options = GameOptions {
+            variant,
+            ..Default::default()
+        };
+        let num_players = hanab_live.players.len();
+        let usual_cards = options.cards_per_player(num_players)?;
+        options.cards_per_player = match (
+            hanab_live.options.one_extra_card,
+            hanab_live.options.one_less_card,
+        ) {
+            (false, false) => None,
+            (true, false) => Some(usual_cards + 1),
+            (false, true) => Some(usual_cards - 1),
+            (true, true) => return Err("A game cannot deal both more and fewer cards."),
+        };
+        options.cards_per_player(num_players)?;
+        if hanab_live.options.starting_player >= num_players {
+            return Err("The starting player is not in the game.");
+        }
+
+        let cards = hanab_live
+            .deck
+            .iter()
+            .map(|card| {
+                let c = *suits
+                    .get(card.suit_index)
+                    .ok_or("Unknown suit in the deck.")?;
+                if !(1..=MAX_VALUE).contains(&card.rank) {
+                    return Err("Unknown rank in the deck.");
+                }
+                Ok(Card { c, v: card.rank })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Deck::check(&options, &cards)?;
+        let deck_len = cards.len();
+        let mut game = Game::deal(
+            hanab_live.players,
+            hanab_live.options.starting_player,
+            options,
+            None,
+            // Cards are drawn from the back.
+            Deck::Visible(cards.into_iter().rev().collect()),
+        );
+
+        for action in hanab_live.actions {
+            if action.kind == GAME_OVER {
+                break;
+            }
+            let player = game
+                .game_state
+                .next_player()
+                .ok_or("The game ended before its last action.")?;
+            let card_id = || {
+                (action.target < deck_len)
+                    .then(|| CardId(deck_len - 1 - action.target))
+                    .ok_or("Unknown card in an action.")
+            };
+            if action.target >= num_players && matches!(action.kind, COLOR_CLUE | RANK_CLUE) {
+                return Err("The clued player is not in the game.");
+            }
+            let hint = |hint: Hint| Move::Hint {
+                hinted_player: action.target,
+                hint,
+            };
+            let mov = match action.kind {
+                PLAY => Move::Play {
+                    card_idx: CardIdx(1),
+                    card_id: Some(card_id()?),
+                },
+                DISCARD => Move::Discard {
+                    card_idx: CardIdx(1),
+                    force: true,
+                    card_id: Some(card_id()?),
+                },
+                COLOR_CLUE => hint(ColorHint(
+                    *suits.get(action.value).ok_or("Unknown color in a clue.")?,
+                )),
+                RANK_CLUE => hint(ValueHint(action.value)),
+                _ => return Err("Unknown type of action."),
+            };
+            game.make_move(player, mov)?;
+        }
+        Ok(game)
+    }
+}
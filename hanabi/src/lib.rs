@@ -1,12 +1,28 @@
+pub mod analysis;
+pub mod bot;
+pub mod hanab_live;
+pub mod replay;
+pub mod schema;
+pub mod theme;
+pub mod tutorial;
+
 use std::{
+    cell::Cell,
     fmt::{Debug, Display},
     ops::{Index, IndexMut},
     str::FromStr,
 };
 
-use owo_colors::{OwoColorize, Style};
-use rand::{seq::SliceRandom, thread_rng, Rng};
+use bot::{BaselineBot, Bot, SearchBot};
+#[cfg(feature = "entropy")]
+use rand::thread_rng;
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
 use serde::{Deserialize, Serialize};
+use theme::Theme;
+use turnbased_game_server::{
+    style::{OwoColorize, Style},
+    types::{Accessibility, SpectatorView, TimeoutMove},
+};
 
 const MAX_HINTS: usize = 8;
 const MAX_LIVES: usize = 3;
@@ -14,73 +30,228 @@ const MAX_LIVES: usize = 3;
 pub type Value = usize;
 const MAX_VALUE: Value = 5;
 
-#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, strum_macros::Display)]
-#[strum(ascii_case_insensitive)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
 pub enum Color {
     Blue = 0,
     Green = 1,
     Red = 2,
     White = 3,
     Yellow = 4,
+    /// The rainbow suit, touched by every color hint.
     Multi = 5,
+    /// The dark suit: a single copy of each value, never touched by color hints.
+    Black = 6,
+    /// A sixth regular suit, touched only by purple hints.
+    Purple = 7,
+    /// The white-ish suit: a regular suit that no color hint touches.
+    Gray = 8,
 }
-const MAX_COLORS: usize = 6;
-const COLORS: [Color; 6] = [
+const MAX_COLORS: usize = 9;
+const COLORS: [Color; MAX_COLORS] = [
     Color::Blue,
     Color::Green,
     Color::Red,
     Color::White,
     Color::Yellow,
     Color::Multi,
+    Color::Black,
+    Color::Purple,
+    Color::Gray,
 ];
 const COLORWIDTH: usize = 6 + 1;
 
-impl FromStr for Color {
-    type Err = &'static str;
+thread_local! {
+    /// Preferences of the viewer of the game that is currently being displayed.
+    static ACCESSIBILITY: Cell<Accessibility> = Cell::new(Accessibility::default());
+}
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "" => Err("Color must not be empty"),
-            s if "blue".starts_with(s) => Ok(Color::Blue),
-            s if "green".starts_with(s) => Ok(Color::Green),
-            s if "red".starts_with(s) => Ok(Color::Red),
-            s if "white".starts_with(s) => Ok(Color::White),
-            s if "yellow".starts_with(s) => Ok(Color::Yellow),
-            s if "multi".starts_with(s) => Ok(Color::Multi),
-            _ => Err("Unknown color"),
+/// Display with the given accessibility preferences while running `f`.
+fn with_accessibility<T>(accessibility: Accessibility, f: impl FnOnce() -> T) -> T {
+    let old = ACCESSIBILITY.with(|cell| cell.replace(accessibility));
+    let result = f();
+    ACCESSIBILITY.with(|cell| cell.set(old));
+    result
+}
+
+/// Custom names of the colors of a game, used for display and for parsing
+/// hints. Colors without a custom name keep their default name.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct ColorNames(Vec<(Color, String)>);
+
+impl ColorNames {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(Color, String)> {
+        self.0.iter()
+    }
+
+    /// The name of the color in this game.
+    pub fn name(&self, c: Color) -> &str {
+        match self.0.iter().find(|(color, _)| *color == c) {
+            Some((_, name)) => name,
+            None => c.default_name(),
+        }
+    }
+
+    /// Parse a color by a prefix of its custom name or, failing that, of its
+    /// default name.
+    pub fn parse(&self, s: &str) -> Result<Color, &'static str> {
+        if s.is_empty() {
+            return Err("Color must not be empty");
+        }
+        let s = s.to_lowercase();
+        let custom = self
+            .0
+            .iter()
+            .find(|(_, name)| name.to_lowercase().starts_with(&s))
+            .map(|(c, _)| *c);
+        if let Some(c) = custom {
+            return Ok(c);
+        }
+        COLORS
+            .into_iter()
+            .find(|c| c.default_name().to_lowercase().starts_with(&s))
+            .ok_or("Unknown color")
+    }
+
+    /// Shortest prefix of the name that is parsed as the color.
+    pub fn abbreviation(&self, c: Color) -> String {
+        let name = self.name(c).to_lowercase();
+        name.char_indices()
+            .map(|(idx, ch)| &name[..idx + ch.len_utf8()])
+            .find(|prefix| self.parse(prefix) == Ok(c))
+            .unwrap_or(&name)
+            .to_string()
+    }
+
+    /// Display `value` with these names.
+    pub fn show<'a, T>(&'a self, value: &'a T) -> Named<'a, T> {
+        Named { value, names: self }
+    }
+
+    /// Check that the renamed colors are in the game and that every color of
+    /// the game is still parsed as itself by its full name: no name may be a
+    /// prefix of a name of another color.
+    fn check(&self, variant: GameVariant) -> Result<(), &'static str> {
+        let colors = variant.colors();
+        for (idx, (c, name)) in self.0.iter().enumerate() {
+            let len = name.chars().count();
+            if len == 0 || len >= COLORWIDTH || !name.chars().all(char::is_alphabetic) {
+                return Err("Color names must consist of 1 to 6 letters");
+            }
+            if self.0[..idx].iter().any(|(other, _)| other == c) {
+                return Err("Each color can be renamed only once");
+            }
+            if !colors.contains(c) {
+                return Err("Only colors of the variant can be renamed");
+            }
+            let name = name.to_lowercase();
+            for other in colors.iter().filter(|&other| other != c) {
+                let other_names = [Some(other.default_name()), self.custom_name(*other)];
+                for other_name in other_names.into_iter().flatten() {
+                    let other_name = other_name.to_lowercase();
+                    if name.starts_with(&other_name) || other_name.starts_with(&name) {
+                        return Err("Color names must not be prefixes of each other");
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn custom_name(&self, c: Color) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(color, _)| *color == c)
+            .map(|(_, name)| name.as_str())
+    }
+}
+
+/// A value displayed with the custom color names of its game.
+pub struct Named<'a, T> {
+    value: &'a T,
+    names: &'a ColorNames,
+}
+
+impl Display for Named<'_, Color> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = self.names.name(*self.value);
+        if ACCESSIBILITY.with(Cell::get).symbols {
+            f.pad(&format!("{}{name}", self.value.symbol()))
+        } else {
+            f.pad(name)
         }
     }
 }
 
 impl Color {
-    fn to_style(&self) -> Style {
+    /// The name of the color when the game does not rename it.
+    pub fn default_name(self) -> &'static str {
+        match self {
+            Color::Blue => "Blue",
+            Color::Green => "Green",
+            Color::Red => "Red",
+            Color::White => "White",
+            Color::Yellow => "Yellow",
+            Color::Multi => "Multi",
+            Color::Black => "Black",
+            Color::Purple => "Purple",
+            Color::Gray => "Gray",
+        }
+    }
+    fn to_style(self) -> Style {
+        Theme::current().color(self)
+    }
+    /// Shape shown before the name for players who cannot tell the colors apart.
+    fn symbol(self) -> char {
         match self {
-            Color::Blue => Style::new().bright_blue(),
-            Color::Green => Style::new().green(),
-            Color::Red => Style::new().red(),
-            Color::White => Style::new().white(),
-            Color::Yellow => Style::new().yellow(),
-            Color::Multi => Style::new().purple(),
+            Color::Blue => '◆',
+            Color::Green => '♣',
+            Color::Red => '♥',
+            Color::White => '○',
+            Color::Yellow => '★',
+            Color::Multi => '✸',
+            Color::Black => '●',
+            Color::Purple => '♠',
+            Color::Gray => '◌',
         }
     }
-    fn to_styled_string(&self) -> String {
-        self.to_string().style(self.to_style()).to_string()
+    fn to_styled_string(self) -> String {
+        self.default_name().style(self.to_style()).to_string()
+    }
+    /// Whether players may give a hint for this color.
+    pub fn is_hintable(self) -> bool {
+        !matches!(self, Color::Multi | Color::Black | Color::Gray)
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Clone)]
 pub struct ColorArray<T>([T; MAX_COLORS]);
+
+/// Games saved before a color existed do not have it, so it is impossible.
+impl<'de> Deserialize<'de> for ColorArray<KnowledgeState> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let states = Vec::<KnowledgeState>::deserialize(deserializer)?;
+        if states.len() > MAX_COLORS {
+            return Err(serde::de::Error::invalid_length(
+                states.len(),
+                &"at most one state per color",
+            ));
+        }
+        let mut array = [KnowledgeState::Impossible; MAX_COLORS];
+        array[..states.len()].copy_from_slice(&states);
+        Ok(ColorArray(array))
+    }
+}
 impl<T> ColorArray<T> {
     pub fn find_eq(&self, t: T) -> Option<Color>
     where
         T: Eq + Copy,
     {
-        for c in COLORS {
-            if self[c] == t {
-                return Some(c);
-            }
-        }
-        None
+        COLORS.into_iter().find(|&c| self[c] == t)
     }
     pub fn count_eq(&self, t: T) -> usize
     where
@@ -110,11 +281,12 @@ pub struct Card {
 }
 const CARDWIDTH: usize = COLORWIDTH + 2;
 
-impl Display for Card {
+impl Display for Named<'_, Card> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        self.c.to_style().fmt_prefix(f)?;
-        f.pad(&format!("{} {}", self.c, self.v))?;
-        self.c.to_style().fmt_suffix(f)
+        let Card { c, v } = self.value;
+        c.to_style().fmt_prefix(f)?;
+        f.pad(&format!("{} {v}", self.names.show(c)))?;
+        c.to_style().fmt_suffix(f)
     }
 }
 
@@ -125,32 +297,67 @@ enum Deck {
 }
 
 impl Deck {
-    fn count(variant: GameVariant, c: Color, v: Value) -> usize {
-        if c == Color::Multi && variant == GameVariant::MultiHard {
+    fn count(options: &GameOptions, c: Color, v: Value) -> usize {
+        if c == Color::Black || (c == Color::Multi && options.variant == GameVariant::MultiHard) {
             return 1;
         }
+        // The first card of a stack is the most common one.
+        let v = match options.stacks {
+            StackOrder::Ascending => v,
+            StackOrder::Descending => MAX_VALUE + 1 - v,
+        };
         match v {
             1 => 3,
-            2 | 3 | 4 => 2,
+            2..=4 => 2,
             5 => 1,
             _ => panic!(),
         }
     }
-    fn new(variant: GameVariant) -> Self {
+    /// Number of cards in a full deck.
+    fn size(options: &GameOptions) -> usize {
+        options
+            .variant
+            .colors()
+            .into_iter()
+            .flat_map(|c| (1..=MAX_VALUE).map(move |v| Deck::count(options, c, v)))
+            .sum()
+    }
+    fn new(options: &GameOptions, rng: &mut impl Rng) -> Self {
         let mut cards = vec![];
-        for c in variant.colors() {
+        for c in options.variant.colors() {
             for v in 1..=MAX_VALUE {
-                for _ in 0..Deck::count(variant, c, v) {
+                for _ in 0..Deck::count(options, c, v) {
                     cards.push(Card { c, v });
                 }
             }
         }
-        cards.shuffle(&mut thread_rng());
+        cards.shuffle(rng);
         Deck::Visible(cards)
     }
+    /// Check that `cards` are exactly the cards of the variant, in any order.
+    fn check(options: &GameOptions, cards: &[Card]) -> Result<(), &'static str> {
+        let key = |card: &Card| (card.c as usize, card.v);
+        let Deck::Visible(mut expected) = Deck::new(options, &mut StdRng::seed_from_u64(0)) else {
+            unreachable!()
+        };
+        expected.sort_by_key(key);
+        let mut sorted: Vec<_> = cards.iter().map(key).collect();
+        sorted.sort();
+        if sorted.into_iter().ne(expected.iter().map(key)) {
+            return Err("The deck does not contain exactly the cards of the variant.");
+        }
+        Ok(())
+    }
+    /// Draw the next card. From a hidden deck the card is unknown, so it
+    /// only gets shorter.
     fn take(&mut self) -> Option<Card> {
-        let Deck::Visible(cards) = self else { panic!() };
-        cards.pop()
+        match self {
+            Deck::Visible(cards) => cards.pop(),
+            Deck::Hidden(len) => {
+                *len = len.saturating_sub(1);
+                None
+            }
+        }
     }
     fn is_empty(&self) -> bool {
         match self {
@@ -171,38 +378,60 @@ impl Deck {
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct Played(Vec<usize>);
+pub struct Played {
+    /// Number of cards played per color.
+    heights: Vec<usize>,
+    /// Order in which the values of each color must be played.
+    sequences: Vec<[Value; MAX_VALUE]>,
+}
 
 impl Index<Color> for Played {
     type Output = usize;
 
     fn index(&self, c: Color) -> &Self::Output {
-        &self.0[c as usize]
-    }
-}
-
-impl IndexMut<Color> for Played {
-    fn index_mut(&mut self, c: Color) -> &mut Self::Output {
-        &mut self.0[c as usize]
+        &self.heights[c as usize]
     }
 }
 
 impl Played {
-    fn new(variant: GameVariant) -> Self {
-        Played(vec![0; variant.num_colors()])
+    fn new(options: &GameOptions) -> Self {
+        // Indexed by color, so colors outside the variant get an empty stack.
+        Played {
+            heights: vec![0; MAX_COLORS],
+            sequences: vec![options.stacks.sequence(); MAX_COLORS],
+        }
     }
 
     pub fn score(&self) -> usize {
-        self.0.iter().sum()
+        self.heights.iter().sum()
+    }
+
+    /// The value that can be played next on the stack, if any.
+    pub fn next(&self, c: Color) -> Option<Value> {
+        self.sequences[c as usize].get(self[c]).copied()
+    }
+
+    /// The value on top of the stack, if any.
+    pub fn top(&self, c: Color) -> Option<Value> {
+        self[c]
+            .checked_sub(1)
+            .map(|height| self.sequences[c as usize][height])
+    }
+
+    pub fn is_played(&self, c: Color, v: Value) -> bool {
+        self.sequences[c as usize][..self[c]].contains(&v)
+    }
+
+    pub fn is_complete(&self, c: Color) -> bool {
+        self[c] == MAX_VALUE
     }
 
     /// Returns the card
     fn play(&mut self, card: Card) -> Result<Card, Card> {
-        let cur_cnt = &mut self[card.c];
-        if card.v != *cur_cnt + 1 {
+        if self.next(card.c) != Some(card.v) {
             Err(card)
         } else {
-            *cur_cnt += 1;
+            self.heights[card.c as usize] += 1;
             Ok(card)
         }
     }
@@ -216,17 +445,16 @@ pub enum KnowledgeState {
     Impossible,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub enum Turn {
-    Start,
-    Turn(usize),
-}
+/// Number of moves made so far. Turn 0 is the start of the game, and the
+/// move of turn `i` is entry `i - 1` of the move log.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TurnIndex(pub usize);
 
-impl Display for Turn {
+impl Display for TurnIndex {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Turn::Start => write!(f, "start of game"),
-            Turn::Turn(turn) => write!(f, "turn {turn}"),
+        match self.0 {
+            0 => write!(f, "start of game"),
+            turn => write!(f, "turn {turn}"),
         }
     }
 }
@@ -247,12 +475,55 @@ impl<T: std::fmt::Display> Display for DisplayVec<T> {
     }
 }
 
+/// 64-bit FNV-1a. Unlike `DefaultHasher` it is the same on every platform and
+/// Rust version, so hashes can be stored.
+struct StableHasher(u64);
+
+impl StableHasher {
+    fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+    fn write(&mut self, n: usize) {
+        for byte in (n as u64).to_le_bytes() {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct CardKnowledge {
-    /// NOTE: Indices are 1 lower than values.
-    pub vs: [KnowledgeState; MAX_VALUE],
-    pub cs: ColorArray<KnowledgeState>,
-    pub picked_up: Turn,
+    /// The colors and values the card may still have, serialized as the
+    /// `vs` and `cs` states of each value and color.
+    #[serde(flatten)]
+    pub packed: PackedKnowledge,
+    pub picked_up: TurnIndex,
+    /// Identifies the card, also in views where it is hidden.
+    #[serde(default)]
+    pub id: CardId,
+    /// The hints given to the hand since the card was picked up, oldest first.
+    #[serde(default)]
+    pub hints: Vec<HintRecord>,
+}
+
+/// A hint given to the hand holding a card, and whether it touched the card.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HintRecord {
+    pub turn: TurnIndex,
+    pub hint: Hint,
+    pub touched: bool,
+}
+
+impl Display for Named<'_, HintRecord> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let HintRecord {
+            turn,
+            hint,
+            touched,
+        } = self.value;
+        let how = if *touched { "clued" } else { "not" };
+        write!(f, "{how} {} on {turn}", self.names.show(hint))
+    }
 }
 
 impl Debug for CardKnowledge {
@@ -263,13 +534,13 @@ impl Debug for CardKnowledge {
             DisplayVec(
                 COLORS
                     .iter()
-                    .filter(|c| self.cs[**c] != KnowledgeState::Impossible)
+                    .filter(|c| self.packed.may_be_color(**c))
                     .map(|c| c.to_styled_string())
                     .collect()
             ),
             DisplayVec(
                 (1..=MAX_VALUE)
-                    .filter(|v| self.vs[*v - 1] != KnowledgeState::Impossible)
+                    .filter(|v| self.packed.may_be_value(*v))
                     .collect()
             ),
             self.picked_up
@@ -277,15 +548,26 @@ impl Debug for CardKnowledge {
     }
 }
 
-impl Display for CardKnowledge {
+/// The other color a card may have when it is either that color or Multi,
+/// e.g. after a color hint touched it.
+fn multi_candidate(packed: &PackedKnowledge) -> Option<Color> {
+    if packed.colors.count_ones() != 2 || !packed.may_be_color(Color::Multi) {
+        return None;
+    }
+    COLORS
+        .into_iter()
+        .find(|&c| c != Color::Multi && packed.may_be_color(c))
+}
+
+impl Display for Named<'_, CardKnowledge> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        use KnowledgeState::*;
+        let know = &self.value.packed;
         // c:
         // known:
         // red/.../?
         //
         // multi + one other:
-        // red/... + italics or *
+        // red/... + *
         //
         // else:
         // ?
@@ -293,26 +575,29 @@ impl Display for CardKnowledge {
         // when multi is not `possible`: bold
 
         // Known color?
-        let mut c = self.cs.find_eq(Known);
+        let mut c = know.color();
         // Otherwise, multi-candidate?
-        if c.is_none() && self.cs.count_eq(Possible) == 2 && self.cs[Color::Multi] == Possible {
-            c = self.cs.find_eq(Possible);
+        let candidate = c.is_none().then(|| multi_candidate(know)).flatten();
+        let maybemulti = candidate.is_some();
+        if maybemulti {
+            c = candidate;
         }
 
         // v: 1/2/3/4/5 or ?
-        let v = self.vs.iter().position(|&k| k == Known);
-        let v = match v {
-            Some(v) => b'1' + v as u8,
+        let v = match know.value() {
+            Some(v) => b'0' + v as u8,
             None => b'?',
         } as char;
 
         let (text, mut style) = match (c, v) {
             (None, '?') => ("?".into(), Style::new()),
             (None, _) => (v.to_string(), Style::new()),
-            (Some(c), '?') => (c.to_string(), c.to_style()),
-            (Some(c), _) => (format!("{c} {v}"), c.to_style()),
+            (Some(c), '?') => (self.names.show(&c).to_string(), c.to_style()),
+            (Some(c), _) => (format!("{} {v}", self.names.show(&c)), c.to_style()),
         };
-        if self.cs[Color::Multi] != KnowledgeState::Possible {
+        // Mark 'red or multi' so it can't be mistaken for 'definitely red'.
+        let text = if maybemulti { text + "*" } else { text };
+        if know.color_state(Color::Multi) != KnowledgeState::Possible {
             style = style.bold();
         }
 
@@ -324,28 +609,227 @@ impl Display for CardKnowledge {
 }
 
 impl CardKnowledge {
-    fn new(variant: GameVariant, turn: Turn) -> Self {
-        use KnowledgeState::*;
-        let mut this = Self {
-            vs: [Possible; MAX_VALUE],
-            cs: ColorArray([Possible; MAX_COLORS]),
+    /// Whether hints, or the cards its owner can see, narrowed down the
+    /// color or value of the card.
+    pub fn is_hinted(&self) -> bool {
+        self.packed.value().is_some()
+            || self.packed.color().is_some()
+            || multi_candidate(&self.packed).is_some()
+    }
+
+    /// Probability of each color and value of the card, given the hints and
+    /// the cards its owner can see. All zero when no unseen card fits.
+    pub fn probabilities(&self, visible: &VisibleCards) -> [[f64; MAX_VALUE]; MAX_COLORS] {
+        let mut probabilities = [[0.; MAX_VALUE]; MAX_COLORS];
+        let mut total = 0;
+        for c in COLORS.into_iter().filter(|&c| self.packed.may_be_color(c)) {
+            for v in (1..=MAX_VALUE).filter(|&v| self.packed.may_be_value(v)) {
+                let unseen = visible.unseen(c, v);
+                probabilities[c as usize][v - 1] = unseen as f64;
+                total += unseen;
+            }
+        }
+        if total > 0 {
+            for p in probabilities.iter_mut().flatten() {
+                *p /= total as f64;
+            }
+        }
+        probabilities
+    }
+
+    /// The most likely color and value of the card, with its probability.
+    pub fn most_likely(&self, visible: &VisibleCards) -> Option<(Color, Value, f64)> {
+        let probabilities = self.probabilities(visible);
+        COLORS
+            .into_iter()
+            .flat_map(|c| (1..=MAX_VALUE).map(move |v| (c, v, probabilities[c as usize][v - 1])))
+            .filter(|&(_, _, p)| p > 0.)
+            .max_by(|a, b| a.2.total_cmp(&b.2))
+    }
+
+    /// Rule out the colors and values of which no copy is left that fits,
+    /// given the number of `unseen` copies of each card. Returns whether
+    /// anything was ruled out.
+    fn deduce(&mut self, unseen: &[[usize; MAX_VALUE]; MAX_COLORS]) -> bool {
+        let old = self.packed;
+        let mut new = PackedKnowledge::default();
+        for c in COLORS {
+            for v in (1..=MAX_VALUE).filter(|&v| old.may_be(c, v)) {
+                if unseen[c as usize][v - 1] > 0 {
+                    new.colors |= PackedKnowledge::color_bit(c);
+                    new.values |= PackedKnowledge::value_bit(v);
+                }
+            }
+        }
+        // Only the card itself may be left; keep what the hints said then.
+        if new.colors == 0 {
+            return false;
+        }
+        self.packed = new;
+        new != old
+    }
+
+    /// What the hints alone say about the card, which everyone knows, unlike
+    /// what its owner deduced from the cards they see.
+    pub fn hinted(&self, variant: GameVariant) -> PackedKnowledge {
+        let mut packed = PackedKnowledge::new(variant);
+        for record in &self.hints {
+            packed.apply_hint(&record.hint, record.touched);
+        }
+        packed
+    }
+
+    fn new(variant: GameVariant, turn: TurnIndex, id: CardId) -> Self {
+        Self {
+            packed: PackedKnowledge::new(variant),
             picked_up: turn,
+            id,
+            hints: vec![],
+        }
+    }
+}
+
+/// The colors and values a card may still have, as bitmasks, which are cheap
+/// to copy and compare, e.g. for bots that search many games. Serialized as
+/// the state of each value and color, in `vs` and `cs`.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[serde(from = "KnowledgeStates", into = "KnowledgeStates")]
+pub struct PackedKnowledge {
+    /// Bit `c as usize` is set when the card may have color `c`.
+    colors: u32,
+    /// Bit `v - 1` is set when the card may have value `v`.
+    values: u32,
+}
+
+impl PackedKnowledge {
+    /// Nothing is known yet, but the colors that are not in the game are
+    /// ruled out.
+    fn new(variant: GameVariant) -> Self {
+        let mut packed = PackedKnowledge {
+            colors: 0,
+            values: (1 << MAX_VALUE) - 1,
         };
-        // Disable Multi possibility if needed.
-        if !variant.has_multi() {
-            this.cs[Color::Multi] = Impossible;
+        for c in variant.colors() {
+            packed.colors |= Self::color_bit(c);
+        }
+        packed
+    }
+    /// Rule out what a hint that did or did not touch the card says it is not.
+    fn apply_hint(&mut self, hint: &Hint, touched: bool) {
+        match *hint {
+            ValueHint(v) => {
+                let bit = Self::value_bit(v);
+                self.values &= if touched { bit } else { !bit };
+            }
+            ColorHint(c) => {
+                // Multi cards are touched by every color hint.
+                let bits = Self::color_bit(c) | Self::color_bit(Color::Multi);
+                self.colors &= if touched { bits } else { !bits };
+            }
+        }
+    }
+    fn color_bit(c: Color) -> u32 {
+        1 << c as u32
+    }
+    fn value_bit(v: Value) -> u32 {
+        1 << (v - 1)
+    }
+    pub fn may_be_color(&self, c: Color) -> bool {
+        self.colors & Self::color_bit(c) != 0
+    }
+    pub fn may_be_value(&self, v: Value) -> bool {
+        self.values & Self::value_bit(v) != 0
+    }
+    pub fn may_be(&self, c: Color, v: Value) -> bool {
+        self.may_be_color(c) && self.may_be_value(v)
+    }
+    /// The color of the card, if it is the only one left.
+    pub fn color(&self) -> Option<Color> {
+        (self.colors.count_ones() == 1).then(|| COLORS[self.colors.trailing_zeros() as usize])
+    }
+    /// The value of the card, if it is the only one left.
+    pub fn value(&self) -> Option<Value> {
+        (self.values.count_ones() == 1).then(|| self.values.trailing_zeros() as usize + 1)
+    }
+    /// Whether the card may have color `c`, and whether it is the only one
+    /// left.
+    pub fn color_state(&self, c: Color) -> KnowledgeState {
+        Self::state(self.may_be_color(c), self.colors)
+    }
+    /// Whether the card may have value `v`, and whether it is the only one
+    /// left.
+    pub fn value_state(&self, v: Value) -> KnowledgeState {
+        Self::state(self.may_be_value(v), self.values)
+    }
+    fn state(possible: bool, mask: u32) -> KnowledgeState {
+        match (possible, mask.count_ones()) {
+            (false, _) => KnowledgeState::Impossible,
+            (true, 1) => KnowledgeState::Known,
+            (true, _) => KnowledgeState::Possible,
+        }
+    }
+    fn from_states(vs: &[KnowledgeState; MAX_VALUE], cs: &ColorArray<KnowledgeState>) -> Self {
+        let mut packed = PackedKnowledge::default();
+        for c in COLORS
+            .into_iter()
+            .filter(|&c| cs[c] != KnowledgeState::Impossible)
+        {
+            packed.colors |= Self::color_bit(c);
         }
-        this
+        for v in (1..=MAX_VALUE).filter(|&v| vs[v - 1] != KnowledgeState::Impossible) {
+            packed.values |= Self::value_bit(v);
+        }
+        packed
+    }
+    fn states(&self) -> ([KnowledgeState; MAX_VALUE], ColorArray<KnowledgeState>) {
+        let vs = std::array::from_fn(|v| self.value_state(v + 1));
+        let cs = ColorArray(std::array::from_fn(|c| self.color_state(COLORS[c])));
+        (vs, cs)
+    }
+}
+
+/// The states of the values and colors of a card, as `PackedKnowledge` is
+/// serialized. NOTE: Indices of `vs` are 1 lower than values.
+#[derive(Serialize, Deserialize, Clone)]
+struct KnowledgeStates {
+    vs: [KnowledgeState; MAX_VALUE],
+    cs: ColorArray<KnowledgeState>,
+}
+
+impl From<KnowledgeStates> for PackedKnowledge {
+    fn from(states: KnowledgeStates) -> Self {
+        Self::from_states(&states.vs, &states.cs)
+    }
+}
+
+impl From<PackedKnowledge> for KnowledgeStates {
+    fn from(packed: PackedKnowledge) -> Self {
+        let (vs, cs) = packed.states();
+        KnowledgeStates { vs, cs }
+    }
+}
+
+/// The cards a player can see: the played and discarded cards and the hands
+/// of the other players. Kept as the number of copies of each card that the
+/// player cannot see.
+#[derive(Debug, Clone)]
+pub struct VisibleCards {
+    unseen: [[usize; MAX_VALUE]; MAX_COLORS],
+}
+
+impl VisibleCards {
+    /// Number of copies of the card the player cannot see.
+    pub fn unseen(&self, c: Color, v: Value) -> usize {
+        self.unseen[c as usize][v - 1]
     }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct CardWithKnowledge(Card, CardKnowledge);
 
-impl Display for CardWithKnowledge {
+impl Display for Named<'_, CardWithKnowledge> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let Self(card, CardKnowledge { vs, cs, .. }) = self;
-        use KnowledgeState::*;
+        let CardWithKnowledge(card, CardKnowledge { packed, .. }) = self.value;
         // Put an underline under the color/value once it is hinted.
         // Show as bold when color is known for sure.
 
@@ -357,24 +841,23 @@ impl Display for CardWithKnowledge {
             }
         };
         // Multi card was hinted a color but is still ambiguous.
-        let maybemulti = cs.count_eq(Possible) == 2 && cs[Color::Multi] == Possible;
-        let mut color_style = to_style(cs.count_eq(Known) == 1 || maybemulti);
-        let mut value_style = to_style(vs.iter().position(|&x| x == Known).is_some());
-        if cs[Color::Multi] != Possible {
+        let maybemulti = multi_candidate(packed).is_some();
+        let mut color_style = to_style(packed.color().is_some() || maybemulti);
+        let mut value_style = to_style(packed.value().is_some());
+        if packed.color_state(Color::Multi) != KnowledgeState::Possible {
             color_style = color_style.bold();
             value_style = value_style.bold();
         }
 
-        let len = format!("{} {}", card.c, card.v).len();
+        let name = self.names.show(&card.c).to_string();
+        let len = format!("{name} {}", card.v).chars().count();
         if let Some(width) = f.width() {
-            write!(f, "{}", " ".repeat((width - len as usize) / 2),)?;
+            write!(f, "{}", " ".repeat((width - len) / 2),)?;
         }
 
         let styled_maybemulti = || -> String {
-            let maybe_color = cs.find_eq(Possible).unwrap();
-            Color::Multi
-                .to_string()
-                .chars()
+            let maybe_color = multi_candidate(packed).unwrap();
+            name.chars()
                 .enumerate()
                 .map(|(i, ch)| {
                     if i % 2 == 0 {
@@ -392,15 +875,12 @@ impl Display for CardWithKnowledge {
             if card.c == Color::Multi && maybemulti {
                 styled_maybemulti()
             } else {
-                card.c
-                    .style(color_style)
-                    .style(card.c.to_style())
-                    .to_string()
+                name.style(color_style).style(card.c.to_style()).to_string()
             },
             card.v.style(value_style).style(card.c.to_style()),
         )?;
         if let Some(width) = f.width() {
-            write!(f, "{}", " ".repeat((width - len as usize + 1) / 2),)?;
+            write!(f, "{}", " ".repeat((width - len).div_ceil(2)),)?;
         }
         Ok(())
     }
@@ -416,20 +896,20 @@ impl Hand {
     fn new(variant: GameVariant, cards_per_player: usize, deck: &mut Deck) -> Self {
         let cards = (0..cards_per_player)
             .map(|_| {
-                CardWithKnowledge(
-                    deck.take().unwrap(),
-                    CardKnowledge::new(variant, Turn::Start),
-                )
+                let card = deck.take().unwrap();
+                let id = CardId(deck.len());
+                CardWithKnowledge(card, CardKnowledge::new(variant, TurnIndex(0), id))
             })
             .collect();
         Self::Visible(cards)
     }
-    fn draw(&mut self, variant: GameVariant, deck: &mut Deck) {
+    fn draw(&mut self, variant: GameVariant, deck: &mut Deck, turn: TurnIndex) {
         let Hand::Visible(cards) = self else { panic!() };
         if let Some(card) = deck.take() {
+            let id = CardId(deck.len());
             cards.push(CardWithKnowledge(
                 card,
-                CardKnowledge::new(variant, Turn::Start),
+                CardKnowledge::new(variant, turn, id),
             ));
         }
     }
@@ -442,61 +922,39 @@ impl Hand {
         }
     }
     /// Returns the hinted indices.
-    fn hint(&mut self, hint: Hint) -> Result<Vec<CardIdx>, &'static str> {
-        use KnowledgeState::*;
+    fn hint(&mut self, hint: Hint, turn: TurnIndex) -> Result<Vec<CardIdx>, &'static str> {
         let Hand::Visible(cards) = self else { panic!() };
-        let mut card_indices = vec![];
         match hint {
-            ValueHint(v) => {
-                if !(1..=MAX_VALUE).contains(&v) {
-                    return Err("Hinted value is out of range.");
-                }
-                for (idx, CardWithKnowledge(card, know)) in cards.iter_mut().enumerate() {
-                    if v == card.v {
-                        // Answer to hint is 'yes': fix the value of the card.
-                        card_indices.push(CardIdx(idx + 1));
-                        know.vs.fill(Impossible);
-                        know.vs[v - 1] = Known;
-                    } else {
-                        // Answer to hint is 'no': remove the possible value.
-                        know.vs[v - 1] = Impossible;
-                        // If only one 'possible' remaining, set it to Known.
-                        if know.vs.iter().filter(|&&s| s == Possible).count() == 1 {
-                            *know.vs.iter_mut().find(|&&mut s| s == Possible).unwrap() = Known;
-                        }
-                    }
-                }
+            ValueHint(v) if !(1..=MAX_VALUE).contains(&v) => {
+                return Err("Hinted value is out of range.");
             }
-            ColorHint(c) => {
-                if c == Color::Multi {
-                    return Err("Hinting multi is not allowed.");
-                }
-                for (idx, CardWithKnowledge(card, know)) in cards.iter_mut().enumerate() {
-                    if card.c == c || card.c == Color::Multi {
-                        // Answer to hint is 'yes': remove other non-multi colors.
-                        card_indices.push(CardIdx(idx + 1));
-                        for ci in COLORS {
-                            if ci != c && ci != Color::Multi {
-                                know.cs[ci] = Impossible;
-                            }
-                        }
-                    } else {
-                        // Answer to hint is 'no'.
-                        know.cs[Color::Multi] = Impossible;
-                        know.cs[c] = Impossible;
-                    }
-
-                    // If only one 'possible' remaining, set it to Known.
-                    if know.cs.0.iter().filter(|&&s| s == Possible).count() == 1 {
-                        *know.cs.0.iter_mut().find(|&&mut s| s == Possible).unwrap() = Known;
-                    }
-                }
+            ColorHint(c) if !c.is_hintable() => return Err("This color cannot be hinted."),
+            _ => {}
+        }
+        let mut card_indices = vec![];
+        for (idx, CardWithKnowledge(card, know)) in cards.iter_mut().enumerate() {
+            let touched = match hint {
+                ValueHint(v) => card.v == v,
+                ColorHint(c) => card.c == c || card.c == Color::Multi,
+            };
+            know.packed.apply_hint(&hint, touched);
+            if touched {
+                card_indices.push(CardIdx(idx + 1));
             }
         }
+        for (idx, CardWithKnowledge(_, know)) in cards.iter_mut().enumerate() {
+            know.hints.push(HintRecord {
+                turn,
+                hint: hint.clone(),
+                touched: card_indices.iter().any(|i| i.0 == idx + 1),
+            });
+        }
         Ok(card_indices)
     }
-    fn to_view(&mut self) {
-        let Hand::Visible(cards) = std::mem::replace(self, Hand::Hidden(vec![])) else { panic!() };
+    fn view(&mut self) {
+        let Hand::Visible(cards) = std::mem::replace(self, Hand::Hidden(vec![])) else {
+            panic!()
+        };
         *self = Hand::Hidden(
             cards
                 .into_iter()
@@ -504,12 +962,46 @@ impl Hand {
                 .collect(),
         );
     }
+    /// Assert that no card was ruled out as being what it really is.
+    #[cfg(debug_assertions)]
+    fn check_knowledge(&self) {
+        let Hand::Visible(cards) = self else {
+            // Views do not know their own cards.
+            return;
+        };
+        for (idx, CardWithKnowledge(card, know)) in cards.iter().enumerate() {
+            let consistent = know.packed.may_be(card.c, card.v);
+            assert!(
+                consistent,
+                "Knowledge{know:?} of card {} ({:?} {}) rules out the card itself",
+                idx + 1,
+                card.c,
+                card.v
+            );
+        }
+    }
     fn knowledge(&self, card_idx: CardIdx) -> Option<&CardKnowledge> {
         match self {
             Hand::Visible(cards) => cards.get(card_idx.0 - 1).map(|ck| &ck.1),
             Hand::Hidden(cards) => cards.get(card_idx.0 - 1),
         }
     }
+    /// The cards in this hand, if they are visible, with what their owner
+    /// knows about them.
+    pub fn slots(&self) -> Vec<(Option<&Card>, &CardKnowledge)> {
+        match self {
+            Hand::Visible(cards) => cards.iter().map(|ck| (Some(&ck.0), &ck.1)).collect(),
+            Hand::Hidden(cards) => cards.iter().map(|know| (None, know)).collect(),
+        }
+    }
+    /// Current position of the card with the given id.
+    fn position(&self, id: CardId) -> Option<CardIdx> {
+        let idx = match self {
+            Hand::Visible(cards) => cards.iter().position(|ck| ck.1.id == id),
+            Hand::Hidden(cards) => cards.iter().position(|know| know.id == id),
+        };
+        idx.map(|idx| CardIdx(idx + 1))
+    }
 }
 
 /// 0-based player index. Shown to user as 1-based.
@@ -530,6 +1022,12 @@ fn parse_player(s: Option<&str>) -> Result<usize, &'static str> {
 #[derive(Debug, Serialize, Deserialize, Clone, Copy)]
 pub struct CardIdx(usize);
 
+/// Opaque identifier of a card in a hand, unique within a game. It is the
+/// number of cards left in the deck after the card was drawn, so it reveals
+/// nothing beyond the draw order.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CardId(pub usize);
+
 impl FromStr for CardIdx {
     type Err = &'static str;
 
@@ -556,11 +1054,10 @@ pub enum Hint {
 }
 pub use Hint::*;
 
-impl FromStr for Hint {
-    type Err = &'static str;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if let Ok(c) = s.parse() {
+impl Hint {
+    /// Parse a color, by one of the `names`, or a value.
+    fn parse(s: &str, names: &ColorNames) -> Result<Self, &'static str> {
+        if let Ok(c) = names.parse(s) {
             Ok(ColorHint(c))
         } else if let Ok(v) = s.parse() {
             Ok(ValueHint(v))
@@ -570,21 +1067,111 @@ impl FromStr for Hint {
     }
 }
 
-impl Display for Hint {
+impl Display for Named<'_, Hint> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
+        match self.value {
             ValueHint(v) => write!(f, "{}", v.bold()),
-            ColorHint(c) => write!(f, "{}", c.style(c.to_style()).bold()),
+            ColorHint(c) => write!(f, "{}", self.names.show(c).style(c.to_style()).bold()),
+        }
+    }
+}
+
+/// A seat relative to the player making a move.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum RelativePlayer {
+    /// The only other player, in 2-player games.
+    Other,
+    /// The player who moves next.
+    Next,
+    /// The player who moved last.
+    Previous,
+}
+
+impl RelativePlayer {
+    fn resolve(self, player: Player, num_players: usize) -> Result<Player, &'static str> {
+        match self {
+            RelativePlayer::Other if num_players != 2 => Err("Specify the player to hint"),
+            RelativePlayer::Other | RelativePlayer::Next => Ok((player + 1) % num_players),
+            RelativePlayer::Previous => Ok((player + num_players - 1) % num_players),
         }
     }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum Move {
-    Play { card_idx: CardIdx },
-    Discard { card_idx: CardIdx },
-    Hint { hinted_player: Player, hint: Hint },
-    HintOtherPlayer { hint: Hint },
+    /// When `card_id` is given, it takes precedence over `card_idx`, so the
+    /// move applies to the same card even if the mover saw an outdated view.
+    Play {
+        card_idx: CardIdx,
+        #[serde(default)]
+        card_id: Option<CardId>,
+    },
+    /// `force` confirms the discard despite warnings in strict mode.
+    Discard {
+        card_idx: CardIdx,
+        force: bool,
+        #[serde(default)]
+        card_id: Option<CardId>,
+    },
+    Hint {
+        hinted_player: Player,
+        hint: Hint,
+    },
+    /// Hint a player by their seat relative to the hinting player.
+    HintOtherPlayer {
+        relative: RelativePlayer,
+        hint: Hint,
+    },
+    /// Give up the turn at the cost of a hint token. Only made for players
+    /// whose turn timer ran out.
+    Pass,
+}
+
+/// What the owner of a card can prove about it from the hints and the cards
+/// they see.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum CardStatus {
+    /// Every card it may be can be played now.
+    Playable,
+    /// No card it may be is still needed.
+    Trash,
+    /// Every card it may be is the last copy of a needed card.
+    Critical,
+}
+
+impl CardStatus {
+    /// Marker shown next to the knowledge of the card.
+    fn marker(self) -> char {
+        match self {
+            CardStatus::Playable => '!',
+            CardStatus::Trash => 'x',
+            CardStatus::Critical => '#',
+        }
+    }
+}
+
+/// Something a player may want to confirm before doing a move.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum MoveWarning {
+    /// The discarded card can only be the last copy of a card that is still
+    /// needed.
+    CriticalDiscard,
+    /// The hint does not touch any card, so it only rules out possibilities.
+    EmptyHint,
+}
+
+impl Display for MoveWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MoveWarning::CriticalDiscard => {
+                write!(
+                    f,
+                    "This card can only be the last copy of a card that is still needed."
+                )
+            }
+            MoveWarning::EmptyHint => write!(f, "This hint does not touch any cards."),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -595,32 +1182,68 @@ pub enum ClientAction {
     CardInfo { player: Player, card_idx: CardIdx },
     /// Show the game
     Game,
+    /// Toggle rendering the own hand as a grid of possible colors and values.
+    ToggleGrid,
+    /// List the colors of the variant and how to hint them.
+    Colors,
+    /// Print with the given theme from now on.
+    Theme(String),
+    /// List the discard pile in the order the cards were discarded.
+    Discards,
+    /// Print the finished game in the JSON format of hanab.live.
+    ExportHanabLive,
     /// TODO: Show the game-state at the given turn.
     ShowTurn { turn: usize },
 }
 
+/// Parses hints by the default color names.
 impl FromStr for Move {
     type Err = &'static str;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Move::parse(s, &ColorNames::default())
+    }
+}
+
+impl Move {
+    /// Parse a move whose hint may name a color by one of the `names`.
+    pub fn parse(s: &str, names: &ColorNames) -> Result<Self, &'static str> {
         let mut tokens = s.split_ascii_whitespace();
         let mov = match tokens.next().ok_or("Empty string")? {
             "" => return Err("Empty action"),
             a if "play".starts_with(a) => Move::Play {
                 card_idx: tokens.next().ok_or("Missing index")?.parse()?,
+                card_id: None,
             },
             a if "discard".starts_with(a) => Move::Discard {
                 card_idx: tokens.next().ok_or("Missing index")?.parse()?,
+                force: match tokens.next() {
+                    None => false,
+                    Some("force") => true,
+                    Some(_) => return Err("Trailing tokens"),
+                },
+                card_id: None,
             },
             a if "hint".starts_with(a) => {
                 if tokens.clone().count() == 2 {
-                    Move::Hint {
-                        hinted_player: parse_player(tokens.next())?,
-                        hint: tokens.next().ok_or("Missing hint")?.parse()?,
+                    let player = tokens.next();
+                    let relative = match player {
+                        Some("next") => Some(RelativePlayer::Next),
+                        Some("prev" | "previous") => Some(RelativePlayer::Previous),
+                        _ => None,
+                    };
+                    let hint = Hint::parse(tokens.next().ok_or("Missing hint")?, names)?;
+                    match relative {
+                        Some(relative) => Move::HintOtherPlayer { relative, hint },
+                        None => Move::Hint {
+                            hinted_player: parse_player(player)?,
+                            hint,
+                        },
                     }
                 } else {
                     Move::HintOtherPlayer {
-                        hint: tokens.next().ok_or("Missing hint")?.parse()?,
+                        relative: RelativePlayer::Other,
+                        hint: Hint::parse(tokens.next().ok_or("Missing hint")?, names)?,
                     }
                 }
             }
@@ -652,6 +1275,15 @@ impl FromStr for ClientAction {
                 card_idx: tokens.next().ok_or("Missing index")?.parse()?,
             },
             a if "game".starts_with(a) => ClientAction::Game,
+            a if "grid".starts_with(a) => ClientAction::ToggleGrid,
+            a if "colors".starts_with(a) => ClientAction::Colors,
+            "discards" => ClientAction::Discards,
+            "export" => ClientAction::ExportHanabLive,
+            "theme" => {
+                let name = tokens.next().ok_or("Missing theme")?;
+                name.parse::<Theme>()?;
+                ClientAction::Theme(name.to_string())
+            }
             _ => return Err("Unknown action"),
         };
         if tokens.next().is_some() {
@@ -679,24 +1311,61 @@ pub enum MoveLog {
         hint: Hint,
         card_indices: Vec<CardIdx>,
     },
+    Pass,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct PlayerMoveLog {
+/// Risky situations, flagged in the log so they need not be re-derived from the moves.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum MoveFlag {
+    /// A card without any hint information was played successfully.
+    BlindPlay,
+    /// A card was discarded directly after the previous player discarded.
+    DoubleDiscard,
+    /// The last copy of a needed card was lost, lowering the maximum score to the given value.
+    MaxScoreLowered(usize),
+}
+
+impl Display for MoveFlag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MoveFlag::BlindPlay => write!(f, "blind play"),
+            MoveFlag::DoubleDiscard => write!(f, "double discard"),
+            MoveFlag::MaxScoreLowered(max_score) => write!(f, "max score is now {max_score}"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PlayerMoveLog {
     pub player: Player,
     pub mov: MoveLog,
+    #[serde(default)]
+    pub flags: Vec<MoveFlag>,
+}
+
+/// Moments of the game that deserve more attention than a regular move.
+#[derive(Debug, Clone)]
+pub enum GameEvent {
+    /// A misplay cost a life.
+    LifeLost {
+        player: Player,
+        card: Card,
+        lives_left: usize,
+    },
 }
 
 pub struct PlayerMoveLogWithNames<'a> {
     pub mov: &'a PlayerMoveLog,
     pub players: &'a Vec<String>,
+    pub colors: &'a ColorNames,
 }
 
 impl<'a> Display for PlayerMoveLogWithNames<'a> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let Self {
-            mov: PlayerMoveLog { player, mov },
+            mov: PlayerMoveLog { player, mov, flags },
             players: names,
+            colors,
         } = self;
         let player = &names[*player];
         match mov {
@@ -709,18 +1378,20 @@ impl<'a> Display for PlayerMoveLogWithNames<'a> {
                 if *success {
                     write!(
                         f,
-                        "{} played the {} from position {card_idx} knowing {know}.",
+                        "{} played the {} from position {card_idx} knowing {}.",
                         player.bold(),
-                        card.bold(),
+                        colors.show(card).bold(),
+                        colors.show(know),
                     )
                 } else {
                     write!(
                         f,
-                        "{} {} the {} from position {card_idx} knowing {know}, and {}.",
+                        "{} {} the {} from position {card_idx} knowing {}, and {}.",
                         player.bold(),
-                        "played".red(),
-                        card.bold(),
-                        "LOST A LIFE".red()
+                        "played".style(Theme::current().error),
+                        colors.show(card).bold(),
+                        colors.show(know),
+                        "LOST A LIFE".style(Theme::current().error)
                     )
                 }
             }
@@ -730,9 +1401,10 @@ impl<'a> Display for PlayerMoveLogWithNames<'a> {
                 know,
             } => write!(
                 f,
-                "{} discarded the {} from position {card_idx} knowing {know}.",
+                "{} discarded the {} from position {card_idx} knowing {}.",
                 player.bold(),
-                card.bold(),
+                colors.show(card).bold(),
+                colors.show(know),
             ),
             MoveLog::Hint {
                 hinted_player,
@@ -742,9 +1414,10 @@ impl<'a> Display for PlayerMoveLogWithNames<'a> {
                 let hinted_player = &names[*hinted_player];
                 write!(
                     f,
-                    "{} hinted {} {hint} at pos [",
+                    "{} hinted {} {} at pos [",
                     player.bold(),
                     hinted_player.bold(),
+                    colors.show(hint),
                 )?;
                 for (idx, card_idx) in card_indices.iter().enumerate() {
                     if idx == 0 {
@@ -755,7 +1428,12 @@ impl<'a> Display for PlayerMoveLogWithNames<'a> {
                 }
                 write!(f, "].")
             }
+            MoveLog::Pass => write!(f, "{} passed and lost a hint.", player.bold()),
+        }?;
+        for flag in flags {
+            write!(f, " {}", format!("({flag})").style(Theme::current().warn))?;
         }
+        Ok(())
     }
 }
 
@@ -773,24 +1451,34 @@ impl<'a> Display for PlayerMoveLogWithNames<'a> {
 #[strum(ascii_case_insensitive)]
 pub enum GameVariant {
     Base,
+    /// With a rainbow suit that every color hint touches.
+    #[strum(to_string = "Multi", serialize = "Rainbow")]
     Multi,
     MultiHard,
+    /// With a black suit of single cards that color hints do not touch.
+    Dark,
+    /// With both the Multi suit and the black suit.
+    MultiDark,
+    /// With a purple suit that is hinted like the others.
+    Purple,
+    /// With a gray, white-ish suit that color hints do not touch.
+    #[strum(to_string = "Gray", serialize = "White-ish")]
+    Gray,
 }
 
 impl GameVariant {
     pub fn num_colors(&self) -> usize {
-        match self {
-            GameVariant::Base => 5,
-            GameVariant::Multi | GameVariant::MultiHard => 6,
-        }
+        self.colors().len()
     }
     pub fn max_score(&self) -> usize {
         5 * self.num_colors()
     }
     pub fn has_multi(&self) -> bool {
         match self {
-            GameVariant::Base => false,
-            GameVariant::Multi | GameVariant::MultiHard => true,
+            GameVariant::Base | GameVariant::Dark | GameVariant::Purple | GameVariant::Gray => {
+                false
+            }
+            GameVariant::Multi | GameVariant::MultiHard | GameVariant::MultiDark => true,
         }
     }
     pub fn colors(&self) -> Vec<Color> {
@@ -800,73 +1488,594 @@ impl GameVariant {
             GameVariant::Multi | GameVariant::MultiHard => {
                 vec![Blue, Green, Red, White, Yellow, Multi]
             }
+            GameVariant::Dark => vec![Blue, Green, Red, White, Yellow, Black],
+            GameVariant::MultiDark => vec![Blue, Green, Red, White, Yellow, Multi, Black],
+            GameVariant::Purple => vec![Blue, Green, Red, White, Yellow, Purple],
+            GameVariant::Gray => vec![Blue, Green, Red, White, Yellow, Gray],
+        }
+    }
+}
+
+/// How hint tokens are regained.
+#[derive(
+    Debug,
+    Serialize,
+    Deserialize,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    strum_macros::Display,
+    strum_macros::EnumString,
+)]
+#[strum(ascii_case_insensitive, serialize_all = "kebab-case")]
+pub enum HintEconomy {
+    /// Only discarding regains a hint.
+    Discard,
+    /// Discarding and completing a stack regain a hint.
+    #[default]
+    DiscardAndFives,
+    /// Only completing a stack regains a hint.
+    Fives,
+    /// Hints are never regained.
+    Never,
+}
+
+impl HintEconomy {
+    pub fn regains_on_discard(&self) -> bool {
+        matches!(self, HintEconomy::Discard | HintEconomy::DiscardAndFives)
+    }
+    pub fn regains_on_five(&self) -> bool {
+        matches!(self, HintEconomy::DiscardAndFives | HintEconomy::Fives)
+    }
+}
+
+/// How the game ends once the deck is empty.
+#[derive(
+    Debug,
+    Serialize,
+    Deserialize,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    strum_macros::Display,
+    strum_macros::EnumString,
+)]
+#[strum(ascii_case_insensitive, serialize_all = "kebab-case")]
+pub enum EndgameRule {
+    /// Every player gets exactly one more turn.
+    #[default]
+    FinalRound,
+    /// Play on until no card in any hand can still be played. Players without
+    /// cards are skipped when there are no hint tokens.
+    UntilStuck,
+}
+
+/// Whether hints that touch no cards are allowed. Allowed empty hints still
+/// tell the hinted player which cards do not match.
+#[derive(
+    Debug,
+    Serialize,
+    Deserialize,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    strum_macros::Display,
+    strum_macros::EnumString,
+)]
+#[strum(ascii_case_insensitive, serialize_all = "kebab-case")]
+pub enum EmptyHintRule {
+    #[default]
+    Lenient,
+    Strict,
+}
+
+/// Direction in which the stacks are built.
+#[derive(
+    Debug,
+    Serialize,
+    Deserialize,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    strum_macros::Display,
+    strum_macros::EnumString,
+)]
+#[strum(ascii_case_insensitive)]
+pub enum StackOrder {
+    /// From 1 up to 5.
+    #[default]
+    #[strum(serialize = "up")]
+    Ascending,
+    /// From 5 down to 1.
+    #[strum(serialize = "down")]
+    Descending,
+}
+
+impl StackOrder {
+    /// The order in which the values of a stack must be played.
+    pub fn sequence(&self) -> [Value; MAX_VALUE] {
+        match self {
+            StackOrder::Ascending => [1, 2, 3, 4, 5],
+            StackOrder::Descending => [5, 4, 3, 2, 1],
+        }
+    }
+}
+
+/// Settings of a game, written as the variant followed by `key=value` options,
+/// e.g. `multi hints=fives`. Options left out take their default value.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct GameOptions {
+    pub variant: GameVariant,
+    pub hint_economy: HintEconomy,
+    pub stacks: StackOrder,
+    /// Overrides the default number of cards per player.
+    pub cards_per_player: Option<usize>,
+    /// Refuse discards of cards that are provably critical unless they are
+    /// forced.
+    pub strict_warnings: bool,
+    /// Deal from the given seed, so different groups can play the same game.
+    pub seed: Option<u64>,
+    /// End the game as soon as a perfect score becomes impossible.
+    pub perfect_or_bust: bool,
+    /// Custom names of colors, used for display and for parsing hints.
+    #[serde(default)]
+    pub color_names: ColorNames,
+    /// Overrides the default of 8 hint tokens, which is also the starting number.
+    #[serde(default)]
+    pub max_hints: Option<usize>,
+    #[serde(default)]
+    pub endgame: EndgameRule,
+    /// Overrides the number of hint tokens at the start, which is the maximum
+    /// by default.
+    #[serde(default)]
+    pub starting_hints: Option<usize>,
+    /// Overrides the default of 3 lives.
+    #[serde(default)]
+    pub starting_lives: Option<usize>,
+    #[serde(default)]
+    pub empty_hints: EmptyHintRule,
+    /// Discards regain only half a hint each.
+    #[serde(default)]
+    pub clue_starved: bool,
+}
+
+impl Default for GameOptions {
+    fn default() -> Self {
+        Self {
+            variant: GameVariant::Base,
+            hint_economy: HintEconomy::default(),
+            stacks: StackOrder::default(),
+            cards_per_player: None,
+            strict_warnings: false,
+            seed: None,
+            perfect_or_bust: false,
+            color_names: ColorNames::default(),
+            max_hints: None,
+            endgame: EndgameRule::default(),
+            starting_hints: None,
+            starting_lives: None,
+            empty_hints: EmptyHintRule::default(),
+            clue_starved: false,
+        }
+    }
+}
+
+impl GameOptions {
+    pub fn max_hints(&self) -> usize {
+        self.max_hints.unwrap_or(MAX_HINTS)
+    }
+
+    pub fn starting_hints(&self) -> usize {
+        self.starting_hints.unwrap_or(self.max_hints())
+    }
+
+    pub fn starting_lives(&self) -> usize {
+        self.starting_lives.unwrap_or(MAX_LIVES)
+    }
+
+    /// Check the options that do not depend on the number of players. Options
+    /// also arrive as JSON, which does not go through `FromStr`.
+    pub fn check(&self) -> Result<(), &'static str> {
+        if self.max_hints == Some(0) {
+            return Err("Players need at least one hint token.");
+        }
+        if self.starting_lives == Some(0) {
+            return Err("Players need at least one life.");
+        }
+        if self.starting_hints() > self.max_hints() {
+            return Err("Players cannot start with more hint tokens than the maximum.");
+        }
+        self.color_names.check(self.variant)
+    }
+
+    /// Number of cards per player: 5 for 2-3 players, 4 for 4-5 players and 3
+    /// for 6 players, unless overridden. Larger games need an override.
+    pub fn cards_per_player(&self, num_players: usize) -> Result<usize, &'static str> {
+        let cards_per_player = match (self.cards_per_player, num_players) {
+            (_, 0 | 1) => return Err("At least 2 players are needed."),
+            (Some(cards), _) => cards,
+            (None, 2 | 3) => 5,
+            (None, 4 | 5) => 4,
+            (None, 6) => 3,
+            (None, _) => return Err("Set cards=<n> for more than 6 players."),
+        };
+        if cards_per_player == 0 {
+            return Err("Players need at least one card.");
+        }
+        if num_players * cards_per_player >= self.deck_size() {
+            return Err("Not enough cards in the deck to deal all hands.");
+        }
+        Ok(cards_per_player)
+    }
+
+    /// Total number of cards in the deck.
+    pub fn deck_size(&self) -> usize {
+        self.variant
+            .colors()
+            .into_iter()
+            .map(|c| {
+                (1..=MAX_VALUE)
+                    .map(|v| Deck::count(self, c, v))
+                    .sum::<usize>()
+            })
+            .sum()
+    }
+}
+
+impl FromStr for GameOptions {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut options = GameOptions::default();
+        for token in s.split_ascii_whitespace() {
+            let Some((key, value)) = token.split_once('=') else {
+                options.variant = token.parse().map_err(|_| "Unknown variant")?;
+                continue;
+            };
+            match key {
+                "hints" => {
+                    options.hint_economy = value.parse().map_err(|_| "Unknown hint economy")?
+                }
+                "cards" => {
+                    options.cards_per_player = Some(
+                        value
+                            .parse()
+                            .map_err(|_| "Could not parse number of cards")?,
+                    )
+                }
+                "strict" => {
+                    options.strict_warnings = value.parse().map_err(|_| "Expected true or false")?
+                }
+                "perfect" => {
+                    options.perfect_or_bust = value.parse().map_err(|_| "Expected true or false")?
+                }
+                "starved" => {
+                    options.clue_starved = value.parse().map_err(|_| "Expected true or false")?
+                }
+                "seed" => options.seed = Some(value.parse().map_err(|_| "Could not parse seed")?),
+                "stacks" => options.stacks = value.parse().map_err(|_| "Unknown stack order")?,
+                "endgame" => options.endgame = value.parse().map_err(|_| "Unknown endgame rule")?,
+                "empty" => {
+                    options.empty_hints = value.parse().map_err(|_| "Expected lenient or strict")?
+                }
+                "names" => options.color_names = parse_color_names(value)?,
+                "tokens" => {
+                    options.max_hints = Some(
+                        value
+                            .parse()
+                            .map_err(|_| "Could not parse number of hint tokens")?,
+                    )
+                }
+                "start_tokens" => {
+                    options.starting_hints = Some(
+                        value
+                            .parse()
+                            .map_err(|_| "Could not parse number of hint tokens")?,
+                    )
+                }
+                "lives" => {
+                    options.starting_lives = Some(
+                        value
+                            .parse()
+                            .map_err(|_| "Could not parse number of lives")?,
+                    )
+                }
+                _ => return Err("Unknown option"),
+            }
+        }
+        options.check()?;
+        Ok(options)
+    }
+}
+
+/// Parse `color:name` pairs separated by commas, e.g. `multi:rainbow,red:rood`.
+fn parse_color_names(s: &str) -> Result<ColorNames, &'static str> {
+    let mut names: Vec<(Color, String)> = vec![];
+    for pair in s.split(',') {
+        let (color, name) = pair.split_once(':').ok_or("Expected color:name")?;
+        // Parse the color by its default name.
+        let color = ColorNames::default().parse(color)?;
+        names.push((color, name.to_string()));
+    }
+    // Checked with the variant in `GameOptions::check`.
+    Ok(ColorNames(names))
+}
+
+impl Display for GameOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut s = self.variant.to_string();
+        if self.hint_economy != HintEconomy::default() {
+            s += &format!(" hints={}", self.hint_economy);
+        }
+        if self.stacks != StackOrder::default() {
+            s += &format!(" stacks={}", self.stacks);
+        }
+        if self.endgame != EndgameRule::default() {
+            s += &format!(" endgame={}", self.endgame);
         }
+        if let Some(cards) = self.cards_per_player {
+            s += &format!(" cards={cards}");
+        }
+        if let Some(tokens) = self.max_hints {
+            s += &format!(" tokens={tokens}");
+        }
+        if let Some(tokens) = self.starting_hints {
+            s += &format!(" start_tokens={tokens}");
+        }
+        if let Some(lives) = self.starting_lives {
+            s += &format!(" lives={lives}");
+        }
+        if self.empty_hints != EmptyHintRule::default() {
+            s += &format!(" empty={}", self.empty_hints);
+        }
+        if self.strict_warnings {
+            s += " strict=true";
+        }
+        if self.clue_starved {
+            s += " starved=true";
+        }
+        if let Some(seed) = self.seed {
+            s += &format!(" seed={seed}");
+        }
+        if self.perfect_or_bust {
+            s += " perfect=true";
+        }
+        if !self.color_names.is_empty() {
+            let names: Vec<_> = self
+                .color_names
+                .iter()
+                .map(|(c, name)| format!("{}:{name}", c.default_name().to_lowercase()))
+                .collect();
+            s += &format!(" names={}", names.join(","));
+        }
+        f.pad(&s)
     }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Copy)]
 pub enum GameState {
     NextPlayer(Player),
+    /// The deck is empty; every player gets one last turn.
+    FinalRound {
+        next_player: Player,
+        turns_left: usize,
+    },
     Won,
     Died,
     Ended,
+    /// A perfect score became impossible in a perfect-score-or-bust game.
+    Unwinnable,
+    /// The game was stopped before it ended, e.g. because a player forfeited
+    /// or stopped moving. Its score does not count.
+    Abandoned,
 }
 
 impl GameState {
     fn has_ended(&self) -> bool {
-        match self {
-            GameState::NextPlayer(_) => false,
-            _ => true,
-        }
+        self.next_player().is_none()
     }
 
-    fn to_string(&self, players: &Vec<String>) -> String {
+    /// The player to move, if the game has not ended.
+    pub fn next_player(&self) -> Option<Player> {
         match *self {
+            GameState::NextPlayer(player) => Some(player),
+            GameState::FinalRound { next_player, .. } => Some(next_player),
+            _ => None,
+        }
+    }
+
+    fn to_string(self, players: &[String]) -> String {
+        match self {
             GameState::NextPlayer(player) => format!("next: {}", players[player]),
-            GameState::Won => "won".green().to_string(),
-            GameState::Died => "died".red().to_string(),
-            GameState::Ended => "ended".red().to_string(),
+            GameState::FinalRound {
+                next_player,
+                turns_left,
+            } => format!(
+                "next: {} ({turns_left} {} left)",
+                players[next_player],
+                if turns_left == 1 { "turn" } else { "turns" }
+            ),
+            GameState::Won => "won".style(Theme::current().good).to_string(),
+            GameState::Died => "died".style(Theme::current().error).to_string(),
+            GameState::Ended => "ended".style(Theme::current().error).to_string(),
+            GameState::Unwinnable => "unwinnable".style(Theme::current().error).to_string(),
+            GameState::Abandoned => "abandoned".style(Theme::current().error).to_string(),
         }
     }
 }
 
+/// Hints given and received by one player.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HintCount {
+    pub given: usize,
+    pub received: usize,
+}
+
+/// Standard metrics of how a game goes so far, as hanab.live shows them.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct GameStats {
+    pub hints_given: usize,
+    pub cards_played: usize,
+    /// Cards played per hint given, once a hint was given.
+    pub efficiency: Option<f64>,
+    /// Turns left for other moves than plays: the cards left to draw plus the
+    /// turns after the deck runs out, minus the plays still needed for the
+    /// maximum score. Once negative, the maximum score is out of reach.
+    pub pace: isize,
+    /// Discards that lost the last copy of a needed card.
+    pub critical_discards: usize,
+}
+
+/// A card on the discard pile, with the move that put it there.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Discard {
+    pub card: Card,
+    pub player: Player,
+    /// The turn of the move that discarded the card.
+    pub turn: TurnIndex,
+    /// The card was played, but did not fit on its stack.
+    pub misplay: bool,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Game {
     // data
     players: Vec<String>,
     start_player: Player,
-    /// None when the game has ended.
     game_state: GameState,
-    /// Set as soon as the deck is empty.
-    last_player: Option<Player>,
 
     cards_per_player: usize,
     hints: usize,
+    /// Half a hint regained by a discard in a clue-starved game, which the
+    /// next such discard completes.
+    #[serde(default)]
+    half_hint: bool,
     lives: usize,
-    variant: GameVariant,
+    options: GameOptions,
+    /// Seed from which the deal was generated. Hidden from players until the game ends.
+    seed: Option<u64>,
 
     // cards
+    /// All cards in drawing order, for replays. Hidden from players until the
+    /// game ends.
+    #[serde(default)]
+    deal: Vec<Card>,
     deck: Deck,
     hands: Vec<Hand>,
-    discarded: Vec<Card>,
+    /// The discard pile, oldest card first.
+    discarded: Vec<Discard>,
     played: Played,
 
     // move
     move_log: Vec<PlayerMoveLog>,
+
+    /// Preferences of the player this view is for.
+    #[serde(default)]
+    accessibility: Accessibility,
+
+    /// Whether the own hand is rendered as a grid of possible colors and
+    /// values. Toggled by the client on its own view, and kept by it from one
+    /// view to the next.
+    #[serde(skip)]
+    knowledge_grid: bool,
+
+    /// Games that bots play out in their head skip the work that only serves
+    /// people, like deducing what players can rule out from the cards they see.
+    #[serde(skip)]
+    simulation: bool,
 }
 
 impl Game {
-    pub fn new(mut players: Vec<String>, variant: GameVariant) -> Self {
+    pub fn new(mut players: Vec<String>, options: GameOptions) -> Result<Self, &'static str> {
         let num_players = players.len();
-        players.shuffle(&mut rand::thread_rng());
-        let start_player = thread_rng().gen_range(0..num_players);
-        let cards_per_player = match num_players {
-            2 | 3 => 5,
-            4 | 5 => 4,
-            _ => panic!(),
+        options.check()?;
+        options.cards_per_player(num_players)?;
+        let seed = match options.seed {
+            Some(seed) => seed,
+            None => Self::random_seed()?,
+        };
+        let mut rng = StdRng::seed_from_u64(seed);
+        players.shuffle(&mut rng);
+        let start_player = rng.gen_range(0..num_players);
+        let deck = Deck::new(&options, &mut rng);
+        Ok(Self::deal(players, start_player, options, Some(seed), deck))
+    }
+
+    /// Like `new`, but players keep their order and the first one starts.
+    pub fn new_in_order(players: Vec<String>, options: GameOptions) -> Result<Self, &'static str> {
+        options.check()?;
+        options.cards_per_player(players.len())?;
+        let seed = match options.seed {
+            Some(seed) => seed,
+            None => Self::random_seed()?,
+        };
+        let deck = Deck::new(&options, &mut StdRng::seed_from_u64(seed));
+        Ok(Self::deal(players, 0, options, Some(seed), deck))
+    }
+
+    /// A seed for a game created without one.
+    #[cfg(feature = "entropy")]
+    fn random_seed() -> Result<u64, &'static str> {
+        Ok(thread_rng().gen())
+    }
+    #[cfg(not(feature = "entropy"))]
+    fn random_seed() -> Result<u64, &'static str> {
+        Err("This build has no source of randomness; give the game a seed.")
+    }
+
+    /// Like `new`, but dealt from `seed`: the seating, start player and deck
+    /// are the same for every game with the same players, options and seed.
+    pub fn new_with_seed(
+        players: Vec<String>,
+        options: GameOptions,
+        seed: u64,
+    ) -> Result<Self, &'static str> {
+        Self::new(
+            players,
+            GameOptions {
+                seed: Some(seed),
+                ..options
+            },
+        )
+    }
+
+    /// Deal a pre-constructed deck, given in drawing order. Players keep their
+    /// order and the first one starts.
+    pub fn with_deck(
+        players: Vec<String>,
+        options: GameOptions,
+        mut cards: Vec<Card>,
+    ) -> Result<Self, &'static str> {
+        options.check()?;
+        options.cards_per_player(players.len())?;
+        Deck::check(&options, &cards)?;
+        // Cards are drawn from the back.
+        cards.reverse();
+        Ok(Self::deal(players, 0, options, None, Deck::Visible(cards)))
+    }
+
+    fn deal(
+        players: Vec<String>,
+        start_player: Player,
+        options: GameOptions,
+        seed: Option<u64>,
+        mut deck: Deck,
+    ) -> Self {
+        let variant = options.variant;
+        let cards_per_player = options.cards_per_player(players.len()).unwrap();
+        let Deck::Visible(cards) = &deck else {
+            unreachable!("new decks are visible")
         };
-        let mut deck = Deck::new(variant);
-        let hands = (0..num_players)
+        let deal = cards.iter().rev().cloned().collect();
+        let hands = (0..players.len())
             .map(|_| Hand::new(variant, cards_per_player, &mut deck))
             .collect();
 
@@ -874,34 +2083,42 @@ impl Game {
             players,
             start_player,
             game_state: GameState::NextPlayer(start_player),
-            last_player: None,
             cards_per_player,
-            hints: MAX_HINTS,
-            lives: MAX_LIVES,
-            variant,
+            hints: options.starting_hints(),
+            half_hint: false,
+            lives: options.starting_lives(),
+            played: Played::new(&options),
+            options,
+            seed,
+            deal,
             deck,
             hands,
             discarded: vec![],
-            played: Played::new(variant),
             move_log: vec![],
+            accessibility: Default::default(),
+            knowledge_grid: false,
+            simulation: false,
         }
     }
 
-    pub fn player_id(&self, player: &String) -> Option<Player> {
+    pub fn player_id(&self, player: &str) -> Option<Player> {
         self.players.iter().position(|x| x == player)
     }
 
     pub fn make_move(&mut self, player: Player, mov: Move) -> Result<(), &'static str> {
-        let GameState::NextPlayer(next_player) = self.game_state else {
+        let Some(next_player) = self.game_state.next_player() else {
             return Err("Game has ended.")?;
         };
         if player != next_player {
             return Err("Not this player's turn.");
         }
 
+        let max_score = self.max_score();
+        let mov = self.resolve_card_id(player, mov)?;
+
         // Do the move.
         match mov {
-            Move::Play { card_idx } => {
+            Move::Play { card_idx, .. } => {
                 let CardWithKnowledge(card, know) = self.hands[player]
                     .take(card_idx)
                     .ok_or("Card index out of range.")?;
@@ -910,48 +2127,78 @@ impl Game {
                 // Card is cloned for the log.
                 let success = match self.played.play(card.clone()) {
                     Ok(card) => {
-                        if card.v == MAX_VALUE {
-                            self.hints += 1;
+                        if self.played.is_complete(card.c)
+                            && self.options.hint_economy.regains_on_five()
+                        {
+                            self.hints = (self.hints + 1).min(self.options.max_hints());
                         }
                         drop(card);
                         true
                     }
                     Err(card) => {
-                        self.discarded.push(card);
+                        self.discarded.push(Discard {
+                            card,
+                            player,
+                            turn: TurnIndex(self.turn().0 + 1),
+                            misplay: true,
+                        });
                         self.lives -= 1;
                         false
                     }
                 };
 
-                self.hands[player].draw(self.variant, &mut self.deck);
-                self.move_log.push(PlayerMoveLog {
+                let turn = TurnIndex(self.turn().0 + 1);
+                self.hands[player].draw(self.options.variant, &mut self.deck, turn);
+                self.log_move(
                     player,
-                    mov: MoveLog::Play {
+                    MoveLog::Play {
                         card_idx,
                         card,
                         know,
                         success,
                     },
-                })
+                );
             }
-            Move::Discard { card_idx } => {
-                if self.hints == MAX_HINTS {
+            Move::Discard {
+                card_idx, force, ..
+            } => {
+                let regains = self.options.hint_economy.regains_on_discard();
+                if regains && self.hints == self.options.max_hints() {
                     return Err("Already at max hints; discarding not allowed.");
                 }
+                if self.options.strict_warnings
+                    && !force
+                    && !self.move_warnings(player, &mov).is_empty()
+                {
+                    return Err(
+                        "This card can only be the last copy of a needed card; add `force` to confirm.",
+                    );
+                }
                 let CardWithKnowledge(card, know) = self.hands[player]
                     .take(card_idx)
                     .ok_or("Card index out of range.")?;
-                self.discarded.push(card.clone());
-                self.hints += 1;
-                self.hands[player].draw(self.variant, &mut self.deck);
-                self.move_log.push(PlayerMoveLog {
+                self.discarded.push(Discard {
+                    card: card.clone(),
+                    player,
+                    turn: TurnIndex(self.turn().0 + 1),
+                    misplay: false,
+                });
+                if regains && self.options.clue_starved {
+                    self.hints += usize::from(self.half_hint);
+                    self.half_hint = !self.half_hint;
+                } else if regains {
+                    self.hints += 1;
+                }
+                let turn = TurnIndex(self.turn().0 + 1);
+                self.hands[player].draw(self.options.variant, &mut self.deck, turn);
+                self.log_move(
                     player,
-                    mov: MoveLog::Discard {
+                    MoveLog::Discard {
                         card_idx,
                         card,
                         know,
                     },
-                })
+                );
             }
             Move::Hint {
                 hinted_player,
@@ -959,87 +2206,851 @@ impl Game {
             } => {
                 self.hint(hinted_player, player, hint)?;
             }
-            Move::HintOtherPlayer { hint } => {
-                if self.players.len() == 2 {
-                    self.hint((player + 1) % 2, player, hint)?;
-                } else {
-                    return Err("Specify the player to hint");
+            Move::HintOtherPlayer { relative, hint } => {
+                let hinted_player = relative.resolve(player, self.players.len())?;
+                self.hint(hinted_player, player, hint)?;
+            }
+            Move::Pass => {
+                if self.hints == 0 {
+                    return Err("No hints remaining; passing not allowed.");
                 }
+                self.hints -= 1;
+                self.log_move(player, MoveLog::Pass);
             }
         }
 
+        if !self.simulation {
+            self.deduce_knowledge();
+        }
+
+        if self.max_score() < max_score {
+            let flag = MoveFlag::MaxScoreLowered(self.max_score());
+            self.move_log.last_mut().unwrap().flags.push(flag);
+        }
+
         // End the game?
         self.game_state = if self.lives == 0 {
             GameState::Died
-        } else if self.played.score() == self.variant.max_score() {
+        } else if self.options.perfect_or_bust
+            && self.max_score() < self.options.variant.max_score()
+        {
+            GameState::Unwinnable
+        } else if self.played.score() == self.options.variant.max_score() {
             GameState::Won
-        } else if self.last_player == Some(player) {
-            GameState::Ended
+        } else if self.deck.is_empty() && self.options.endgame == EndgameRule::UntilStuck {
+            match self.next_player_with_moves(player) {
+                Some(next_player) if self.holds_needed_card() => GameState::NextPlayer(next_player),
+                _ => GameState::Ended,
+            }
         } else {
-            GameState::NextPlayer((player + 1) % self.players.len())
+            let next_player = (player + 1) % self.players.len();
+            match self.game_state {
+                GameState::FinalRound { turns_left: 1, .. } => GameState::Ended,
+                GameState::FinalRound { turns_left, .. } => GameState::FinalRound {
+                    next_player,
+                    turns_left: turns_left - 1,
+                },
+                // Everyone, including this player, gets one more turn.
+                _ if self.deck.is_empty() => GameState::FinalRound {
+                    next_player,
+                    turns_left: self.players.len(),
+                },
+                _ => GameState::NextPlayer(next_player),
+            }
         };
 
-        // This player will have the last turn?
-        if self.deck.is_empty() && self.last_player.is_none() {
-            self.last_player = Some(player);
-        }
+        #[cfg(feature = "check-invariants")]
+        self.check_card_conservation();
 
         Ok(())
     }
 
-    pub fn client_action(&mut self, action: ClientAction) {
-        match action {
-            ClientAction::ShowLog { count } => self.print_log(count),
-            ClientAction::CardInfo { player, card_idx } => {
-                if let Some(know) = self.hands[player].knowledge(card_idx) {
-                    eprintln!("{know:?}");
-                } else {
-                    eprintln!("Not a valid card.");
-                }
-            }
-            ClientAction::ShowTurn { .. } => {
-                eprintln!("Showing a specific turn is not yet implemented.");
-            }
-            ClientAction::Game => {
-                eprintln!("{self}");
+    /// Assert that the cards in the deck, hands, played stacks and discard pile
+    /// together are exactly the cards of a fresh deck.
+    #[cfg(feature = "check-invariants")]
+    fn check_card_conservation(&self) {
+        let Deck::Visible(deck) = &self.deck else {
+            // Views do not know all cards.
+            return;
+        };
+        let mut count = [[0; MAX_VALUE]; MAX_COLORS];
+        let mut add = |card: &Card| count[card.c as usize][card.v - 1] += 1;
+        deck.iter().for_each(&mut add);
+        self.discarded.iter().for_each(|d| add(&d.card));
+        for hand in &self.hands {
+            let Hand::Visible(cards) = hand else {
+                return;
+            };
+            cards
+                .iter()
+                .for_each(|CardWithKnowledge(card, _)| add(card));
+        }
+        for c in self.options.variant.colors() {
+            for v in 1..=MAX_VALUE {
+                let played = usize::from(self.played.is_played(c, v));
+                debug_assert_eq!(
+                    count[c as usize][v - 1] + played,
+                    Deck::count(&self.options, c, v),
+                    "Number of {c} {v} cards changed"
+                );
             }
         }
     }
 
-    fn hint(
-        &mut self,
-        hinted_player: usize,
-        player: usize,
-        hint: Hint,
-    ) -> Result<(), &'static str> {
-        if self.hints == 0 {
-            return Err("No hints remaining; hinting not allowed.");
-        }
-        if hinted_player == player {
-            return Err("Hinting yourself is not allowed.");
-        }
-        if !(0..self.players.len()).contains(&hinted_player) {
-            return Err("Player out of range");
+    fn move_warnings(&self, player: Player, mov: &Move) -> Vec<MoveWarning> {
+        let mut warnings = vec![];
+        if let Move::Discard { card_idx, .. } = mov {
+            if self.is_discard_critical(player, *card_idx) {
+                warnings.push(MoveWarning::CriticalDiscard);
+            }
         }
-        self.hints -= 1;
-        let card_indices = self.hands[hinted_player].hint(hint.clone())?;
-        Ok(self.move_log.push(PlayerMoveLog {
-            player,
-            mov: MoveLog::Hint {
-                hinted_player,
-                hint,
-                card_indices,
-            },
-        }))
+        warnings
     }
 
-    /// Create a view for the given player, with secret information removed.
-    pub fn to_view(&self, player: Player) -> Self {
-        let mut view = self.clone();
-        view.deck.view();
-        view.hands[player].to_view();
-        view
-    }
+    /// Whether the card of `player` at `card_idx` is provably the last copy
+    /// of a needed card, going only by what everyone knows: the hints on the
+    /// card and the cards that were played and discarded. Neither the card
+    /// itself nor what its owner deduced from the cards they see is looked
+    /// at, so the answer gives nothing away.
+    pub fn is_discard_critical(&self, player: Player, card_idx: CardIdx) -> bool {
+        let Some(know) = self
+            .hands
+            .get(player)
+            .and_then(|hand| hand.knowledge(card_idx))
+        else {
+            return false;
+        };
+        let hinted = know.hinted(self.options.variant);
+        let mut candidates = self
+            .options
+            .variant
+            .colors()
+            .into_iter()
+            .flat_map(|c| (1..=MAX_VALUE).map(move |v| (c, v)))
+            .filter(|&(c, v)| hinted.may_be(c, v))
+            // Copies that are all played or discarded cannot be this card.
+            .filter(|&(c, v)| {
+                self.discarded_count(c, v) + usize::from(self.played.is_played(c, v))
+                    < Deck::count(&self.options, c, v)
+            })
+            .peekable();
+        candidates.peek().is_some() && candidates.all(|(c, v)| self.is_critical(c, v))
+    }
+
+    fn discarded_count(&self, c: Color, v: Value) -> usize {
+        self.discarded
+            .iter()
+            .filter(|d| d.card.c == c && d.card.v == v)
+            .count()
+    }
+
+    /// Whether the card can still be played, i.e. it was not played yet and
+    /// all cards below it on the stack are still available.
+    pub fn is_needed(&self, c: Color, v: Value) -> bool {
+        if self.played.is_played(c, v) {
+            return false;
+        }
+        for u in self.options.stacks.sequence()[self.played[c]..]
+            .iter()
+            .copied()
+        {
+            if u == v {
+                return true;
+            }
+            if self.discarded_count(c, u) == Deck::count(&self.options, c, u) {
+                return false;
+            }
+        }
+        false
+    }
+
+    /// Whether any player holds a card that can still be played.
+    fn holds_needed_card(&self) -> bool {
+        self.hands.iter().any(|hand| {
+            hand.slots()
+                .into_iter()
+                .any(|(card, _)| card.is_some_and(|card| self.is_needed(card.c, card.v)))
+        })
+    }
+
+    /// The first player after `player` who can move: who holds a card or may
+    /// give a hint. The player themself comes last.
+    fn next_player_with_moves(&self, player: Player) -> Option<Player> {
+        let num_players = self.players.len();
+        (1..=num_players)
+            .map(|offset| (player + offset) % num_players)
+            .find(|&p| self.hints > 0 || !self.hands[p].slots().is_empty())
+    }
+
+    /// The height the stack of the color can still reach with the cards left.
+    pub fn max_reachable(&self, c: Color) -> usize {
+        let sequence = self.options.stacks.sequence();
+        let available = sequence[self.played[c]..]
+            .iter()
+            .take_while(|&&v| self.discarded_count(c, v) < Deck::count(&self.options, c, v))
+            .count();
+        self.played[c] + available
+    }
+
+    /// The highest score that can still be reached with the cards left.
+    pub fn max_score(&self) -> usize {
+        self.options
+            .variant
+            .colors()
+            .into_iter()
+            .map(|c| self.max_reachable(c))
+            .sum()
+    }
+
+    /// The cards `viewer` can see. Hands that are hidden in this view do not
+    /// count, so spectators who see no hands only see the piles.
+    pub fn visible_cards(&self, viewer: Player) -> VisibleCards {
+        let mut unseen = [[0; MAX_VALUE]; MAX_COLORS];
+        for c in self.options.variant.colors() {
+            for v in 1..=MAX_VALUE {
+                let played = self.played.is_played(c, v) as usize;
+                unseen[c as usize][v - 1] = Deck::count(&self.options, c, v) - played;
+            }
+        }
+        let discarded = self.discarded.iter().map(|discard| &discard.card);
+        let others = self
+            .hands
+            .iter()
+            .enumerate()
+            .filter(|&(player, _)| player != viewer)
+            .flat_map(|(_, hand)| hand.slots())
+            .filter_map(|(card, _)| card);
+        for card in discarded.chain(others) {
+            let count = &mut unseen[card.c as usize][card.v - 1];
+            *count = count.saturating_sub(1);
+        }
+        VisibleCards { unseen }
+    }
+
+    /// Let every player rule out what their cards cannot be, given the cards
+    /// they see and the cards in their own hand they know completely. Only
+    /// full games know what all players see; views are left as they are.
+    fn deduce_knowledge(&mut self) {
+        if self
+            .hands
+            .iter()
+            .any(|hand| matches!(hand, Hand::Hidden(_)))
+        {
+            return;
+        }
+        for player in 0..self.players.len() {
+            let unseen = self.visible_cards(player).unseen;
+            let Hand::Visible(cards) = &mut self.hands[player] else {
+                continue;
+            };
+            // Knowing one card completely can tell more about the others.
+            loop {
+                let known: Vec<Option<(Color, Value)>> = cards
+                    .iter()
+                    .map(|CardWithKnowledge(_, know)| {
+                        Some((know.packed.color()?, know.packed.value()?))
+                    })
+                    .collect();
+                let mut changed = false;
+                for (idx, CardWithKnowledge(_, know)) in cards.iter_mut().enumerate() {
+                    let mut unseen = unseen;
+                    for &(c, v) in known
+                        .iter()
+                        .enumerate()
+                        .filter(|&(other, _)| other != idx)
+                        .filter_map(|(_, known)| known.as_ref())
+                    {
+                        let count = &mut unseen[c as usize][v - 1];
+                        *count = count.saturating_sub(1);
+                    }
+                    changed |= know.deduce(&unseen);
+                }
+                if !changed {
+                    break;
+                }
+            }
+            #[cfg(debug_assertions)]
+            self.hands[player].check_knowledge();
+        }
+    }
+
+    /// What `player` can prove about their card at `card_idx`, if anything.
+    pub fn card_status(&self, player: Player, card_idx: CardIdx) -> Option<CardStatus> {
+        let know = self.hands.get(player)?.knowledge(card_idx)?;
+        let probabilities = know.probabilities(&self.visible_cards(player));
+        let candidates: Vec<(Color, Value)> = COLORS
+            .into_iter()
+            .flat_map(|c| (1..=MAX_VALUE).map(move |v| (c, v)))
+            .filter(|&(c, v)| probabilities[c as usize][v - 1] > 0.)
+            .collect();
+        if candidates.is_empty() {
+            return None;
+        }
+        if candidates
+            .iter()
+            .all(|&(c, v)| self.played.next(c) == Some(v))
+        {
+            Some(CardStatus::Playable)
+        } else if candidates.iter().all(|&(c, v)| !self.is_needed(c, v)) {
+            Some(CardStatus::Trash)
+        } else if candidates.iter().all(|&(c, v)| self.is_critical(c, v)) {
+            Some(CardStatus::Critical)
+        } else {
+            None
+        }
+    }
+
+    /// Whether the card is needed and all other copies were discarded.
+    pub fn is_critical(&self, c: Color, v: Value) -> bool {
+        self.is_needed(c, v) && self.discarded_count(c, v) + 1 == Deck::count(&self.options, c, v)
+    }
+
+    /// Events in the moves made since the given turn.
+    pub fn events_since(&self, turn: TurnIndex) -> Vec<GameEvent> {
+        let mut lives_left = self.lives;
+        let mut events = vec![];
+        for log in self.move_log[turn.0.min(self.move_log.len())..]
+            .iter()
+            .rev()
+        {
+            if let MoveLog::Play {
+                card,
+                success: false,
+                ..
+            } = &log.mov
+            {
+                events.push(GameEvent::LifeLost {
+                    player: log.player,
+                    card: card.clone(),
+                    lives_left,
+                });
+                lives_left += 1;
+            }
+        }
+        events.reverse();
+        events
+    }
+
+    /// Parse a move, using the color names of this game.
+    pub fn parse_move(&self, s: &str) -> Result<Move, &'static str> {
+        Move::parse(s, &self.options.color_names)
+    }
+
+    pub fn client_action(&mut self, action: ClientAction) {
+        let names = &self.options.color_names;
+        match action {
+            ClientAction::ShowLog { count } => self.print_log(count),
+            ClientAction::CardInfo { player, card_idx } => {
+                if let Some(know) = self.hands[player].knowledge(card_idx) {
+                    eprintln!("{know:?}");
+                    for record in &know.hints {
+                        eprintln!(" {}", names.show(record));
+                    }
+                } else {
+                    eprintln!("Not a valid card.");
+                }
+            }
+            ClientAction::ShowTurn { .. } => {
+                eprintln!("Showing a specific turn is not yet implemented.");
+            }
+            ClientAction::Game => {
+                eprintln!("{self}");
+            }
+            ClientAction::ToggleGrid => {
+                self.knowledge_grid = !self.knowledge_grid;
+                eprintln!("{self}");
+            }
+            ClientAction::Theme(name) => {
+                // Checked when parsing the action.
+                name.parse::<Theme>().unwrap().set();
+                eprintln!("{self}");
+            }
+            ClientAction::ExportHanabLive => match self.export_hanab_live() {
+                Ok(json) => eprintln!("{json}"),
+                Err(err) => eprintln!("{err}"),
+            },
+            ClientAction::Discards => {
+                eprintln!("{}", "discards:".bold());
+                for Discard {
+                    card,
+                    player,
+                    turn,
+                    misplay,
+                } in &self.discarded
+                {
+                    let how = if *misplay { "misplayed" } else { "discarded" };
+                    eprintln!(
+                        " {:2}: {} {how} by {}",
+                        turn.0,
+                        names.show(card),
+                        self.players[*player]
+                    );
+                }
+            }
+            ClientAction::Colors => {
+                let descriptor = self.options.descriptor();
+                eprintln!("{}", format!("Colors of {}:", descriptor.variant).bold());
+                for (suit, c) in descriptor.suits.iter().zip(self.options.variant.colors()) {
+                    eprintln!(
+                        " {} abbreviation: {:2} {}  copies: {}",
+                        format!("{:COLORWIDTH$}", names.show(&c)).style(c.to_style()),
+                        suit.abbreviation.bold(),
+                        if suit.hintable {
+                            "hintable    "
+                        } else {
+                            "not hintable"
+                        },
+                        suit.copies
+                            .iter()
+                            .map(|n| n.to_string())
+                            .collect::<Vec<_>>()
+                            .join(" ")
+                    );
+                }
+                eprintln!(
+                    " values: {}",
+                    descriptor
+                        .values
+                        .iter()
+                        .map(|v| v.to_string())
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                        .bold()
+                );
+            }
+        }
+    }
+
+    /// Replace the position of the card of a play or discard by the
+    /// position of the card with its id, if the move has one.
+    fn resolve_card_id(&self, player: Player, mut mov: Move) -> Result<Move, &'static str> {
+        if let Move::Play {
+            card_idx,
+            card_id: Some(id),
+        }
+        | Move::Discard {
+            card_idx,
+            card_id: Some(id),
+            ..
+        } = &mut mov
+        {
+            *card_idx = self.hands[player]
+                .position(*id)
+                .ok_or("That card is no longer in your hand.")?;
+        }
+        Ok(mov)
+    }
+
+    /// Refer to the card of a play or discard by its id in this view.
+    pub fn pin_card_id(&self, player: Player, mut mov: Move) -> Move {
+        if let Move::Play { card_idx, card_id }
+        | Move::Discard {
+            card_idx, card_id, ..
+        } = &mut mov
+        {
+            *card_id = self.hands[player].knowledge(*card_idx).map(|know| know.id);
+        }
+        mov
+    }
+
+    /// Whether the last move was a hint that touched the card of `player`
+    /// that the play or discard refers to by id.
+    pub fn last_hint_touches(&self, player: Player, mov: &Move) -> bool {
+        let (Move::Play {
+            card_id: Some(id), ..
+        }
+        | Move::Discard {
+            card_id: Some(id), ..
+        }) = mov
+        else {
+            return false;
+        };
+        let Some(PlayerMoveLog {
+            mov:
+                MoveLog::Hint {
+                    hinted_player,
+                    card_indices,
+                    ..
+                },
+            ..
+        }) = self.move_log.last()
+        else {
+            return false;
+        };
+        *hinted_player == player
+            && card_indices
+                .iter()
+                .any(|idx| self.hands[player].knowledge(*idx).map(|know| know.id) == Some(*id))
+    }
+
+    /// The cards of `hinted_player` that the hint would touch, without giving
+    /// it. Fails for hints that touch no cards if the rules forbid them.
+    pub fn simulate_hint(
+        &self,
+        player: Player,
+        hinted_player: Player,
+        hint: &Hint,
+    ) -> Result<Vec<CardIdx>, &'static str> {
+        if self.hints == 0 {
+            return Err("No hints remaining; hinting not allowed.");
+        }
+        if hinted_player == player {
+            return Err("Hinting yourself is not allowed.");
+        }
+        if !(0..self.players.len()).contains(&hinted_player) {
+            return Err("Player out of range");
+        }
+        let mut hand = self.hands[hinted_player].clone();
+        if let Hand::Hidden(_) = hand {
+            return Err("The hinted hand is not visible in this view.");
+        }
+        let card_indices = hand.hint(hint.clone(), TurnIndex(self.turn().0 + 1))?;
+        if card_indices.is_empty() && self.options.empty_hints == EmptyHintRule::Strict {
+            return Err("This hint does not touch any cards.");
+        }
+        Ok(card_indices)
+    }
+
+    /// Check whether the move is allowed without doing it. Returns the warnings
+    /// the player may want to confirm, even when strict mode is disabled.
+    ///
+    /// This only needs the view of `player`, so clients can catch mistakes
+    /// before sending the move.
+    pub fn check_move(&self, player: Player, mov: &Move) -> Result<Vec<MoveWarning>, &'static str> {
+        if self.game_state.next_player() != Some(player) {
+            return Err("Not this player's turn.");
+        }
+        let mov = &self.resolve_card_id(player, mov.clone())?;
+        let mut warnings = vec![];
+        match mov {
+            Move::Play { card_idx, .. } => {
+                self.hands[player]
+                    .knowledge(*card_idx)
+                    .ok_or("Card index out of range.")?;
+            }
+            Move::Discard { card_idx, .. } => {
+                if self.options.hint_economy.regains_on_discard()
+                    && self.hints == self.options.max_hints()
+                {
+                    return Err("Already at max hints; discarding not allowed.");
+                }
+                self.hands[player]
+                    .knowledge(*card_idx)
+                    .ok_or("Card index out of range.")?;
+                warnings = self.move_warnings(player, mov);
+            }
+            Move::Hint {
+                hinted_player,
+                hint,
+            } => {
+                if self.simulate_hint(player, *hinted_player, hint)?.is_empty() {
+                    warnings.push(MoveWarning::EmptyHint);
+                }
+            }
+            Move::HintOtherPlayer { relative, hint } => {
+                let hinted_player = relative.resolve(player, self.players.len())?;
+                if self.simulate_hint(player, hinted_player, hint)?.is_empty() {
+                    warnings.push(MoveWarning::EmptyHint);
+                }
+            }
+            Move::Pass => return Err("Only the turn timer passes turns."),
+        }
+        Ok(warnings)
+    }
+
+    fn hint(
+        &mut self,
+        hinted_player: usize,
+        player: usize,
+        hint: Hint,
+    ) -> Result<(), &'static str> {
+        // Check everything before spending the hint token.
+        self.simulate_hint(player, hinted_player, &hint)?;
+        self.hints -= 1;
+        let turn = TurnIndex(self.turn().0 + 1);
+        let card_indices = self.hands[hinted_player].hint(hint.clone(), turn)?;
+        #[cfg(debug_assertions)]
+        self.hands[hinted_player].check_knowledge();
+        self.log_move(
+            player,
+            MoveLog::Hint {
+                hinted_player,
+                hint,
+                card_indices,
+            },
+        );
+        Ok(())
+    }
+
+    /// Append the move to the log, flagging risky situations.
+    fn log_move(&mut self, player: Player, mov: MoveLog) {
+        let mut flags = vec![];
+        match &mov {
+            MoveLog::Play {
+                know,
+                success: true,
+                ..
+            } if !know.is_hinted() => flags.push(MoveFlag::BlindPlay),
+            MoveLog::Discard { .. } => {
+                if let Some(PlayerMoveLog {
+                    mov: MoveLog::Discard { .. },
+                    ..
+                }) = self.move_log.last()
+                {
+                    flags.push(MoveFlag::DoubleDiscard);
+                }
+            }
+            _ => {}
+        }
+        self.move_log.push(PlayerMoveLog { player, mov, flags });
+    }
+
+    /// Forget the cards that were not drawn yet, and the seed they were dealt
+    /// from, for games whose deck stays hidden after they end. Replays still
+    /// work, since every card that was drawn is kept.
+    pub fn hide_deck(&mut self) {
+        self.deal
+            .truncate(Deck::size(&self.options) - self.deck.len());
+        self.deck.view();
+        self.seed = None;
+        self.options.seed = None;
+    }
+
+    /// Create a view for the given player, with secret information removed.
+    pub fn to_view(&self, player: Player) -> Self {
+        let mut view = self.to_spectator_view(SpectatorView::Hands);
+        view.hands[player].view();
+        view
+    }
+
+    /// A full game that matches what `player` sees in this view, in which
+    /// bots can try out moves. Their own hand and the deck are dealt at random
+    /// from the cards they cannot see, respecting what they know about their
+    /// own cards. `None` when other hands are hidden too, or no fitting deal
+    /// was found.
+    pub fn sample_deal(&self, player: Player, rng: &mut impl Rng) -> Option<Game> {
+        const ATTEMPTS: usize = 100;
+        let Hand::Hidden(own) = &self.hands[player] else {
+            return None;
+        };
+        if self
+            .hands
+            .iter()
+            .enumerate()
+            .any(|(p, hand)| p != player && matches!(hand, Hand::Hidden(_)))
+        {
+            return None;
+        }
+        let visible = self.visible_cards(player);
+        let mut unseen = vec![];
+        for c in self.options.variant.colors() {
+            for v in 1..=MAX_VALUE {
+                unseen.extend((0..visible.unseen(c, v)).map(|_| Card { c, v }));
+            }
+        }
+        if unseen.len() != own.len() + self.deck.len() {
+            return None;
+        }
+        for _ in 0..ATTEMPTS {
+            unseen.shuffle(rng);
+            let mut deck = unseen.clone();
+            let mut hand = Vec::with_capacity(own.len());
+            for know in own {
+                let Some(idx) = deck
+                    .iter()
+                    .position(|card| know.packed.may_be(card.c, card.v))
+                else {
+                    break;
+                };
+                hand.push(CardWithKnowledge(deck.swap_remove(idx), know.clone()));
+            }
+            if hand.len() < own.len() {
+                continue;
+            }
+            deck.shuffle(rng);
+            let mut game = self.clone();
+            game.hands[player] = Hand::Visible(hand);
+            game.deck = Deck::Visible(deck);
+            game.simulation = true;
+            return Some(game);
+        }
+        None
+    }
+
+    /// Create a view for someone who does not play, with the deck and, if
+    /// asked, all hands hidden.
+    pub fn to_spectator_view(&self, spectator_view: SpectatorView) -> Self {
+        let mut view = self.clone();
+        view.deck.view();
+        if spectator_view == SpectatorView::Hidden {
+            for hand in &mut view.hands {
+                hand.view();
+            }
+        }
+        if !self.has_ended() {
+            view.seed = None;
+            view.options.seed = None;
+            view.deal.clear();
+        }
+        view
+    }
+
+    pub fn turn(&self) -> TurnIndex {
+        TurnIndex(self.move_log.len())
+    }
+
+    /// A hash of the public state: whose turn it is, the hints, lives and
+    /// deck size, the played stacks and discard pile, and what players know
+    /// about their cards. Games that reach the same state by different moves
+    /// hash the same, e.g. for transposition tables. The hash is stable across
+    /// platforms and versions.
+    pub fn state_hash(&self) -> u64 {
+        let mut hasher = StableHasher::new();
+        let (next_player, turns_left) = match self.game_state {
+            GameState::NextPlayer(next_player) => (next_player, 0),
+            GameState::FinalRound {
+                next_player,
+                turns_left,
+            } => (next_player, turns_left),
+            _ => (self.players.len(), 0),
+        };
+        for n in [
+            next_player,
+            turns_left,
+            self.hints,
+            usize::from(self.half_hint),
+            self.lives,
+            self.deck.len(),
+        ] {
+            hasher.write(n);
+        }
+        for c in COLORS {
+            hasher.write(self.played[c]);
+        }
+        // The discard pile as a multiset, so the order of discards does not matter.
+        let mut discarded = [[0; MAX_VALUE]; MAX_COLORS];
+        for Discard { card, .. } in &self.discarded {
+            discarded[card.c as usize][card.v - 1] += 1;
+        }
+        for &count in discarded.iter().flatten() {
+            hasher.write(count);
+        }
+        for hand in &self.hands {
+            let slots = hand.slots();
+            hasher.write(slots.len());
+            for (_, know) in slots {
+                hasher.write(know.packed.colors as usize);
+                hasher.write(know.packed.values as usize);
+            }
+        }
+        hasher.0
+    }
+
+    /// Seed the deal was generated from, unless the deck was given or this
+    /// is a view of a game in progress.
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    pub fn players(&self) -> &[String] {
+        &self.players
+    }
+
+    pub fn hints(&self) -> usize {
+        self.hints
+    }
+
+    /// Whether a discard in a clue-starved game regained half a hint on top
+    /// of `hints`.
+    pub fn half_hint(&self) -> bool {
+        self.half_hint
+    }
+
+    /// The hint tokens, with a ½ for a half hint.
+    fn hints_text(&self) -> String {
+        if self.half_hint {
+            format!("{}½", self.hints)
+        } else {
+            self.hints.to_string()
+        }
+    }
+
+    pub fn lives(&self) -> usize {
+        self.lives
+    }
+
+    /// Number of cards left to draw.
+    pub fn deck_len(&self) -> usize {
+        self.deck.len()
+    }
+
+    pub fn options(&self) -> &GameOptions {
+        &self.options
+    }
+
+    /// The hand of the player; in views, the own hand of the viewer is hidden.
+    pub fn hand(&self, player: Player) -> &Hand {
+        &self.hands[player]
+    }
+
+    pub fn played(&self) -> &Played {
+        &self.played
+    }
+
+    /// The discard pile, including misplays, in the order the cards were discarded.
+    pub fn discards(&self) -> &[Discard] {
+        &self.discarded
+    }
+
+    /// All moves so far, oldest first; the move of turn `i` is entry `i - 1`.
+    pub fn move_log(&self) -> &[PlayerMoveLog] {
+        &self.move_log
+    }
+
+    /// Number of hints each player gave and received so far, by seat.
+    pub fn hint_counts(&self) -> Vec<HintCount> {
+        let mut counts = vec![HintCount::default(); self.players.len()];
+        for log in &self.move_log {
+            if let MoveLog::Hint { hinted_player, .. } = log.mov {
+                counts[log.player].given += 1;
+                counts[hinted_player].received += 1;
+            }
+        }
+        counts
+    }
+
+    /// Efficiency, pace and other metrics of the game so far.
+    pub fn stats(&self) -> GameStats {
+        let hints_given = self
+            .move_log
+            .iter()
+            .filter(|log| matches!(log.mov, MoveLog::Hint { .. }))
+            .count();
+        let cards_played = self.played.score();
+        let turns_after_deck = match self.game_state {
+            GameState::NextPlayer(_) => self.players.len(),
+            GameState::FinalRound { turns_left, .. } => turns_left,
+            _ => 0,
+        };
+        let critical_discards = self
+            .move_log
+            .iter()
+            .filter(|log| matches!(log.mov, MoveLog::Discard { .. }))
+            .filter(|log| {
+                log.flags
+                    .iter()
+                    .any(|flag| matches!(flag, MoveFlag::MaxScoreLowered(_)))
+            })
+            .count();
+        GameStats {
+            hints_given,
+            cards_played,
+            efficiency: (hints_given > 0).then(|| cards_played as f64 / hints_given as f64),
+            pace: (cards_played + self.deck.len() + turns_after_deck) as isize
+                - self.max_score() as isize,
+            critical_discards,
+        }
+    }
 
     pub fn game_state(&self) -> GameState {
         self.game_state
@@ -1049,6 +3060,87 @@ impl Game {
         self.game_state.has_ended()
     }
 
+    /// Let the baseline bot move for the current player, based on their view.
+    /// Its random choices follow the state of the game.
+    pub fn skip_turn(&mut self) -> Result<(), &'static str> {
+        let player = self.game_state.next_player().ok_or("Game has ended.")?;
+        let mov = BaselineBot::new(self.state_hash()).choose_move(&self.to_view(player), player);
+        self.make_move(player, mov)
+    }
+
+    /// Move for the current player, whose turn timer ran out: pass at the
+    /// cost of a hint, or discard the oldest card without hints. When that
+    /// is not allowed, the other one is done instead.
+    pub fn time_out(&mut self, on_timeout: TimeoutMove) -> Result<(), &'static str> {
+        let player = self.game_state.next_player().ok_or("Game has ended.")?;
+        let slots = self.hands[player].slots();
+        let can_pass = self.hints > 0;
+        let at_max_hints = self.options.hint_economy.regains_on_discard()
+            && self.hints == self.options.max_hints();
+        let can_discard = !slots.is_empty() && !at_max_hints;
+        let mov = match (on_timeout, can_pass, can_discard) {
+            (TimeoutMove::Skip, true, _) | (TimeoutMove::Discard, true, false) => Move::Pass,
+            (_, _, true) => {
+                let idx = slots
+                    .iter()
+                    .position(|(_, know)| !know.is_hinted())
+                    .unwrap_or(0);
+                Move::Discard {
+                    card_idx: CardIdx(idx + 1),
+                    force: true,
+                    card_id: None,
+                }
+            }
+            _ => return Err("The player can neither pass nor discard."),
+        };
+        self.make_move(player, mov)
+    }
+
+    /// Give the seat of a player to someone else.
+    pub fn replace_player(&mut self, old: &str, new: &str) -> Result<(), &'static str> {
+        if self.player_id(new).is_some() {
+            return Err("The new player already plays in this game.");
+        }
+        let player = self.player_id(old).ok_or("Player not found")?;
+        self.players[player] = new.to_string();
+        Ok(())
+    }
+
+    /// Stop the game before it ended.
+    pub fn abandon(&mut self) {
+        if !self.has_ended() {
+            self.game_state = GameState::Abandoned;
+        }
+    }
+
+    /// One row per color, with the values that are still possible for each card.
+    fn fmt_knowledge_grid(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        hand: &[CardKnowledge],
+    ) -> std::fmt::Result {
+        let names = &self.options.color_names;
+        for (idx, c) in self.options.variant.colors().into_iter().enumerate() {
+            if idx > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "   {:12}", names.show(&c).style(c.to_style()))?;
+            for know in hand {
+                // Centered below the card in the row above.
+                write!(f, "   ")?;
+                for v in 1..=MAX_VALUE {
+                    if know.packed.may_be(c, v) {
+                        write!(f, "{}", v.style(c.to_style()))?;
+                    } else {
+                        write!(f, "{}", "·".dimmed())?;
+                    }
+                }
+                write!(f, "  ")?;
+            }
+        }
+        Ok(())
+    }
+
     fn print_log(&self, count: Option<usize>) {
         eprintln!("{}", "log:".bold());
         for (id, mov) in self
@@ -1064,7 +3156,8 @@ impl Game {
                 id + 1,
                 PlayerMoveLogWithNames {
                     mov,
-                    players: &self.players
+                    players: &self.players,
+                    colors: &self.options.color_names,
                 }
             );
         }
@@ -1078,13 +3171,13 @@ impl Game {
 ///
 /// turn: 0 | hints: 8 | lives: 3
 ///
-///   played | discarded:
-/// red    0 | 0 0 0 0 0
-/// green  0 | 0 0 0 0 0
-/// blue   0 | 0 0 0 0 0
-/// yellow 0 | 0 0 0 0 0
-/// green  0 | 0 0 0 0 0
-/// multi  0 | 0 0 0 0 0
+///   played | max | discarded:
+/// red    0 |   5 | 0 0 0 0 0
+/// green  0 |   5 | 0 0 0 0 0
+/// blue   0 |   5 | 0 0 0 0 0
+/// yellow 0 |   3 | 0 0 0 2 0
+/// green  0 |   5 | 0 0 0 0 0
+/// multi  0 |   5 | 0 0 0 0 0
 ///
 ///  p 1        2        3        4        5
 /// *1 green 1
@@ -1092,12 +3185,22 @@ impl Game {
 ///  3 5        yellow
 impl Display for Game {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        with_accessibility(self.accessibility, || self.fmt_board(f))
+    }
+}
+
+impl Game {
+    fn fmt_board(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "\n------------------------------------------\n")?;
 
-        let good = Style::new().green();
-        let ok = Style::new().white();
-        let warn = Style::new().yellow();
-        let error = Style::new().red();
+        let names = &self.options.color_names;
+        let Theme {
+            good,
+            ok,
+            warn,
+            error,
+            ..
+        } = Theme::current();
 
         let hints_style = match self.hints {
             0 => error,
@@ -1106,8 +3209,8 @@ impl Display for Game {
         };
 
         let lives_style = match self.lives {
-            MAX_LIVES => good,
             0 => error,
+            lives if lives == self.options.starting_lives() => good,
             _ => warn,
         };
 
@@ -1119,46 +3222,74 @@ impl Display for Game {
 
         write!(
             f,
-            "Hints: {} | Lives: {} | Deck: {} | Score: {} | Turn: {}",
-            self.hints.style(hints_style).bold(),
+            "Hints: {}/{} ({}{}) | Lives: {} | Deck: {} | Score: {} | Turn: {}",
+            self.hints_text().style(hints_style).bold(),
+            self.options.max_hints(),
+            self.options.hint_economy,
+            if self.options.clue_starved {
+                ", clue-starved"
+            } else {
+                ""
+            },
             self.lives.style(lives_style).bold(),
             self.deck.len().style(deck_style).bold(),
             self.played.score().bold(),
             self.move_log.len().bold(),
         )?;
-        if let Some(last_player) = self.last_player {
+        if let GameState::FinalRound { turns_left, .. } = self.game_state {
             writeln!(
                 f,
                 " | {}",
-                format!("Last move: {}", self.players[last_player])
-                    .red()
-                    .bold()
+                format!(
+                    "Final round: {turns_left} {} left",
+                    if turns_left == 1 { "turn" } else { "turns" }
+                )
+                .style(error)
+                .bold()
             )?;
         } else {
             writeln!(f)?;
         }
 
         writeln!(f)?;
-        writeln!(f, "    {} | {}", "played".bold(), "discarded".bold())?;
+        writeln!(
+            f,
+            "    {} | {} | {}",
+            "played".bold(),
+            "max".bold(),
+            "discarded".bold()
+        )?;
         let mut discarded = [[0; MAX_VALUE]; MAX_COLORS];
-        for card in &self.discarded {
+        for Discard { card, .. } in &self.discarded {
             discarded[card.c as usize][card.v - 1] += 1;
         }
-        for c in self.variant.colors() {
-            write!(f, " {:COLORWIDTH$}", c.style(c.to_style()))?;
+        for c in self.options.variant.colors() {
+            write!(f, " {:COLORWIDTH$}", names.show(&c).style(c.to_style()))?;
             write!(
                 f,
                 " {} {}",
-                self.played[c].bold().style(c.to_style()),
+                self.played.top(c).unwrap_or(0).bold().style(c.to_style()),
+                "|".style(c.to_style())
+            )?;
+            // Value of the highest card the stack can still reach.
+            let reachable = self.max_reachable(c);
+            let max = reachable
+                .checked_sub(1)
+                .map_or(0, |height| self.options.stacks.sequence()[height]);
+            let max_style = if reachable == MAX_VALUE { ok } else { error };
+            write!(
+                f,
+                " {:>3} {}",
+                max.style(max_style).bold(),
                 "|".style(c.to_style())
             )?;
             for v in 1..=MAX_VALUE {
                 let d = discarded[c as usize][v - 1];
-                let style = if v <= self.played[c] {
+                let style = if self.played.is_played(c, v) {
                     good.bold()
-                } else if d == Deck::count(self.variant, c, v) {
+                } else if d == Deck::count(&self.options, c, v) {
                     error
-                } else if d == Deck::count(self.variant, c, v) - 1 {
+                } else if d == Deck::count(&self.options, c, v) - 1 {
                     warn
                 } else {
                     ok
@@ -1169,13 +3300,15 @@ impl Display for Game {
         }
         writeln!(f)?;
 
+        // Wide boards separate cards and hands by more space.
+        let gap = if self.accessibility.wide { "   " } else { " " };
         write!(f, " {:13} ", "")?;
         for idx in 1..=self.cards_per_player {
-            write!(f, " {:^CARDWIDTH$}", idx.italic())?;
+            write!(f, "{gap}{:^CARDWIDTH$}", idx.italic())?;
         }
         writeln!(f)?;
         for (pid, p) in self.players.iter().enumerate() {
-            let this_turn_style = if self.game_state == GameState::NextPlayer(pid) {
+            let this_turn_style = if self.game_state.next_player() == Some(pid) {
                 Style::new().bold()
             } else {
                 Style::new()
@@ -1188,35 +3321,124 @@ impl Display for Game {
             match &self.hands[pid] {
                 Hand::Visible(hand) => {
                     for card_with_know in hand {
-                        write!(f, " {card_with_know:^CARDWIDTH$}")?;
+                        write!(f, "{gap}{:^CARDWIDTH$}", names.show(card_with_know))?;
                     }
                 }
                 Hand::Hidden(hand) => {
+                    for (idx, know) in hand.iter().enumerate() {
+                        let marker = match self.card_status(pid, CardIdx(idx + 1)) {
+                            Some(status) => status.marker().bold().to_string(),
+                            None => " ".into(),
+                        };
+                        write!(
+                            f,
+                            "{gap}{:^w$}{marker}",
+                            names.show(know),
+                            w = CARDWIDTH - 1
+                        )?;
+                    }
+                    // The most likely card, given everything the owner sees.
+                    writeln!(f)?;
+                    write!(f, " {:13} ", "")?;
+                    let visible = self.visible_cards(pid);
                     for know in hand {
-                        write!(f, " {know:^CARDWIDTH$}")?;
+                        let (text, style) = match know.most_likely(&visible) {
+                            Some((c, v, p)) => (
+                                format!("{}{v} {:.0}%", names.abbreviation(c), 100. * p),
+                                c.to_style(),
+                            ),
+                            None => (String::new(), Style::new()),
+                        };
+                        write!(f, "{gap}{}", format!("{text:^CARDWIDTH$}").style(style))?;
+                    }
+                    if self.knowledge_grid {
+                        writeln!(f)?;
+                        self.fmt_knowledge_grid(f, hand)?;
                     }
                 }
             };
             writeln!(f)?;
+            if self.accessibility.wide {
+                writeln!(f)?;
+            }
         }
         writeln!(f)?;
+
+        // Flag the player who received the fewest hints, when clearly behind.
+        let counts = self.hint_counts();
+        let least = counts.iter().map(|c| c.received).min().unwrap_or(0);
+        let most = counts.iter().map(|c| c.received).max().unwrap_or(0);
+        write!(f, "{}", "hints given/received:".bold())?;
+        for (p, count) in self.players.iter().zip(&counts) {
+            let style = if most >= least + 3 && count.received == least {
+                warn
+            } else {
+                ok
+            };
+            write!(
+                f,
+                "  {p} {}",
+                format!("{}/{}", count.given, count.received).style(style)
+            )?;
+        }
+        writeln!(f)?;
+        writeln!(f)?;
         self.print_log(Some(self.players.len()));
         writeln!(f, "{}", self.game_state.to_string(&self.players).bold())?;
+        let stats = self.stats();
+        let efficiency = match stats.efficiency {
+            Some(efficiency) => format!("{efficiency:.2}"),
+            None => "-".into(),
+        };
+        let pace_style = match stats.pace {
+            ..0 => error,
+            0 => warn,
+            _ => ok,
+        };
+        let critical_style = if stats.critical_discards > 0 {
+            error
+        } else {
+            ok
+        };
+        writeln!(
+            f,
+            "hints given: {} | efficiency: {efficiency} | pace: {} | critical discards: {}",
+            stats.hints_given,
+            stats.pace.style(pace_style),
+            stats.critical_discards.style(critical_style),
+        )?;
+        if let (true, Some(seed)) = (self.has_ended(), self.seed) {
+            writeln!(f, "seed: {seed}")?;
+        }
         Ok(())
     }
 }
 
 impl turnbased_game_server::GameT for Game {
-    type Settings = GameVariant;
+    type Settings = GameOptions;
     type Move = Move;
 
     type ClientAction = ClientAction;
 
-    fn new(players: Vec<String>, variant: Self::Settings) -> Self {
-        Self::new(players, variant)
+    fn new(players: Vec<String>, options: Self::Settings) -> Result<Self, &'static str> {
+        Self::new(players, options)
     }
 
-    fn make_move(&mut self, player: &String, mov: Move) -> Result<(), &'static str> {
+    fn new_in_order(players: Vec<String>, options: GameOptions) -> Result<Self, &'static str> {
+        Self::new_in_order(players, options)
+    }
+
+    fn public_settings(options: &GameOptions) -> GameOptions {
+        GameOptions {
+            seed: None,
+            ..options.clone()
+        }
+    }
+
+    fn make_move(&mut self, player: &str, mov: Move) -> Result<(), &'static str> {
+        if let Move::Pass = mov {
+            return Err("Only the turn timer passes turns.");
+        }
         Self::make_move(self, self.player_id(player).ok_or("Player not found")?, mov)
     }
 
@@ -1224,14 +3446,626 @@ impl turnbased_game_server::GameT for Game {
         Self::client_action(self, action)
     }
 
-    fn to_view(&self, player: &String) -> Self {
+    fn check_move(&self, player: &str, mov: &Move) -> Result<Vec<String>, &'static str> {
+        let player = self
+            .player_id(player)
+            .ok_or("You are not playing in this game.")?;
+        let warnings = self.check_move(player, mov)?;
+        Ok(warnings.iter().map(MoveWarning::to_string).collect())
+    }
+
+    fn pin_move(&self, player: &str, mov: Move) -> Move {
+        match self.player_id(player) {
+            Some(player) => self.pin_card_id(player, mov),
+            None => mov,
+        }
+    }
+
+    fn set_accessibility(&mut self, accessibility: Accessibility) {
+        self.accessibility = accessibility;
+    }
+
+    fn to_view(&self, player: &str) -> Self {
         match self.player_id(player) {
             Some(player) => self.to_view(player),
-            None => self.clone(),
+            None => self.to_spectator_view(SpectatorView::default()),
         }
     }
 
+    fn to_spectator_view(&self, view: SpectatorView) -> Self {
+        Self::to_spectator_view(self, view)
+    }
+
+    fn forget_hidden_cards(&mut self) {
+        Self::hide_deck(self)
+    }
+
+    fn has_ended(&self) -> bool {
+        Self::has_ended(self)
+    }
+
+    fn abandon(&mut self) {
+        Self::abandon(self)
+    }
+
+    fn current_player(&self) -> Option<String> {
+        self.game_state
+            .next_player()
+            .map(|player| self.players[player].clone())
+    }
+
+    fn skip_turn(&mut self) -> Result<(), &'static str> {
+        Self::skip_turn(self)
+    }
+
+    fn time_out(&mut self, on_timeout: TimeoutMove) -> Result<(), &'static str> {
+        Self::time_out(self, on_timeout)
+    }
+
+    fn undo(&mut self) -> Result<(), &'static str> {
+        Self::undo(self)
+    }
+
+    fn replace_player(&mut self, old: &str, new: &str) -> Result<(), &'static str> {
+        Self::replace_player(self, old, new)
+    }
+
+    fn num_moves(&self) -> usize {
+        self.move_log.len()
+    }
+
+    fn replay(&self, turn: usize) -> Result<Self, &'static str> {
+        let mut game = self.to_replay()?.game_at(turn)?;
+        game.accessibility = self.accessibility;
+        Ok(game)
+    }
+
+    fn analyze_move(&self, turn: usize) -> Result<String, &'static str> {
+        let replay = self.to_replay()?;
+        // Seeded by the move, so the analysis of a move is always the same.
+        let mut bot = SearchBot::new(turn as u64);
+        with_accessibility(self.accessibility, || {
+            analysis::analyze_move(&replay, turn, &mut bot).map(|analysis| analysis.to_string())
+        })
+    }
+
+    fn is_abandoned(&self) -> bool {
+        self.game_state == GameState::Abandoned
+    }
+
+    fn score(&self) -> usize {
+        self.played.score()
+    }
+
+    fn last_move_description(&self) -> Option<String> {
+        self.move_log.last().map(|mov| {
+            PlayerMoveLogWithNames {
+                mov,
+                players: &self.players,
+                colors: &self.options.color_names,
+            }
+            .to_string()
+        })
+    }
+
+    fn parse_move(&self, s: &str) -> Result<Move, &'static str> {
+        Self::parse_move(self, s)
+    }
+
+    fn hand_card_ids(&self, player: &str) -> Vec<usize> {
+        let Some(player) = self.player_id(player) else {
+            return vec![];
+        };
+        self.hands[player]
+            .slots()
+            .iter()
+            .map(|(_, know)| know.id.0)
+            .collect()
+    }
+
+    fn last_move_touches(&self, player: &str, mov: &Move) -> bool {
+        self.player_id(player)
+            .is_some_and(|player| self.last_hint_touches(player, mov))
+    }
+
+    fn keep_display(&mut self, previous: &Self) {
+        self.knowledge_grid = previous.knowledge_grid;
+    }
+
+    fn alerts_since(&self, previous: &Self) -> Vec<String> {
+        let names = &self.options.color_names;
+        self.events_since(previous.turn())
+            .into_iter()
+            .map(|event| match event {
+                GameEvent::LifeLost {
+                    player,
+                    card,
+                    lives_left,
+                } => format!(
+                    "{} lost a life playing {} {}! {lives_left} {} left",
+                    self.players[player],
+                    names.show(&card.c),
+                    card.v,
+                    if lives_left == 1 { "life" } else { "lives" }
+                ),
+            })
+            .collect()
+    }
+
+    fn status(&self) -> String {
+        format!(
+            "{} | hints: {} | lives: {} | deck: {}",
+            self.game_state.to_string(&self.players),
+            self.hints_text(),
+            self.lives,
+            self.deck.len()
+        )
+    }
+
+    /// The cards left in the deck, next card first.
+    fn hidden_cards(&self) -> Option<String> {
+        let Deck::Visible(cards) = &self.deck else {
+            return None;
+        };
+        if cards.is_empty() {
+            return None;
+        }
+        let names = &self.options.color_names;
+        let cards = cards
+            .iter()
+            .rev()
+            .map(|card| {
+                format!("{}{}", names.abbreviation(card.c), card.v)
+                    .style(card.c.to_style())
+                    .to_string()
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        Some(format!("{} {cards}", "deck:".bold()))
+    }
+
     fn move_help() -> &'static str {
-        "p[lay] <index> | d[iscard] <index> | h[int] <playerid> <c[olor]|value> | l[og] [count] | i[nfo] <playerid> <index> | g[ame]"
+        "p[lay] <index> | d[iscard] <index> [force] | h[int] <playerid|next|prev> <c[olor]|value> | l[og] [count] | i[nfo] <playerid> <index> | g[ame] | gr[id] | c[olors] | discards | export | theme <dark|light|contrast>"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Card knowledge as it was tracked before it was packed into bitmasks.
+    struct StateKnowledge {
+        vs: [KnowledgeState; MAX_VALUE],
+        cs: ColorArray<KnowledgeState>,
+    }
+
+    impl StateKnowledge {
+        fn new(variant: GameVariant) -> Self {
+            use KnowledgeState::*;
+            let mut cs = ColorArray([Possible; MAX_COLORS]);
+            for c in COLORS {
+                if !variant.colors().contains(&c) {
+                    cs[c] = Impossible;
+                }
+            }
+            Self {
+                vs: [Possible; MAX_VALUE],
+                cs,
+            }
+        }
+
+        fn hint(&mut self, card: &Card, hint: &Hint) -> bool {
+            use KnowledgeState::*;
+            match *hint {
+                ValueHint(v) => {
+                    let touched = card.v == v;
+                    if touched {
+                        self.vs.fill(Impossible);
+                        self.vs[v - 1] = Known;
+                    } else {
+                        self.vs[v - 1] = Impossible;
+                        if self.vs.iter().filter(|&&k| k == Possible).count() == 1 {
+                            let v = self.vs.iter().position(|&k| k == Possible).unwrap();
+                            self.vs[v] = Known;
+                        }
+                    }
+                    touched
+                }
+                ColorHint(c) => {
+                    let touched = card.c == c || card.c == Color::Multi;
+                    if touched {
+                        for ci in COLORS {
+                            if ci != c && ci != Color::Multi {
+                                self.cs[ci] = Impossible;
+                            }
+                        }
+                    } else {
+                        self.cs[Color::Multi] = Impossible;
+                        self.cs[c] = Impossible;
+                    }
+                    if self.cs.count_eq(Possible) == 1 {
+                        let c = self.cs.find_eq(Possible).unwrap();
+                        self.cs[c] = Known;
+                    }
+                    touched
+                }
+            }
+        }
+
+        fn deduce(&mut self, unseen: &[[usize; MAX_VALUE]; MAX_COLORS]) -> bool {
+            use KnowledgeState::*;
+            let fits = |c: Color, v: Value| {
+                self.cs[c] != Impossible
+                    && self.vs[v - 1] != Impossible
+                    && unseen[c as usize][v - 1] > 0
+            };
+            let colors: Vec<Color> = COLORS
+                .into_iter()
+                .filter(|&c| (1..=MAX_VALUE).any(|v| fits(c, v)))
+                .collect();
+            let values: Vec<Value> = (1..=MAX_VALUE)
+                .filter(|&v| COLORS.into_iter().any(|c| fits(c, v)))
+                .collect();
+            if colors.is_empty() {
+                return false;
+            }
+            let (old_vs, old_cs) = (self.vs, self.cs.0);
+            for c in COLORS {
+                if !colors.contains(&c) {
+                    self.cs[c] = Impossible;
+                }
+            }
+            for v in 1..=MAX_VALUE {
+                if !values.contains(&v) {
+                    self.vs[v - 1] = Impossible;
+                }
+            }
+            if let [c] = colors[..] {
+                self.cs[c] = Known;
+            }
+            if let [v] = values[..] {
+                self.vs[v - 1] = Known;
+            }
+            self.vs != old_vs || self.cs.0 != old_cs
+        }
+
+        fn assert_matches(&self, know: &CardKnowledge) {
+            let (vs, cs) = know.packed.states();
+            assert_eq!(vs, self.vs);
+            assert_eq!(cs.0, self.cs.0);
+        }
+    }
+
+    const VARIANTS: [GameVariant; 7] = [
+        GameVariant::Base,
+        GameVariant::Multi,
+        GameVariant::MultiHard,
+        GameVariant::Dark,
+        GameVariant::MultiDark,
+        GameVariant::Purple,
+        GameVariant::Gray,
+    ];
+
+    fn random_hand(variant: GameVariant, rng: &mut StdRng) -> (Hand, Vec<StateKnowledge>) {
+        let colors = variant.colors();
+        let cards: Vec<CardWithKnowledge> = (0..5)
+            .map(|i| {
+                let card = Card {
+                    c: *colors.choose(rng).unwrap(),
+                    v: rng.gen_range(1..=MAX_VALUE),
+                };
+                CardWithKnowledge(card, CardKnowledge::new(variant, TurnIndex(0), CardId(i)))
+            })
+            .collect();
+        let states = cards.iter().map(|_| StateKnowledge::new(variant)).collect();
+        (Hand::Visible(cards), states)
+    }
+
+    fn random_hint(variant: GameVariant, rng: &mut StdRng) -> Hint {
+        if rng.gen() {
+            ValueHint(rng.gen_range(1..=MAX_VALUE))
+        } else {
+            let colors: Vec<Color> = variant
+                .colors()
+                .into_iter()
+                .filter(|c| c.is_hintable())
+                .collect();
+            ColorHint(*colors.choose(rng).unwrap())
+        }
+    }
+
+    #[test]
+    fn packed_hints_match_states() {
+        let mut rng = StdRng::seed_from_u64(31);
+        for variant in VARIANTS {
+            for _ in 0..200 {
+                let (mut hand, mut states) = random_hand(variant, &mut rng);
+                for turn in 1..8 {
+                    let hint = random_hint(variant, &mut rng);
+                    let touched = hand.hint(hint.clone(), TurnIndex(turn)).unwrap();
+                    let Hand::Visible(cards) = &hand else {
+                        unreachable!()
+                    };
+                    for (idx, (CardWithKnowledge(card, know), state)) in
+                        cards.iter().zip(&mut states).enumerate()
+                    {
+                        let was_touched = state.hint(card, &hint);
+                        assert_eq!(was_touched, touched.iter().any(|i| i.0 == idx + 1));
+                        state.assert_matches(know);
+                        assert_eq!(know.hinted(variant), know.packed);
+                    }
+                    #[cfg(debug_assertions)]
+                    hand.check_knowledge();
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn packed_deductions_match_states() {
+        let mut rng = StdRng::seed_from_u64(32);
+        for variant in VARIANTS {
+            for _ in 0..200 {
+                let (mut hand, mut states) = random_hand(variant, &mut rng);
+                for turn in 1..4 {
+                    let hint = random_hint(variant, &mut rng);
+                    hand.hint(hint.clone(), TurnIndex(turn)).unwrap();
+                    let Hand::Visible(cards) = &hand else {
+                        unreachable!()
+                    };
+                    for (CardWithKnowledge(card, _), state) in cards.iter().zip(&mut states) {
+                        state.hint(card, &hint);
+                    }
+                }
+                let Hand::Visible(cards) = &mut hand else {
+                    unreachable!()
+                };
+                for (CardWithKnowledge(_, know), state) in cards.iter_mut().zip(&mut states) {
+                    // Mostly single copies, so that deductions rule things out.
+                    let mut unseen = [[0; MAX_VALUE]; MAX_COLORS];
+                    for count in unseen.iter_mut().flatten() {
+                        *count = rng.gen_range(0..3) / 2;
+                    }
+                    assert_eq!(know.deduce(&unseen), state.deduce(&unseen));
+                    state.assert_matches(know);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn finished_games_are_analyzed() {
+        use turnbased_game_server::GameT;
+        let options = GameOptions {
+            seed: Some(3),
+            ..Default::default()
+        };
+        let mut game = Game::new(vec!["a".into(), "b".into()], options).unwrap();
+        let mut bot = BaselineBot::new(3);
+        while let Some(player) = game.game_state.next_player() {
+            let mov = bot.choose_move(&game.to_view(player), player);
+            game.make_move(player, mov).unwrap();
+        }
+        assert!(GameT::analyze_move(&game, 0).is_err());
+        for turn in [1, game.move_log.len()] {
+            let analysis = GameT::analyze_move(&game, turn).unwrap();
+            assert!(analysis.contains("expected score"), "{analysis}");
+        }
+    }
+
+    #[test]
+    fn hidden_decks_still_replay() {
+        use turnbased_game_server::GameT;
+        let options = GameOptions {
+            seed: Some(5),
+            ..Default::default()
+        };
+        let mut game = Game::new(vec!["a".into(), "b".into()], options).unwrap();
+        let mut bot = BaselineBot::new(5);
+        for _ in 0..10 {
+            let player = game.game_state.next_player().unwrap();
+            let mov = bot.choose_move(&game.to_view(player), player);
+            game.make_move(player, mov).unwrap();
+        }
+        game.abandon();
+        let mut hidden = game.clone();
+        GameT::forget_hidden_cards(&mut hidden);
+        assert!(GameT::hidden_cards(&hidden).is_none());
+        assert_eq!(hidden.options.seed, None);
+        let full = game.to_replay().unwrap();
+        let replay = hidden.to_replay().unwrap();
+        assert_eq!(replay.hidden, game.deck.len());
+        for turn in 0..=replay.len() {
+            let expected = full.game_at(turn).unwrap();
+            let replayed = replay.game_at(turn).unwrap();
+            assert_eq!(replayed.state_hash(), expected.state_hash());
+            assert!(GameT::hidden_cards(&replayed).is_none());
+        }
+        assert!(GameT::analyze_move(&hidden, 10).is_ok());
+        assert!(hidden.export_hanab_live().is_err());
+    }
+
+    /// Let baseline bots finish the game.
+    fn play_out(game: &mut Game, seed: u64) {
+        let mut bot = BaselineBot::new(seed);
+        while let Some(player) = game.game_state.next_player() {
+            let mov = bot.choose_move(&game.to_view(player), player);
+            game.make_move(player, mov).unwrap();
+        }
+    }
+
+    fn described_moves(game: &Game) -> Vec<String> {
+        game.move_log
+            .iter()
+            .map(|mov| {
+                PlayerMoveLogWithNames {
+                    mov,
+                    players: &game.players,
+                    colors: &game.options.color_names,
+                }
+                .to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn hanab_live_games_round_trip() {
+        for variant in [
+            GameVariant::Base,
+            GameVariant::Multi,
+            GameVariant::MultiHard,
+            GameVariant::Purple,
+        ] {
+            for (seed, num_players) in [(1, 2), (2, 3), (3, 5)] {
+                let options = GameOptions {
+                    variant,
+                    seed: Some(seed),
+                    ..Default::default()
+                };
+                let players = (1..=num_players).map(|i| format!("p{i}")).collect();
+                let mut game = Game::new(players, options).unwrap();
+                if variant == GameVariant::Purple {
+                    // Purple is the sixth suit of hanab.live, not its purple.
+                    let player = game.game_state.next_player().unwrap();
+                    let hint = Move::Hint {
+                        hinted_player: (player + 1) % num_players,
+                        hint: ColorHint(Color::Purple),
+                    };
+                    game.make_move(player, hint).unwrap();
+                }
+                play_out(&mut game, seed);
+
+                let json = game.export_hanab_live().unwrap();
+                let imported = Game::import_hanab_live(&json).unwrap();
+                assert_eq!(imported.players, game.players);
+                assert_eq!(imported.options.variant, variant);
+                assert_eq!(described_moves(&imported), described_moves(&game));
+                assert_eq!(imported.game_state, game.game_state);
+                assert_eq!(imported.played.score(), game.played.score());
+                assert_eq!(imported.state_hash(), game.state_hash());
+                assert_eq!(imported.export_hanab_live().unwrap(), json);
+            }
+        }
+    }
+
+    #[test]
+    fn hanab_live_black_is_refused() {
+        use hanab_live::*;
+        use Color::*;
+        let options = GameOptions {
+            variant: GameVariant::Dark,
+            seed: Some(4),
+            ..Default::default()
+        };
+        let game = Game::new(vec!["a".into(), "b".into()], options).unwrap();
+        assert!(game.export_hanab_live().is_err());
+        // hanab.live numbers black after its five usual suits, and lets
+        // players clue it.
+        let suits = [Red, Yellow, Green, Blue, White, Black];
+        let deck = (game.deal.iter())
+            .map(|card| HanabLiveCard {
+                suit_index: suits.iter().position(|&c| c == card.c).unwrap(),
+                rank: card.v,
+            })
+            .collect();
+        let black_clue = HanabLiveAction {
+            kind: 2,
+            target: 1,
+            value: 5,
+        };
+        let hanab_live = HanabLiveGame {
+            players: game.players.clone(),
+            deck,
+            actions: vec![black_clue],
+            options: HanabLiveOptions {
+                variant: Some("Black (6 Suits)".into()),
+                ..Default::default()
+            },
+        };
+        let json = serde_json::to_string(&hanab_live).unwrap();
+        assert!(Game::import_hanab_live(&json).is_err());
+    }
+
+    #[test]
+    fn game_options_round_trip() {
+        for s in [
+            "Base",
+            "Multi hints=fives seed=12",
+            "Rainbow cards=4 tokens=6 start_tokens=2 lives=1",
+            "dark stacks=down endgame=until-stuck empty=strict strict=true",
+            "purple starved=true perfect=true names=purple:violet,red:rood",
+            "Gray seed=18446744073709551615",
+        ] {
+            let options: GameOptions = s.parse().unwrap();
+            let shown = options.to_string();
+            assert_eq!(
+                shown.parse::<GameOptions>().unwrap(),
+                options,
+                "{s} -> {shown}"
+            );
+            assert_eq!(shown.parse::<GameOptions>().unwrap().to_string(), shown);
+        }
+        assert_eq!(GameOptions::default().to_string(), "Base");
+        assert!("base lives=0".parse::<GameOptions>().is_err());
+        assert!("base names=black:night".parse::<GameOptions>().is_err());
+    }
+
+    #[test]
+    fn non_ascii_color_names() {
+        let options: GameOptions = "base seed=3 names=red:grøn,green:grå".parse().unwrap();
+        let names = &options.color_names;
+        assert_eq!(names.abbreviation(Color::Red), "g");
+        assert_eq!(names.abbreviation(Color::Green), "grå");
+        let descriptor = options.descriptor();
+        assert!(descriptor
+            .suits
+            .iter()
+            .any(|suit| suit.abbreviation == "grå"));
+        let mut game = Game::new(vec!["a".into(), "b".into()], options).unwrap();
+        game.to_string();
+        game.to_view(0).to_string();
+        assert!(turnbased_game_server::GameT::hidden_cards(&game)
+            .unwrap()
+            .contains("grå"));
+        play_out(&mut game, 3);
+        assert!("base names=red:grønnnn".parse::<GameOptions>().is_err());
+        assert!("base names=red:grønnn".parse::<GameOptions>().is_ok());
+    }
+
+    #[test]
+    fn state_hash_is_stable() {
+        let options = GameOptions {
+            seed: Some(7),
+            ..Default::default()
+        };
+        let mut game = Game::new(vec!["a".into(), "b".into(), "c".into()], options).unwrap();
+        // Changing this value breaks transposition tables saved by bots.
+        assert_eq!(game.state_hash(), 1491553444988754536);
+        play_out(&mut game, 7);
+        let hash = game.state_hash();
+        for player in 0..3 {
+            assert_eq!(game.to_view(player).state_hash(), hash);
+        }
+        let json = serde_json::to_string(&game).unwrap();
+        let loaded: Game = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.state_hash(), hash);
+        let replayed = Game::from_replay(&game.to_replay().unwrap()).unwrap();
+        assert_eq!(replayed.state_hash(), hash);
+    }
+
+    #[test]
+    fn packed_knowledge_serializes_as_states() {
+        let mut know = CardKnowledge::new(GameVariant::Multi, TurnIndex(0), CardId(3));
+        know.packed.colors &=
+            PackedKnowledge::color_bit(Color::Red) | PackedKnowledge::color_bit(Color::Multi);
+        know.packed.values = PackedKnowledge::value_bit(4);
+        let json = serde_json::to_value(&know).unwrap();
+        assert_eq!(json["vs"][3], "Known");
+        assert_eq!(json["vs"][0], "Impossible");
+        assert_eq!(json["cs"][Color::Red as usize], "Possible");
+        assert_eq!(json["cs"][Color::Blue as usize], "Impossible");
+        let back: CardKnowledge = serde_json::from_value(json).unwrap();
+        assert_eq!(back.packed, know.packed);
+        assert_eq!(back.id, know.id);
     }
 }
@@ -1,40 +1,110 @@
-use hanabi::{Game, GameState, GameVariant};
-use owo_colors::OwoColorize;
+use hanabi::{
+    bot::{BaselineBot, Bot},
+    tutorial::Tutorial,
+    Game, GameOptions,
+};
 use text_io::{read, try_read};
+use turnbased_game_server::style::OwoColorize;
 
 pub fn main() {
+    if std::env::args().nth(1).as_deref() == Some("tutorial") {
+        tutorial();
+        return;
+    }
+    if std::env::args().nth(1).as_deref() == Some("import") {
+        import(std::env::args().nth(2));
+        return;
+    }
     eprintln!("Number of players? [3]");
     eprint!(" ");
     let num_players: usize = try_read!("{}\n").unwrap_or(3);
-    eprintln!("Variant? [Base] Base | Multi | MultiHard ");
+    eprintln!("Number of bots among them? [0]");
     eprint!(" ");
-    let variant: GameVariant = try_read!("{}\n").unwrap_or(GameVariant::Base);
-    let players = (1..)
-        .take(num_players)
+    let num_bots: usize = try_read!("{}\n").unwrap_or(0);
+    if num_bots > num_players {
+        eprintln!("There cannot be more bots than players");
+        return;
+    }
+    eprintln!("Settings? [Base] Base | Multi (Rainbow) | MultiHard | Dark | MultiDark | Purple | Gray (White-ish), then options: hints=<discard|discard-and-fives|fives|never> stacks=<up|down> cards=<n> tokens=<n> start_tokens=<n> lives=<n> endgame=<final-round|until-stuck> empty=<lenient|strict> strict=<true|false> seed=<n> perfect=<true|false> starved=<true|false> names=<color>:<name>,...");
+    eprint!(" ");
+    let options: GameOptions = try_read!("{}\n").unwrap_or_default();
+    let humans = num_players - num_bots;
+    let players = (1..=humans)
         .map(|id| format!("Player{id}"))
+        .chain((1..=num_bots).map(|id| format!("Bot{id}")))
         .collect();
-    let mut game = Game::new(players, variant);
-    while let GameState::NextPlayer(next_player) = game.game_state() {
+    let mut bot = BaselineBot::default();
+    let mut game = match Game::new(players, options) {
+        Ok(game) => game,
+        Err(err) => {
+            eprintln!("{err}");
+            return;
+        }
+    };
+    while let Some(next_player) = game.game_state().next_player() {
+        if next_player >= humans {
+            let mov = bot.choose_move(&game.to_view(next_player), next_player);
+            game.make_move(next_player, mov).unwrap();
+            continue;
+        }
         eprintln!("{}", game.to_view(next_player));
         eprintln!("{}", "move:".bold());
-        loop {
-            eprint!(" ");
-            let mov: String = read!("{}\n");
-            let err = match mov.parse() {
-                Ok(mov) => match game.make_move(next_player, mov) {
-                    Ok(()) => break,
-                    Err(err) => err,
-                },
-                Err(err) => err,
-            };
-            eprintln!("{}", err);
-            eprintln!(
-                "{} play <index> | discard <index> | hint <player> <color|value>",
-                "move:".bold()
-            );
-        }
+        read_move(|mov| game.make_move(next_player, game.parse_move(mov)?));
     }
 
     eprintln!("The game is over");
     eprintln!("{game}");
 }
+
+/// Play the scripted lessons against the teacher, then finish the game.
+fn tutorial() {
+    let mut tutorial = Tutorial::new();
+    while tutorial.game.game_state().next_player().is_some() {
+        eprintln!("{}", tutorial.game.to_view(hanabi::tutorial::STUDENT));
+        match tutorial.prompt() {
+            Some(prompt) => eprintln!("{}", prompt.bold()),
+            None => eprintln!("{}", "move:".bold()),
+        }
+        read_move(|mov| {
+            let mov = tutorial.game.parse_move(mov)?;
+            tutorial.make_move(mov)
+        });
+    }
+    eprintln!("The game is over. Start a real game by running hanabi without arguments.");
+    eprintln!("{}", tutorial.game);
+}
+
+/// Show a game exported from hanab.live as JSON, read from `path` or stdin.
+fn import(path: Option<String>) {
+    let json = match path {
+        Some(path) => std::fs::read_to_string(path),
+        None => std::io::read_to_string(std::io::stdin()),
+    };
+    let json = match json {
+        Ok(json) => json,
+        Err(err) => {
+            eprintln!("{err}");
+            return;
+        }
+    };
+    match Game::import_hanab_live(&json) {
+        Ok(game) => eprintln!("{game}"),
+        Err(err) => eprintln!("{err}"),
+    }
+}
+
+/// Read moves until one is parsed and made by `make_move`.
+fn read_move(mut make_move: impl FnMut(&str) -> Result<(), &'static str>) {
+    loop {
+        eprint!(" ");
+        let mov: String = read!("{}\n");
+        let Err(err) = make_move(&mov) else {
+            return;
+        };
+        eprintln!("{}", err);
+        eprintln!(
+            "{} play <index> | discard <index> [force] | hint <player|next|prev> <color|value>",
+            "move:".bold()
+        );
+    }
+}
@@ -0,0 +1,127 @@
+//! Recordings of games that can be stepped through turn by turn.
+//!
+//! A replay holds everything needed to deal the game again: the players in
+//! seat order, the options, the deck in drawing order and the moves. Any turn
+//! is reconstructed by dealing the deck and making the moves up to it.
+use serde::{Deserialize, Serialize};
+
+use crate::{Card, Deck, Game, GameOptions, Move, MoveLog, Player};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Replay {
+    pub players: Vec<String>,
+    pub start_player: Player,
+    pub options: GameOptions,
+    /// Every card of the game, in the order it was dealt and drawn.
+    pub deck: Vec<Card>,
+    /// Number of cards at the end of the deck that are left out, because
+    /// they were never drawn and the deck stays hidden.
+    #[serde(default)]
+    pub hidden: usize,
+    /// The moves in order, with the player who made each.
+    pub moves: Vec<(Player, Move)>,
+}
+
+impl Replay {
+    /// Number of moves in the game.
+    pub fn len(&self) -> usize {
+        self.moves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.moves.is_empty()
+    }
+
+    /// The game as it was after the first `turn` moves.
+    pub fn game_at(&self, turn: usize) -> Result<Game, &'static str> {
+        if turn > self.len() {
+            return Err("The game does not have that many moves.");
+        }
+        // Cards are drawn from the back. The hidden cards are never drawn, so
+        // any card stands in for them.
+        let hidden = std::iter::repeat_n(&self.deck[0], self.hidden);
+        let deck = Deck::Visible(hidden.chain(self.deck.iter().rev()).cloned().collect());
+        let mut game = Game::deal(
+            self.players.clone(),
+            self.start_player,
+            self.options.clone(),
+            None,
+            deck,
+        );
+        for (player, mov) in &self.moves[..turn] {
+            game.make_move(*player, mov.clone())?;
+        }
+        if self.hidden > 0 {
+            game.hide_deck();
+        }
+        Ok(game)
+    }
+}
+
+impl Game {
+    /// Record this game, once its deal is known: for views of games in
+    /// progress it is hidden.
+    pub fn to_replay(&self) -> Result<Replay, &'static str> {
+        if self.deal.is_empty() {
+            return Err("The deal of this game is hidden.");
+        }
+        let moves = self
+            .move_log
+            .iter()
+            .map(|log| {
+                let mov = match &log.mov {
+                    MoveLog::Play { card_idx, .. } => Move::Play {
+                        card_idx: *card_idx,
+                        card_id: None,
+                    },
+                    // The discard was accepted, so any warnings were confirmed.
+                    MoveLog::Discard { card_idx, .. } => Move::Discard {
+                        card_idx: *card_idx,
+                        force: true,
+                        card_id: None,
+                    },
+                    MoveLog::Hint {
+                        hinted_player,
+                        hint,
+                        ..
+                    } => Move::Hint {
+                        hinted_player: *hinted_player,
+                        hint: hint.clone(),
+                    },
+                    MoveLog::Pass => Move::Pass,
+                };
+                (log.player, mov)
+            })
+            .collect();
+        Ok(Replay {
+            players: self.players.clone(),
+            start_player: self.start_player,
+            options: self.options.clone(),
+            deck: self.deal.clone(),
+            hidden: Deck::size(&self.options) - self.deal.len(),
+            moves,
+        })
+    }
+
+    /// Take back the last move by dealing the game again and making all
+    /// moves but the last.
+    pub fn undo(&mut self) -> Result<(), &'static str> {
+        if self.game_state.has_ended() {
+            return Err("Game has ended.");
+        }
+        let replay = self.to_replay()?;
+        if replay.is_empty() {
+            return Err("There is no move to undo.");
+        }
+        let mut game = replay.game_at(replay.len() - 1)?;
+        game.seed = self.seed;
+        game.accessibility = self.accessibility;
+        *self = game;
+        Ok(())
+    }
+
+    /// Play the recorded game up to its last move.
+    pub fn from_replay(replay: &Replay) -> Result<Self, &'static str> {
+        replay.game_at(replay.len())
+    }
+}
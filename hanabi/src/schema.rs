@@ -0,0 +1,249 @@
+//! Stable serde schema of a player's view of the game, for external tools.
+//!
+//! These types are decoupled from the internal representation of `Game`:
+//! internal structs may change freely, while this schema only changes together
+//! with a bump of `VERSION`.
+use serde::{Deserialize, Serialize};
+use turnbased_game_server::types::SpectatorView;
+
+use crate::{
+    Card, CardKnowledge, Color, Deck, Game, GameOptions, Hand, Hint, HintRecord, Player, COLORS,
+    MAX_VALUE,
+};
+
+/// Version of the schema. Bumped on every incompatible change.
+pub const VERSION: u32 = 1;
+
+/// The game as seen by one player (or by a spectator).
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PlayerView {
+    /// Always equal to `VERSION` for views produced by this crate.
+    pub version: u32,
+    pub variant: String,
+    /// Player names in seating order.
+    pub players: Vec<String>,
+    /// Seat of the viewing player, or `None` for spectators.
+    pub viewer: Option<usize>,
+    /// Seat of the player to move, or `None` once the game has ended.
+    pub current_player: Option<usize>,
+    /// Number of turns left once the deck is empty.
+    #[serde(default)]
+    pub turns_left: Option<usize>,
+    pub hints: usize,
+    pub lives: usize,
+    pub deck_size: usize,
+    pub score: usize,
+    /// Height of each stack, one entry per color in the variant.
+    pub played: Vec<Stack>,
+    /// Discarded cards, including misplays, oldest first.
+    pub discarded: Vec<CardFace>,
+    /// For each card of `discarded`, the turn at which it was discarded.
+    #[serde(default)]
+    pub discard_turns: Vec<usize>,
+    /// Hands in seating order, slots in hand order.
+    pub hands: Vec<Vec<Slot>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CardFace {
+    /// Lowercase color name, e.g. `"red"`.
+    pub color: String,
+    pub value: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Stack {
+    pub color: String,
+    pub height: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Slot {
+    /// Opaque identifier of the card in this slot, unique within the game and
+    /// also given for hidden cards. Refer to slots by id, as positions shift.
+    #[serde(default)]
+    pub id: usize,
+    /// The card in this slot, or `None` when it is hidden from the viewer.
+    pub card: Option<CardFace>,
+    pub knowledge: Knowledge,
+    /// The hints given to the hand since the card was drawn, oldest first,
+    /// including those that did not touch it.
+    #[serde(default)]
+    pub hints: Vec<SlotHint>,
+}
+
+/// A hint given to the hand holding a card.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SlotHint {
+    /// The turn at which the hint was given, like `discard_turns`.
+    pub turn: usize,
+    pub hint: HintFace,
+    /// Whether the hint touched the card.
+    pub touched: bool,
+}
+
+/// A color hint, as `{"color": "red"}`, or a value hint, as `{"value": 3}`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HintFace {
+    /// Lowercase color name, as in `CardFace`.
+    Color(String),
+    Value(usize),
+}
+
+/// What the owner of a card knows about it.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Knowledge {
+    /// Colors the card may still have.
+    pub colors: Vec<String>,
+    /// Values the card may still have.
+    pub values: Vec<usize>,
+}
+
+/// The colors of a variant and how players may refer to them.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SettingsDescriptor {
+    pub variant: String,
+    /// One entry per color in the variant.
+    pub suits: Vec<Suit>,
+    /// Values that can be hinted.
+    pub values: Vec<usize>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Suit {
+    pub color: String,
+    /// The name players see, which differs from `color` for renamed colors.
+    pub name: String,
+    /// Shortest prefix accepted in hints, e.g. `"b"` for blue.
+    pub abbreviation: String,
+    /// Whether the color itself may be hinted.
+    pub hintable: bool,
+    /// Number of copies of each value, starting at 1.
+    pub copies: Vec<usize>,
+}
+
+impl GameOptions {
+    /// Describe the colors and hintable values of these settings.
+    pub fn descriptor(&self) -> SettingsDescriptor {
+        let names = &self.color_names;
+        SettingsDescriptor {
+            variant: self.variant.to_string(),
+            suits: self
+                .variant
+                .colors()
+                .into_iter()
+                .map(|c| Suit {
+                    color: color_name(c),
+                    name: names.name(c).to_string(),
+                    abbreviation: names.abbreviation(c),
+                    hintable: c.is_hintable(),
+                    copies: (1..=MAX_VALUE).map(|v| Deck::count(self, c, v)).collect(),
+                })
+                .collect(),
+            values: (1..=MAX_VALUE).collect(),
+        }
+    }
+}
+
+fn color_name(c: Color) -> String {
+    c.default_name().to_lowercase()
+}
+
+impl From<&Card> for CardFace {
+    fn from(card: &Card) -> Self {
+        CardFace {
+            color: color_name(card.c),
+            value: card.v,
+        }
+    }
+}
+
+impl From<&CardKnowledge> for Knowledge {
+    fn from(know: &CardKnowledge) -> Self {
+        Knowledge {
+            colors: COLORS
+                .into_iter()
+                .filter(|&c| know.packed.may_be_color(c))
+                .map(color_name)
+                .collect(),
+            values: (1..=MAX_VALUE)
+                .filter(|&v| know.packed.may_be_value(v))
+                .collect(),
+        }
+    }
+}
+
+impl From<&HintRecord> for SlotHint {
+    fn from(record: &HintRecord) -> Self {
+        SlotHint {
+            turn: record.turn.0,
+            hint: match record.hint {
+                Hint::ColorHint(c) => HintFace::Color(color_name(c)),
+                Hint::ValueHint(v) => HintFace::Value(v),
+            },
+            touched: record.touched,
+        }
+    }
+}
+
+impl Game {
+    /// Export the view of the given player (or of a spectator) in the stable schema.
+    pub fn to_schema(&self, viewer: Option<Player>) -> PlayerView {
+        let view = match viewer {
+            Some(player) => self.to_view(player),
+            None => self.to_spectator_view(SpectatorView::Hands),
+        };
+        PlayerView {
+            version: VERSION,
+            variant: view.options.variant.to_string(),
+            players: view.players.clone(),
+            viewer,
+            current_player: view.game_state.next_player(),
+            turns_left: match view.game_state {
+                crate::GameState::FinalRound { turns_left, .. } => Some(turns_left),
+                _ => None,
+            },
+            hints: view.hints,
+            lives: view.lives,
+            deck_size: view.deck.len(),
+            score: view.played.score(),
+            played: view
+                .options
+                .variant
+                .colors()
+                .into_iter()
+                .map(|c| Stack {
+                    color: color_name(c),
+                    height: view.played[c],
+                })
+                .collect(),
+            discarded: view.discarded.iter().map(|d| (&d.card).into()).collect(),
+            discard_turns: view.discarded.iter().map(|d| d.turn.0).collect(),
+            hands: view
+                .hands
+                .iter()
+                .map(|hand| match hand {
+                    Hand::Visible(cards) => cards
+                        .iter()
+                        .map(|ck| Slot {
+                            id: ck.1.id.0,
+                            card: Some((&ck.0).into()),
+                            knowledge: (&ck.1).into(),
+                            hints: ck.1.hints.iter().map(Into::into).collect(),
+                        })
+                        .collect(),
+                    Hand::Hidden(knows) => knows
+                        .iter()
+                        .map(|know| Slot {
+                            id: know.id.0,
+                            card: None,
+                            knowledge: know.into(),
+                            hints: know.hints.iter().map(Into::into).collect(),
+                        })
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+}
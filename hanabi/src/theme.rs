@@ -0,0 +1,134 @@
+//! Styles used to print the game, so terminals with light backgrounds or
+//! users who need more contrast can pick different ones.
+//!
+//! The theme is global to the process. It starts from the `HANABI_THEME`
+//! environment variable (`dark`, `light` or `contrast`) and can be changed
+//! with the `theme` client action.
+use std::{str::FromStr, sync::RwLock};
+
+use turnbased_game_server::style::Style;
+
+use crate::{Color, MAX_COLORS};
+
+static THEME: RwLock<Option<Theme>> = RwLock::new(None);
+
+#[derive(Debug, Clone, Copy)]
+pub struct Theme {
+    /// Healthy values, e.g. plenty of hints, and played cards.
+    pub good: Style,
+    /// Neutral values.
+    pub ok: Style,
+    /// Values that need attention, e.g. few hints left.
+    pub warn: Style,
+    /// Bad news, e.g. lost lives and cards that can no longer be played.
+    pub error: Style,
+    /// Style of each color, indexed by `Color as usize`.
+    pub colors: [Style; MAX_COLORS],
+}
+
+impl Theme {
+    /// The default, for terminals with a dark background.
+    pub fn dark() -> Self {
+        Self {
+            good: Style::new().green(),
+            ok: Style::new().white(),
+            warn: Style::new().yellow(),
+            error: Style::new().red(),
+            colors: [
+                Style::new().bright_blue(),
+                Style::new().green(),
+                Style::new().red(),
+                Style::new().white(),
+                Style::new().yellow(),
+                Style::new().purple(),
+                Style::new().bright_black(),
+                Style::new().bright_magenta().bold(),
+                Style::new().white().dimmed(),
+            ],
+        }
+    }
+
+    /// For terminals with a light background, on which white and yellow
+    /// text is hard to read.
+    pub fn light() -> Self {
+        Self {
+            good: Style::new().green(),
+            ok: Style::new().black(),
+            warn: Style::new().magenta(),
+            error: Style::new().red(),
+            colors: [
+                Style::new().blue(),
+                Style::new().green(),
+                Style::new().red(),
+                Style::new().bright_black(),
+                Style::new().yellow().on_black(),
+                Style::new().purple(),
+                Style::new().black().bold(),
+                Style::new().magenta().bold(),
+                Style::new().black().dimmed(),
+            ],
+        }
+    }
+
+    /// Bright colors on solid backgrounds.
+    pub fn high_contrast() -> Self {
+        Self {
+            good: Style::new().black().on_bright_green(),
+            ok: Style::new().bright_white(),
+            warn: Style::new().black().on_bright_yellow(),
+            error: Style::new().bright_white().on_red(),
+            colors: [
+                Style::new().bright_blue().bold(),
+                Style::new().bright_green().bold(),
+                Style::new().bright_red().bold(),
+                Style::new().bright_white().bold(),
+                Style::new().bright_yellow().bold(),
+                Style::new().bright_magenta().bold(),
+                Style::new().black().on_white().bold(),
+                Style::new().magenta().on_white().bold(),
+                Style::new().bright_black().on_white(),
+            ],
+        }
+    }
+
+    pub fn color(&self, c: Color) -> Style {
+        self.colors[c as usize]
+    }
+
+    /// The theme currently used for printing.
+    pub fn current() -> Self {
+        if let Some(theme) = *THEME.read().unwrap() {
+            return theme;
+        }
+        let theme = std::env::var("HANABI_THEME")
+            .ok()
+            .and_then(|name| name.parse().ok())
+            .unwrap_or_else(Theme::dark);
+        *THEME.write().unwrap() = Some(theme);
+        theme
+    }
+
+    /// Use this theme for all printing from now on.
+    pub fn set(self) {
+        *THEME.write().unwrap() = Some(self);
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+impl FromStr for Theme {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "dark" => Ok(Theme::dark()),
+            "light" => Ok(Theme::light()),
+            "contrast" | "high-contrast" => Ok(Theme::high_contrast()),
+            _ => Err("Unknown theme; use dark, light or contrast"),
+        }
+    }
+}
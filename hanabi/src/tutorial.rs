@@ -0,0 +1,178 @@
+//! Scripted lessons that teach the rules on a pre-constructed deal.
+//!
+//! The student plays with a teacher in a 2-player base game. During the
+//! lessons only the suggested move is accepted and the teacher answers with
+//! scripted moves; afterwards the teacher is played by the baseline bot.
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::{CardIdx, Color, Deck, Game, GameOptions, Hint, Move, Player, Value};
+
+/// Player id of the student. The teacher is the only other player.
+pub const STUDENT: Player = 0;
+const TEACHER: Player = 1;
+
+/// The remaining cards are drawn in the order of this seed.
+const SEED: u64 = 1;
+
+/// Hands of the student and the teacher, dealt in this order.
+const HANDS: [(Color, Value); 10] = [
+    (Color::Blue, 3),
+    (Color::Red, 2),
+    (Color::White, 4),
+    (Color::Green, 2),
+    (Color::Yellow, 4),
+    (Color::Red, 1),
+    (Color::Blue, 1),
+    (Color::Green, 4),
+    (Color::Yellow, 5),
+    (Color::White, 3),
+];
+
+/// The move a lesson asks for.
+enum Expected {
+    Hint(Hint),
+    Play(usize),
+    Discard(usize),
+}
+
+impl Expected {
+    fn accepts(&self, mov: &Move) -> bool {
+        match (self, mov) {
+            (Expected::Play(idx), Move::Play { card_idx, .. }) => card_idx.0 == *idx,
+            (Expected::Discard(idx), Move::Discard { card_idx, .. }) => card_idx.0 == *idx,
+            (
+                Expected::Hint(expected),
+                Move::Hint { hint, .. } | Move::HintOtherPlayer { hint, .. },
+            ) => match (expected, hint) {
+                (Hint::ValueHint(a), Hint::ValueHint(b)) => a == b,
+                (Hint::ColorHint(a), Hint::ColorHint(b)) => a == b,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+}
+
+struct Lesson {
+    prompt: &'static str,
+    expected: Expected,
+    /// Shown when the student makes a different move.
+    correction: &'static str,
+    /// The scripted answer of the teacher.
+    reply: Move,
+}
+
+fn lessons() -> Vec<Lesson> {
+    vec![
+        Lesson {
+            prompt: "You can see the cards of the teacher, but not your own. The teacher holds \
+                     two 1s, which can start the stacks. Hint the 1s now: hint next 1",
+            expected: Expected::Hint(Hint::ValueHint(1)),
+            correction: "Hints tell a player all their cards of one color or value. \
+                         Tell the teacher about the 1s: hint next 1",
+            reply: Move::Play {
+                card_idx: CardIdx(1),
+                card_id: None,
+            },
+        },
+        Lesson {
+            prompt: "The teacher played the Red 1. Every hint costs a hint token, and \
+                     discarding a card gains one back. Discard your oldest card: discard 1",
+            expected: Expected::Discard(1),
+            correction: "Your oldest card is the one on the left. Discard it: discard 1",
+            reply: Move::Hint {
+                hinted_player: STUDENT,
+                hint: Hint::ColorHint(Color::Red),
+            },
+        },
+        Lesson {
+            prompt: "The teacher hinted your red card. A hint on a single card usually means \
+                     it can be played: it must be the next red card, the Red 2. Play it: play 1",
+            expected: Expected::Play(1),
+            correction: "The hinted card is the first in your hand. Play it: play 1",
+            reply: Move::Play {
+                card_idx: CardIdx(1),
+                card_id: None,
+            },
+        },
+        Lesson {
+            prompt: "The teacher played its other 1. There is only one 5 of each color, so \
+                     the teacher must not discard its Yellow 5. Save it with a hint: hint next 5",
+            expected: Expected::Hint(Hint::ValueHint(5)),
+            correction: "Fives are never discarded once players know about them. \
+                         Tell the teacher about the 5: hint next 5",
+            reply: Move::Discard {
+                card_idx: CardIdx(1),
+                force: false,
+                card_id: None,
+            },
+        },
+    ]
+}
+
+/// A game between the student and the teacher, with the lessons so far.
+pub struct Tutorial {
+    pub game: Game,
+    lessons: Vec<Lesson>,
+    step: usize,
+}
+
+impl Tutorial {
+    pub fn new() -> Self {
+        let options = GameOptions::default();
+        let Deck::Visible(mut rest) = Deck::new(&options, &mut StdRng::seed_from_u64(SEED)) else {
+            unreachable!()
+        };
+        let mut cards = vec![];
+        for (c, v) in HANDS {
+            let idx = rest.iter().position(|card| card.c == c && card.v == v);
+            cards.push(rest.remove(idx.unwrap()));
+        }
+        // Draw the other cards in the order the seeded deck would.
+        cards.extend(rest.into_iter().rev());
+        let players = vec!["You".to_string(), "Teacher".to_string()];
+        Self {
+            game: Game::with_deck(players, options, cards).unwrap(),
+            lessons: lessons(),
+            step: 0,
+        }
+    }
+
+    /// Instructions for the next move of the student, until the lessons are done.
+    pub fn prompt(&self) -> Option<&'static str> {
+        match self.lessons.get(self.step) {
+            Some(lesson) => Some(lesson.prompt),
+            None if self.step == self.lessons.len() => Some(
+                "That was the last lesson. Finish the game with the teacher, who now plays \
+                 on its own. Try to get every stack to 5!",
+            ),
+            None => None,
+        }
+    }
+
+    /// Make the move of the student, followed by the move of the teacher.
+    pub fn make_move(&mut self, mov: Move) -> Result<(), &'static str> {
+        let lesson = self.lessons.get(self.step);
+        if let Some(lesson) = lesson {
+            if !lesson.expected.accepts(&mov) {
+                return Err(lesson.correction);
+            }
+        }
+        self.game.make_move(STUDENT, mov)?;
+        if self.game.game_state().next_player() != Some(TEACHER) {
+            return Ok(());
+        }
+        match lesson {
+            Some(lesson) => self.game.make_move(TEACHER, lesson.reply.clone())?,
+            None => self.game.skip_turn()?,
+        }
+        self.step += 1;
+        Ok(())
+    }
+}
+
+impl Default for Tutorial {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,16 +1,60 @@
+use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::{Arc, Mutex};
 
-use crate::types::{Action, Response, Room, UserId};
+use crate::tui::{self, Screen, Terminal};
+use crate::types::{
+    format_duration, now, Accessibility, Action, Response, Room, RoomId, RoomState, UserId,
+    PROTOCOL_VERSION,
+};
 use crate::GameT;
-use futures_util::{future, pin_mut, StreamExt};
+use futures_channel::mpsc::{unbounded, UnboundedSender};
+use futures_util::stream::BoxStream;
+use futures_util::{future, pin_mut, StreamExt, TryStreamExt};
 use owo_colors::OwoColorize;
+use serde::{Deserialize, Serialize};
 use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
 use tokio_util::codec::{FramedRead, LinesCodec};
 
 struct ClientState<Game: GameT> {
     userid: Option<UserId>,
     room: Option<Room<Game>>,
+    token: Option<String>,
+    /// Feed entries received since the user last typed a line.
+    unread: usize,
+    /// Room to watch again after reconnecting.
+    resume_roomid: Option<RoomId>,
+    /// Preferences sent with the last login.
+    accessibility: Accessibility,
+    /// Password sent with the last login or registration, to log in again
+    /// once the session expired. It is not cached on disk.
+    password: Option<String>,
+    /// Channel to the server, or `None` while disconnected.
+    sender: Option<UnboundedSender<Message>>,
+    /// Actions typed while disconnected, with the line they were parsed from.
+    queued: Vec<(String, Message)>,
+    /// The next line answers whether the queued actions should be sent.
+    confirm_replay: bool,
+    /// A move with warnings that is sent once the user confirms it.
+    confirm_move: Option<(String, Message)>,
+    /// The finished game the user steps through, if any.
+    replay: Option<ReplayState<Game>>,
+    stdin_closed: bool,
+    /// The screen of the full-screen client, or `None` for the line client.
+    screen: Option<Arc<Mutex<Screen>>>,
+}
+
+/// A finished game that is replayed locally, one move at a time.
+struct ReplayState<Game: GameT> {
+    roomid: RoomId,
+    /// Number of moves shown.
+    turn: usize,
+    /// The finished game, once the server sent it.
+    game: Option<Game>,
+    /// Whether each move is annotated with an analysis.
+    analyze: bool,
+    /// Analysis of the move that led to the current turn, in analysis mode.
+    analysis: Option<String>,
 }
 
 impl<Game: GameT> Default for ClientState<Game> {
@@ -18,120 +62,662 @@ impl<Game: GameT> Default for ClientState<Game> {
         Self {
             userid: Default::default(),
             room: Default::default(),
+            token: Default::default(),
+            unread: 0,
+            resume_roomid: None,
+            accessibility: Default::default(),
+            password: None,
+            sender: None,
+            queued: vec![],
+            confirm_replay: false,
+            confirm_move: None,
+            replay: None,
+            stdin_closed: false,
+            screen: None,
+        }
+    }
+}
+
+impl<Game: GameT> ClientState<Game> {
+    fn from_cache(cache: ClientCache) -> Self {
+        Self {
+            userid: cache.userid,
+            token: cache.token,
+            resume_roomid: cache.roomid,
+            accessibility: cache.accessibility,
+            ..Default::default()
+        }
+    }
+
+    /// Greet the server, then resume the session or log in and watch the
+    /// room again, e.g. after a reconnect.
+    fn resume_actions(&self) -> Vec<Action<Game>> {
+        let mut actions = vec![Action::Hello {
+            protocol_version: PROTOCOL_VERSION,
+        }];
+        if let Some(token) = &self.token {
+            actions.push(Action::Resume(token.clone()));
+        } else if let Some(userid) = &self.userid {
+            actions.push(Action::Login(
+                userid.clone(),
+                self.password.clone(),
+                self.accessibility,
+            ));
+            if let Some(roomid) = self.resume_roomid {
+                actions.push(Action::WatchRoom(roomid, None));
+            }
+        }
+        actions
+    }
+
+    /// Whether the update numbered `seq` of the room is not stale. Asks the
+    /// server to resync the watched room when updates were missed.
+    fn check_seq(&mut self, roomid: RoomId, seq: u64) -> bool {
+        let Some(room) = self.room.as_mut().filter(|room| room.roomid == roomid) else {
+            return true;
+        };
+        if seq < room.seq {
+            return false;
+        }
+        let missed = seq > room.seq + 1;
+        room.seq = seq;
+        if missed {
+            eprintln!("Missed updates of room {roomid}; resyncing");
+            let message =
+                Message::Binary(serde_json::to_vec(&Action::<Game>::Resync).unwrap().into());
+            self.send("resync".into(), message);
+        }
+        true
+    }
+
+    /// Send the message, or queue it while disconnected.
+    fn send(&mut self, line: String, message: Message) {
+        if let Some(sender) = &self.sender {
+            if sender.unbounded_send(message.clone()).is_ok() {
+                return;
+            }
+        }
+        self.queued.push((line, message));
+        eprintln!(
+            "{} Not connected; {} queued",
+            "Error:".bold(),
+            match self.queued.len() {
+                1 => "1 action".to_string(),
+                n => format!("{n} actions"),
+            }
+        );
+    }
+
+    /// Room, user, game summary and unread feed entries in a single line.
+    fn status_line(&self) -> String {
+        let mut parts = vec![];
+        if let Some(room) = &self.room {
+            parts.push(format!("room {}", room.roomid));
+        }
+        parts.push(self.userid.clone().unwrap_or("not logged in".into()));
+        if let Some(room) = &self.room {
+            match &room.state {
+                RoomState::WaitingForPlayers { max_players, .. } => parts.push(format!(
+                    "waiting for players ({}/{max_players})",
+                    room.players.len()
+                )),
+                RoomState::Started(Some(game)) | RoomState::Ended(Some(game)) => {
+                    parts.push(game.status())
+                }
+                _ => {}
+            }
+            if !room.undo_votes.is_empty() {
+                parts.push(format!("undo asked by {}", room.undo_votes.join(", ")));
+            }
+            if let Some(deadline) = room.turn_deadline {
+                let left = deadline.saturating_sub(now());
+                parts.push(format!("{} left", format_duration(left)));
+            }
+        }
+        if self.unread > 0 {
+            parts.push(format!("{} unread", self.unread));
+        }
+        format!("[{}]", parts.join(" | "))
+    }
+
+    /// The board and status line of the replayed game at the current turn.
+    fn replay_board(&self) -> Option<(String, String)> {
+        let Some(ReplayState {
+            roomid,
+            turn,
+            game: Some(game),
+            analysis,
+            ..
+        }) = &self.replay
+        else {
+            return None;
+        };
+        let mut board = match game.replay(*turn) {
+            Ok(board) => format!("{board}\n"),
+            Err(err) => format!(" Error: {}\n", err.bold()),
+        };
+        if let Some(analysis) = analysis {
+            board += &format!("{analysis}\n");
         }
+        let status = format!(
+            "[replay of room {roomid} | move {turn}/{}]",
+            game.num_moves()
+        );
+        Some((board, status))
+    }
+
+    /// Print the board of the replayed game at the current turn.
+    fn show_replay(&self) {
+        let Some((board, status)) = self.replay_board() else {
+            return;
+        };
+        if self.screen.is_none() {
+            eprint!("{board}");
+            eprintln!("{}", status.reversed());
+        }
+        self.prompt("replay: [n]ext | [p]rev | <move> | [a]nalyze | [q]uit: ");
+    }
+
+    fn show_status(&self) {
+        if self.screen.is_none() {
+            eprintln!("{}", self.status_line().reversed());
+        }
+    }
+
+    /// Ask for the next line. The full-screen client redraws the room or
+    /// replay as well.
+    fn prompt(&self, prompt: &str) {
+        let Some(screen) = &self.screen else {
+            eprint!("{}", prompt.bold());
+            return;
+        };
+        let (board, status) = self.replay_board().unwrap_or_else(|| {
+            let board = self.room.as_ref().map(ToString::to_string);
+            (board.unwrap_or_default(), self.status_line())
+        });
+        screen.lock().unwrap().show(board, status, prompt);
+    }
+
+    fn save(&self) {
+        ClientCache {
+            userid: self.userid.clone(),
+            roomid: self.resume_roomid,
+            token: self.token.clone(),
+            accessibility: self.accessibility,
+        }
+        .save();
+    }
+}
+
+/// Client state that is kept on disk, so that a restarted client re-enters
+/// the room it was in.
+#[derive(Serialize, Deserialize, Default)]
+struct ClientCache {
+    userid: Option<UserId>,
+    roomid: Option<RoomId>,
+    /// Token with which the session is resumed after a reconnect.
+    #[serde(default)]
+    token: Option<String>,
+    #[serde(default)]
+    accessibility: Accessibility,
+}
+
+impl ClientCache {
+    /// `$TURNBASED_CLIENT_CACHE`, or `~/.cache/turnbased-game-client.json`.
+    fn path() -> Option<PathBuf> {
+        if let Some(path) = std::env::var_os("TURNBASED_CLIENT_CACHE") {
+            return Some(path.into());
+        }
+        let home = std::env::var_os("HOME")?;
+        Some(PathBuf::from(home).join(".cache/turnbased-game-client.json"))
+    }
+
+    fn load() -> Self {
+        Self::path()
+            .and_then(|path| std::fs::read(path).ok())
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_default()
+    }
+
+    /// Failing to write the cache is not fatal; the client just won't reconnect.
+    fn save(&self) {
+        let Some(path) = Self::path() else {
+            return;
+        };
+        if let Some(dir) = path.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        let _ = std::fs::write(path, serde_json::to_vec(self).unwrap());
     }
 }
 
 pub enum ClientOrServerAction<Game: GameT> {
     ServerAction(Action<Game>),
     ClientAction(Game::ClientAction),
+    /// Step through the finished game of a room, with its password if it
+    /// is private.
+    Replay(RoomId, Option<String>),
 }
 
 impl<Game: GameT> FromStr for ClientOrServerAction<Game> {
     type Err = &'static str;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse_in(s, None)
+    }
+}
+
+impl<Game: GameT> ClientOrServerAction<Game> {
+    /// Parse an action, reading moves in the context of the game if there is one.
+    fn parse_in(s: &str, game: Option<&Game>) -> Result<Self, &'static str> {
+        let mut tokens = s.split_ascii_whitespace();
+        if tokens.next() == Some("replay") {
+            let roomid = tokens.next().ok_or("missing room id")?.parse()?;
+            let password = tokens.next().map(|password| password.into());
+            return Ok(ClientOrServerAction::Replay(roomid, password));
+        }
         let err = match s.parse() {
             Ok(action) => return Ok(ClientOrServerAction::ClientAction(action)),
             Err(err) => err,
         };
-        match s.parse() {
-            Ok(action) => Ok(ClientOrServerAction::ServerAction(action)),
-            Err(err2) => Err(if err != "Unknown action" { err } else { err2 }),
+        if let (Some(mov), Some(game)) = (s.trim().strip_prefix("check "), game) {
+            return Ok(ClientOrServerAction::ServerAction(Action::ProbeMove(
+                game.parse_move(mov.trim())?,
+            )));
+        }
+        if let (Some(mov), Some(game)) = (s.trim().strip_prefix("premove "), game) {
+            if mov.trim() != "off" {
+                return Ok(ClientOrServerAction::ServerAction(Action::Premove(Some(
+                    game.parse_move(mov.trim())?,
+                ))));
+            }
+        }
+        match (s.parse(), game) {
+            (Ok(Action::MakeMove(_)) | Err(_), Some(game)) if game.parse_move(s).is_ok() => Ok(
+                ClientOrServerAction::ServerAction(Action::MakeMove(game.parse_move(s)?)),
+            ),
+            (Ok(action), _) => Ok(ClientOrServerAction::ServerAction(action)),
+            (Err(err2), _) => Err(if err != "Unknown action" { err } else { err2 }),
         }
     }
 }
 
-pub async fn start_client<Game: GameT>(address: &str) {
-    let (stdin_sink, stdin_stream) = futures_channel::mpsc::unbounded();
+/// Connect to the server at `address` and play from the terminal: in
+/// `full_screen` mode if stdin and stdout are terminals, else line by line.
+pub async fn start_client<Game: GameT>(address: &str, full_screen: bool) {
+    // Re-enter the user and room of the previous session.
+    let state: Arc<Mutex<ClientState<Game>>> =
+        Arc::new(Mutex::new(ClientState::from_cache(ClientCache::load())));
 
-    let (ws_stream, _) = connect_async(address).await.expect("Failed to connect");
+    // Restores the terminal when the client returns.
+    let terminal = full_screen.then(Terminal::enter).flatten();
+    let lines: BoxStream<'static, String> = match &terminal {
+        Some(terminal) => {
+            state.lock().unwrap().screen = Some(terminal.screen.clone());
+            let (sender, lines) = unbounded();
+            tokio::spawn(tui::read_keys(terminal.screen.clone(), sender));
+            lines.boxed()
+        }
+        None => FramedRead::new(tokio::io::stdin(), LinesCodec::new())
+            .filter_map(|line| future::ready(line.ok()))
+            .boxed(),
+    };
+    tokio::spawn(read_user_input::<Game>(state.clone(), lines));
 
-    let state: Arc<Mutex<ClientState<Game>>> = Arc::new(Mutex::new(ClientState::default()));
+    let mut backoff = 1;
+    let mut reconnecting = false;
+    loop {
+        let ws_stream = match connect_async(address).await {
+            Ok((ws_stream, _)) => ws_stream,
+            Err(err) => {
+                eprintln!("Failed to connect: {err}. Retrying in {backoff}s.");
+                tokio::time::sleep(std::time::Duration::from_secs(backoff)).await;
+                backoff = (2 * backoff).min(30);
+                continue;
+            }
+        };
+        backoff = 1;
+        let (sink, stream) = unbounded();
+        {
+            let mut state = state.lock().unwrap();
+            for action in state.resume_actions() {
+                let message = Message::Binary(serde_json::to_vec(&action).unwrap().into());
+                sink.unbounded_send(message).unwrap();
+            }
+            state.sender = Some(sink);
+            if !reconnecting {
+                // Lines typed while first connecting are sent right away.
+                for (line, message) in std::mem::take(&mut state.queued) {
+                    state.send(line, message);
+                }
+            } else if !state.queued.is_empty() {
+                state.confirm_replay = true;
+                eprintln!("Reconnected. Send the queued actions?");
+                for (line, _) in &state.queued {
+                    eprintln!(" {line}");
+                }
+                state.prompt("[y/n] ");
+            }
+        }
 
-    tokio::spawn(read_user_input::<Game>(stdin_sink, state.clone()));
+        let (outgoing, incoming) = ws_stream.split();
+        let stdin_to_ws = stream.map(Ok).forward(outgoing);
+        let ws_to_stdout = incoming.try_for_each(|msg| {
+            handle_message(&state, msg);
+            future::ok(())
+        });
 
-    let (outgoing, incoming) = ws_stream.split();
-    let stdin_to_ws = stdin_stream.map(Ok).forward(outgoing);
+        pin_mut!(stdin_to_ws, ws_to_stdout);
+        if let future::Either::Right((Err(err), _)) =
+            future::select(stdin_to_ws, ws_to_stdout).await
+        {
+            eprintln!("Error: {err}");
+        }
 
-    let ws_to_stdout = incoming.for_each(|msg| async {
-        let msg = msg
-            .map_err(|err| {
-                eprintln!("Error: {err}");
-                // Kill the hanging stdin task.
-                std::process::exit(1);
-            })
-            .unwrap();
-        if !msg.is_binary() {
+        let mut state = state.lock().unwrap();
+        state.sender = None;
+        if state.stdin_closed {
             return;
         }
-        let text = msg.into_data();
-        let response: Response<Game> = serde_json::from_slice(&text).unwrap();
+        eprintln!("Connection lost. Reconnecting; actions are queued meanwhile.");
+        reconnecting = true;
+    }
+}
+
+fn handle_message<Game: GameT>(state: &Mutex<ClientState<Game>>, msg: Message) {
+    if !msg.is_binary() {
+        return;
+    }
+    let text = msg.into_data();
+    let response: Response<Game> = serde_json::from_slice(&text).unwrap();
 
+    let mut state = state.lock().unwrap();
+    let fresh = match &response {
+        Response::Room(room) => state.check_seq(room.roomid, room.seq),
+        Response::Feed { roomid, seq, .. } => state.check_seq(*roomid, *seq),
+        _ => true,
+    };
+    if !fresh {
+        return;
+    }
+    // The full-screen client shows the room in its own panel.
+    if !(state.screen.is_some() && matches!(response, Response::Room(_))) {
         eprint!("{response}");
-        match response {
-            Response::LoggedIn(userid) => {
-                state.lock().unwrap().userid = Some(userid.clone());
-                state.lock().unwrap().room = None;
-                // The login message is followed by another message anyway.
-            }
-            Response::Room(room) => {
-                state.lock().unwrap().room = Some(room);
-                eprint!("{}", "action: ".bold());
+    }
+    match response {
+        Response::NotLoggedIn => {
+            state.userid = None;
+            state.token = None;
+            state.room = None;
+            state.resume_roomid = None;
+            state.prompt("action: ");
+        }
+        Response::LoggedIn(userid) => {
+            state.userid = Some(userid);
+            state.room = None;
+            // The login message is followed by another message anyway.
+        }
+        Response::Session(token) => {
+            state.token = Some(token);
+        }
+        Response::Room(mut room) => {
+            let same_room = state
+                .room
+                .as_ref()
+                .is_some_and(|previous| previous.roomid == room.roomid);
+            let mut alerts = vec![];
+            if let (Some(previous), Some(game)) =
+                (state.room.as_ref().and_then(Room::game), room.game_mut())
+            {
+                game.keep_display(previous);
+                if same_room {
+                    alerts = game.alerts_since(previous);
+                }
+            }
+            for alert in alerts {
+                if state.accessibility.no_flash {
+                    eprintln!("{}", format!("!!! {alert} !!!").bold());
+                } else {
+                    eprintln!(
+                        "{}{}",
+                        7 as char,
+                        format!(" !!! {alert} !!! ").on_red().bold()
+                    );
+                }
+            }
+            let replay_requested = state
+                .replay
+                .as_ref()
+                .is_some_and(|replay| replay.roomid == room.roomid && replay.game.is_none());
+            if replay_requested {
+                if let RoomState::Ended(Some(game)) = &room.state {
+                    state.replay.as_mut().unwrap().game = Some(game.clone());
+                } else {
+                    state.replay = None;
+                    eprintln!(" Error: {}", "The game has not ended yet".bold());
+                }
+            }
+            state.resume_roomid = Some(room.roomid);
+            state.room = Some(room);
+            if state
+                .replay
+                .as_ref()
+                .is_some_and(|replay| replay.game.is_some())
+            {
+                state.show_replay();
+                state.save();
+                return;
+            }
+            state.show_status();
+            state.prompt("action: ");
+            if !state.accessibility.no_flash {
                 eprint!("{}", 7 as char);
             }
-            _ => {
-                state.lock().unwrap().room = None;
-                eprint!("{}", "action: ".bold());
+        }
+        Response::Feed { entries, .. } => {
+            if entries.is_empty() {
+                return;
             }
-        };
-    });
-
-    pin_mut!(stdin_to_ws, ws_to_stdout);
-    future::select(stdin_to_ws, ws_to_stdout).await;
+            // Feed entries do not change the room.
+            state.unread += entries.len();
+            state.show_status();
+            state.prompt("action: ");
+            return;
+        }
+        Response::Error(_)
+        | Response::LimitExceeded(_)
+        | Response::GameFinished(_)
+        | Response::MoveProbe(_)
+        | Response::Premove(..)
+        | Response::PresenceSharing(_)
+        | Response::Vacation(_)
+        | Response::Notify(_)
+        | Response::Registered
+        | Response::ServerRestarting => {
+            // Errors do not change the room.
+            state.prompt("action: ");
+            return;
+        }
+        Response::RoomList(..) if state.room.is_none() => {
+            // Sent after logging in, possibly before the room is watched again.
+            state.prompt("action: ");
+        }
+        _ => {
+            state.room = None;
+            state.resume_roomid = None;
+            state.prompt("action: ");
+        }
+    };
+    state.save();
 }
 
 async fn read_user_input<Game: GameT>(
-    tx: futures_channel::mpsc::UnboundedSender<Message>,
     state: Arc<Mutex<ClientState<Game>>>,
+    mut lines: BoxStream<'static, String>,
 ) {
-    let stdin = tokio::io::stdin();
-    let mut lines = FramedRead::new(stdin, LinesCodec::new());
     loop {
-        let action: ClientOrServerAction<Game> = loop {
+        let (line, action): (String, ClientOrServerAction<Game>) = loop {
             let line = lines.next().await;
             let Some(line) = line else {
+                let mut state = state.lock().unwrap();
+                state.stdin_closed = true;
+                // Closing the channel ends the connection.
+                state.sender = None;
                 return;
             };
-            if line.is_err() {
+            let mut state = state.lock().unwrap();
+            state.unread = 0;
+            if state.confirm_replay {
+                match line.trim() {
+                    "y" | "yes" => {
+                        state.confirm_replay = false;
+                        for (line, message) in std::mem::take(&mut state.queued) {
+                            state.send(line, message);
+                        }
+                    }
+                    "n" | "no" => {
+                        state.confirm_replay = false;
+                        state.queued.clear();
+                        eprintln!("Dropped the queued actions.");
+                        state.prompt("action: ");
+                    }
+                    _ => state.prompt("Send the queued actions? [y/n] "),
+                }
                 continue;
             }
-            match line.unwrap().parse() {
-                Ok(action) => break action,
+            if let Some(ReplayState {
+                turn,
+                game: Some(game),
+                analyze,
+                analysis,
+                ..
+            }) = &mut state.replay
+            {
+                let moves = game.num_moves();
+                match line.trim() {
+                    "" | "n" | "next" => *turn = (*turn + 1).min(moves),
+                    "p" | "prev" => *turn = turn.saturating_sub(1),
+                    "a" | "analyze" => *analyze = !*analyze,
+                    "q" | "quit" => {
+                        state.replay = None;
+                        state.prompt("action: ");
+                        continue;
+                    }
+                    s => match s.parse() {
+                        Ok(n) if n <= moves => *turn = n,
+                        _ => eprintln!(
+                            " Error: {}",
+                            format!("Pick a move from 0 to {moves}").bold()
+                        ),
+                    },
+                }
+                // The move that led to the board, which the first board lacks.
+                *analysis = (*analyze && *turn > 0).then(|| {
+                    game.analyze_move(*turn)
+                        .unwrap_or_else(|err| format!(" Error: {}", err.bold()))
+                });
+                state.show_replay();
+                continue;
+            }
+            if let Some((move_line, message)) = state.confirm_move.take() {
+                match line.trim() {
+                    "y" | "yes" => state.send(move_line, message),
+                    "n" | "no" => state.prompt("action: "),
+                    _ => {
+                        state.confirm_move = Some((move_line, message));
+                        state.prompt("Send the move anyway? [y/n] ");
+                    }
+                }
+                continue;
+            }
+            let game = state.room.as_ref().and_then(Room::game);
+            match ClientOrServerAction::parse_in(&line, game) {
+                Ok(action) => break (line, action),
                 Err(err) => {
                     eprintln!("Error: {err}");
                     eprintln!("Possible actions:");
-                    eprintln!(" action (lobby): login <username> | logout | new <min> <max> <settings> | join <roomid> | watch <roomid>");
-                    eprintln!(" action (game):  join | leave | start");
+                    eprintln!(" action (lobby): login <username> [password <password>] [symbols] [no-flash] [wide] | logout | new <min> <max> [timer <seconds> [skip|discard]] [password <password>] [seats random|join|host] [hide-deck] <settings> | join <roomid> [password] | watch <roomid> [password] | history [username] | leaderboard | replay <roomid> [password] | audit <roomid> | presence <room|online|off> | list [correspondence|live] [pending|started|ended] [mine] [page <n>] | vacation <hours|off> | notify <address|off> | register <password>");
+                    eprintln!(" action (game):  join | leave | start | forfeit | undo [no] | kick <username> | check <move> | premove <move|off> | note <index> [text] | review <move|next|prev|end> | chat <message> | spectator-chat <shared|private> | resync");
+                    eprintln!(" host   (game):  settings <settings> | seats <usernames> | reminders <hours> [chat] [offer <hours>] | reminders off | correspondence <hours per move> <bank hours> | correspondence off | spectators <hands|hidden> | bot | skip | substitute <username>");
                     eprintln!(" move   (game):  {}", Game::move_help());
-                    eprint!(" ");
+                    state.prompt(" ");
                 }
             }
         };
 
         match action {
-            ClientOrServerAction::ServerAction(action) => {
-                let message = Message::Binary(serde_json::to_vec(&action).unwrap());
-                tx.unbounded_send(message).unwrap();
+            ClientOrServerAction::ServerAction(mut action) => {
+                let mut state = state.lock().unwrap();
+                if let (
+                    Action::MakeMove(mov) | Action::ProbeMove(mov) | Action::Premove(Some(mov)),
+                    Some(game),
+                    Some(userid),
+                ) = (
+                    &mut action,
+                    state.room.as_ref().and_then(Room::game),
+                    &state.userid,
+                ) {
+                    *mov = game.pin_move(userid, mov.clone());
+                }
+                let message = Message::Binary(serde_json::to_vec(&action).unwrap().into());
+                match &action {
+                    Action::Login(_, password, accessibility) => {
+                        state.password = password.clone();
+                        state.accessibility = *accessibility;
+                    }
+                    Action::Register(password) => state.password = Some(password.clone()),
+                    _ => {}
+                }
+                if let Action::MakeMove(mov) = &action {
+                    // Catch mistakes locally, before the server sees the move.
+                    let game = state.room.as_ref().and_then(Room::game);
+                    let check = match (game, &state.userid) {
+                        (Some(game), Some(userid)) => game.check_move(userid, mov),
+                        _ => Ok(vec![]),
+                    };
+                    match check {
+                        Err(err) => {
+                            eprintln!(" Error: {}", err.bold());
+                            state.prompt("action: ");
+                            continue;
+                        }
+                        Ok(warnings) if !warnings.is_empty() => {
+                            for warning in warnings {
+                                eprintln!(" Warning: {}", warning.bold());
+                            }
+                            state.prompt("Send the move anyway? [y/n] ");
+                            state.confirm_move = Some((line, message));
+                            continue;
+                        }
+                        Ok(_) => {}
+                    }
+                }
+                state.send(line, message);
+            }
+            ClientOrServerAction::Replay(roomid, password) => {
+                let mut state = state.lock().unwrap();
+                state.replay = Some(ReplayState {
+                    roomid,
+                    turn: 0,
+                    game: None,
+                    analyze: false,
+                    analysis: None,
+                });
+                let message = Message::Binary(
+                    serde_json::to_vec(&Action::<Game>::WatchRoom(roomid, password))
+                        .unwrap()
+                        .into(),
+                );
+                state.send(line, message);
             }
             ClientOrServerAction::ClientAction(action) => {
-                if let Some(room) = &mut state.lock().unwrap().room {
+                let mut state = state.lock().unwrap();
+                if let Some(room) = &mut state.room {
                     match &mut room.state {
-                        crate::types::RoomState::WaitingForPlayers { .. } => {
+                        RoomState::WaitingForPlayers { .. } => {
                             eprintln!(" Error: {}", "Game didn't start yet".bold())
                         }
-                        crate::types::RoomState::Started(Some(game))
-                        | crate::types::RoomState::Ended(Some(game)) => {
+                        RoomState::Started(Some(game)) | RoomState::Ended(Some(game)) => {
                             game.do_client_action(action);
                         }
                         _ => unreachable!("Game should be set."),
@@ -139,7 +725,7 @@ async fn read_user_input<Game: GameT>(
                 } else {
                     eprintln!(" Error: {}", "Not in a room".bold());
                 }
-                eprint!("{}", "action: ".bold());
+                state.prompt("action: ");
             }
         };
     }
@@ -1,5 +1,13 @@
+#[cfg(feature = "net")]
 pub mod client;
+#[cfg(feature = "net")]
+pub mod logging;
+mod registry;
+#[cfg(feature = "net")]
 pub mod server;
+pub mod style;
+#[cfg(feature = "net")]
+mod tui;
 pub mod types;
 
 use serde::{de::DeserializeOwned, Serialize};
@@ -7,20 +15,135 @@ use std::{
     fmt::{Debug, Display},
     str::FromStr,
 };
+use types::{Accessibility, SpectatorView, TimeoutMove};
 
+#[cfg(feature = "net")]
 pub use client::start_client;
-pub use server::start_server;
+#[cfg(feature = "net")]
+pub use server::{start_server, Demo, Limits, Tls};
 
 /// Trait that supported games must implement.
 pub trait GameT:
     Sized + Debug + Display + Serialize + DeserializeOwned + Clone + Send + 'static
 {
     type Settings: Debug + Display + Serialize + DeserializeOwned + Clone + FromStr + Send;
-    type Move: Debug + Serialize + DeserializeOwned + Clone + FromStr<Err = &'static str>;
+    type Move: Debug + Serialize + DeserializeOwned + Clone + FromStr<Err = &'static str> + Send;
     type ClientAction: Debug + Serialize + DeserializeOwned + Clone + FromStr<Err = &'static str>;
-    fn new(player_names: Vec<String>, settings: Self::Settings) -> Self;
-    fn make_move(&mut self, player: &String, mov: Self::Move) -> Result<(), &'static str>;
+    fn new(player_names: Vec<String>, settings: Self::Settings) -> Result<Self, &'static str>;
+    /// Like `new`, but players sit in the given order and the first one
+    /// starts.
+    fn new_in_order(
+        _player_names: Vec<String>,
+        _settings: Self::Settings,
+    ) -> Result<Self, &'static str> {
+        Err("Fixed seating is not supported")
+    }
+    /// The settings without what players may not know until the game ends,
+    /// like the seed of the deal.
+    fn public_settings(settings: &Self::Settings) -> Self::Settings {
+        settings.clone()
+    }
+    fn make_move(&mut self, player: &str, mov: Self::Move) -> Result<(), &'static str>;
     fn do_client_action(&mut self, action: Self::ClientAction);
-    fn to_view(&self, player: &String) -> Self;
+    /// Parse a move in the context of this game, e.g. with its custom names.
+    fn parse_move(&self, s: &str) -> Result<Self::Move, &'static str> {
+        s.parse()
+    }
+    /// Check a move of `player` against a view of the game without making
+    /// it. Errors are moves the server would refuse; the returned warnings
+    /// describe legal moves that are probably mistakes.
+    fn check_move(&self, _player: &str, _mov: &Self::Move) -> Result<Vec<String>, &'static str> {
+        Ok(vec![])
+    }
+    /// Make the move of `player` refer to the objects it touches, e.g. cards,
+    /// by stable ids from this view rather than by position, so it still
+    /// means the same if the view is outdated when the server gets it.
+    fn pin_move(&self, _player: &str, mov: Self::Move) -> Self::Move {
+        mov
+    }
+    fn to_view(&self, player: &str) -> Self;
+    /// The view of someone who does not play in the game, which must not
+    /// reveal anything the players may not know together.
+    fn to_spectator_view(&self, view: SpectatorView) -> Self;
+    /// Remember the accessibility preferences of the viewer of this view, so
+    /// that it is rendered accordingly.
+    fn set_accessibility(&mut self, _accessibility: Accessibility) {}
+    fn has_ended(&self) -> bool;
+    /// Stop the game early. Afterwards `has_ended` and `is_abandoned` return true.
+    fn abandon(&mut self);
+    fn is_abandoned(&self) -> bool;
+    /// Name of the player whose turn it is, if the game has one.
+    fn current_player(&self) -> Option<String> {
+        None
+    }
+    /// Make a move on behalf of the current player, who is not responding.
+    fn skip_turn(&mut self) -> Result<(), &'static str> {
+        Err("Skipping turns is not supported")
+    }
+    /// Make the move of `on_timeout` for the current player, whose turn
+    /// timer ran out.
+    fn time_out(&mut self, _on_timeout: TimeoutMove) -> Result<(), &'static str> {
+        Err("Turn timers are not supported")
+    }
+    /// Take back the last move.
+    fn undo(&mut self) -> Result<(), &'static str> {
+        Err("Undoing moves is not supported")
+    }
+    /// Give the seat of player `old` to player `new`.
+    fn replace_player(&mut self, _old: &str, _new: &str) -> Result<(), &'static str> {
+        Err("Substituting players is not supported")
+    }
+    /// Number of moves made so far.
+    fn num_moves(&self) -> usize {
+        0
+    }
+    /// The game as it was after its first `turn` moves, for stepping through
+    /// finished games.
+    fn replay(&self, _turn: usize) -> Result<Self, &'static str> {
+        Err("Replays are not supported")
+    }
+    /// Commentary on move `turn`, counting from 1, of a finished game for the
+    /// analysis mode of replays, e.g. a better move a bot suggests.
+    fn analyze_move(&self, _turn: usize) -> Result<String, &'static str> {
+        Err("Analysis is not supported")
+    }
+    fn score(&self) -> usize;
+    /// Human-readable description of the last move, for the activity feed,
+    /// which shows it without styles.
+    fn last_move_description(&self) -> Option<String> {
+        None
+    }
+    /// One-line summary of the game, shown in the client's status line.
+    fn status(&self) -> String {
+        String::new()
+    }
+    /// The cards that stay hidden from everyone until the game ends, like
+    /// those left in the deck, for rooms that reveal them afterwards.
+    fn hidden_cards(&self) -> Option<String> {
+        None
+    }
+    /// Forget the cards that `hidden_cards` shows, and anything that reveals
+    /// them like the seed of the deal, for rooms that keep them hidden.
+    fn forget_hidden_cards(&mut self) {}
+    /// Ids of the cards in the hand of `player`, in hand order, that stay the
+    /// same while a card moves within the hand. Notes are kept by these ids.
+    fn hand_card_ids(&self, _player: &str) -> Vec<usize> {
+        vec![]
+    }
+    /// Whether the last move touched what the move of `player` refers to,
+    /// e.g. a hint touched the card a premove plays, so that the move may no
+    /// longer be what the player wants.
+    fn last_move_touches(&self, _player: &str, _mov: &Self::Move) -> bool {
+        false
+    }
+    /// Critical moments since the `previous` view of the game, such as lost
+    /// lives, which the client highlights beyond the usual turn notification.
+    fn alerts_since(&self, _previous: &Self) -> Vec<String> {
+        vec![]
+    }
+    /// Keep the display choices that client actions made on the `previous`
+    /// view of the game, e.g. a different rendering, in the client that
+    /// receives this view.
+    fn keep_display(&mut self, _previous: &Self) {}
     fn move_help() -> &'static str;
 }
@@ -0,0 +1,273 @@
+//! A small `tracing` subscriber for the server, which writes events to
+//! stderr as text or as JSON lines, along with the fields of the spans they
+//! happened in: `connection` for each socket and `room` for each room.
+
+use crate::style::strip_styles;
+use crate::types::{format_timestamp, now};
+use itertools::Itertools;
+use serde_json::{Map, Value};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tracing::field::{Field, Visit};
+use tracing::level_filters::LevelFilter;
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+pub use tracing::Level;
+
+/// How log lines are written.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum LogFormat {
+    /// Lines for humans, like
+    /// `2026-01-01 12:00:00  INFO connection{client=1.2.3.4:5}: connected`.
+    #[default]
+    Text,
+    /// One JSON object per line, for log collectors.
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err("The log format must be text or json"),
+        }
+    }
+}
+
+/// Write all events at `level` and more severe to stderr.
+pub fn init(level: Level, format: LogFormat) {
+    let logger = Logger {
+        level,
+        format,
+        next_id: AtomicU64::new(1),
+        spans: Default::default(),
+    };
+    tracing::subscriber::set_global_default(logger).expect("Logging was already set up");
+}
+
+type Fields = Vec<(&'static str, Value)>;
+
+struct SpanData {
+    name: &'static str,
+    fields: Fields,
+    parent: Option<Id>,
+    /// Number of handles to the span, including those of its children.
+    refs: usize,
+}
+
+struct Logger {
+    level: Level,
+    format: LogFormat,
+    next_id: AtomicU64,
+    spans: Mutex<HashMap<Id, SpanData>>,
+}
+
+thread_local! {
+    /// The spans entered on this thread, innermost last.
+    static ENTERED: RefCell<Vec<Id>> = const { RefCell::new(vec![]) };
+}
+
+fn current_span() -> Option<Id> {
+    ENTERED.with(|entered| entered.borrow().last().cloned())
+}
+
+struct FieldVisitor<'a>(&'a mut Fields);
+
+impl Visit for FieldVisitor<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.0.push((
+            field.name(),
+            Value::String(strip_styles(&format!("{value:?}"))),
+        ));
+    }
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0
+            .push((field.name(), Value::from(strip_styles(value))));
+    }
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.push((field.name(), Value::from(value)));
+    }
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.push((field.name(), Value::from(value)));
+    }
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.push((field.name(), Value::from(value)));
+    }
+}
+
+/// `key=value` pairs, with strings unquoted.
+fn format_fields(fields: &Fields) -> String {
+    fields
+        .iter()
+        .map(|(name, value)| match value {
+            Value::String(value) => format!("{name}={value}"),
+            value => format!("{name}={value}"),
+        })
+        .join(" ")
+}
+
+impl Logger {
+    /// The names and fields of the span and its ancestors, outermost first.
+    fn scope(&self, mut span: Option<Id>) -> Vec<(&'static str, Fields)> {
+        let spans = self.spans.lock().unwrap();
+        let mut scope = vec![];
+        while let Some(data) = span.and_then(|span| spans.get(&span)) {
+            scope.push((data.name, data.fields.clone()));
+            span = data.parent.clone();
+        }
+        scope.reverse();
+        scope
+    }
+
+    fn write(&self, level: &Level, mut fields: Fields, scope: Vec<(&'static str, Fields)>) {
+        let now = now();
+        let time = format!("{}:{:02}", format_timestamp(now), now % 60);
+        let message = fields
+            .iter()
+            .position(|(name, _)| *name == "message")
+            .map(|i| fields.remove(i).1);
+        let message = match message {
+            Some(Value::String(message)) => message,
+            Some(message) => message.to_string(),
+            None => String::new(),
+        };
+        let line = match self.format {
+            LogFormat::Text => {
+                let mut line = format!("{time} {level:>5} ");
+                for (name, fields) in &scope {
+                    line += name;
+                    if !fields.is_empty() {
+                        line += &format!("{{{}}}", format_fields(fields));
+                    }
+                    line += ": ";
+                }
+                line += &message;
+                if !fields.is_empty() {
+                    line += " ";
+                    line += &format_fields(&fields);
+                }
+                line
+            }
+            LogFormat::Json => {
+                let mut object = Map::new();
+                object.insert("time".into(), time.into());
+                object.insert("level".into(), level.as_str().into());
+                object.insert("message".into(), message.into());
+                for (name, value) in fields {
+                    object.insert(name.into(), value);
+                }
+                let spans = scope.into_iter().map(|(name, fields)| {
+                    let mut span = Map::new();
+                    span.insert("name".into(), name.into());
+                    for (name, value) in fields {
+                        span.insert(name.into(), value);
+                    }
+                    Value::Object(span)
+                });
+                object.insert("spans".into(), spans.collect());
+                Value::Object(object).to_string()
+            }
+        };
+        eprintln!("{line}");
+    }
+}
+
+impl Subscriber for Logger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        *metadata.level() <= self.level
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        Some(LevelFilter::from_level(self.level))
+    }
+
+    fn new_span(&self, attrs: &Attributes<'_>) -> Id {
+        let id = Id::from_u64(self.next_id.fetch_add(1, Ordering::Relaxed));
+        let mut fields = vec![];
+        attrs.record(&mut FieldVisitor(&mut fields));
+        let parent = if attrs.is_contextual() {
+            current_span()
+        } else {
+            attrs.parent().cloned()
+        };
+        let mut spans = self.spans.lock().unwrap();
+        // A span keeps its parent alive, to show it with its events.
+        if let Some(parent) = parent.as_ref().and_then(|parent| spans.get_mut(parent)) {
+            parent.refs += 1;
+        }
+        spans.insert(
+            id.clone(),
+            SpanData {
+                name: attrs.metadata().name(),
+                fields,
+                parent,
+                refs: 1,
+            },
+        );
+        id
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        if let Some(data) = self.spans.lock().unwrap().get_mut(span) {
+            values.record(&mut FieldVisitor(&mut data.fields));
+        }
+    }
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = vec![];
+        event.record(&mut FieldVisitor(&mut fields));
+        let parent = if event.is_contextual() {
+            current_span()
+        } else {
+            event.parent().cloned()
+        };
+        self.write(event.metadata().level(), fields, self.scope(parent));
+    }
+
+    fn enter(&self, span: &Id) {
+        ENTERED.with(|entered| entered.borrow_mut().push(span.clone()));
+    }
+
+    fn exit(&self, span: &Id) {
+        ENTERED.with(|entered| {
+            let mut entered = entered.borrow_mut();
+            if let Some(i) = entered.iter().rposition(|id| id == span) {
+                entered.remove(i);
+            }
+        });
+    }
+
+    fn clone_span(&self, span: &Id) -> Id {
+        if let Some(data) = self.spans.lock().unwrap().get_mut(span) {
+            data.refs += 1;
+        }
+        span.clone()
+    }
+
+    fn try_close(&self, span: Id) -> bool {
+        let mut spans = self.spans.lock().unwrap();
+        let mut closing = Some(span.clone());
+        let mut closed = false;
+        while let Some(id) = closing.take() {
+            let Some(data) = spans.get_mut(&id) else {
+                break;
+            };
+            data.refs -= 1;
+            if data.refs == 0 {
+                closing = spans.remove(&id).unwrap().parent;
+                closed |= id == span;
+            }
+        }
+        closed
+    }
+}
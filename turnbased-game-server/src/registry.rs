@@ -0,0 +1,378 @@
+//! Several games behind one server: `game_registry!` defines an enum of
+//! games that implements `GameT` by passing everything on to the game of each
+//! room, so `start_server` and `start_client` can be used with it as with a
+//! single game.
+//!
+//! The settings of a room start with the name of its game, e.g.
+//! `new 2 5 hanabi multi`, which the room list shows as well. Settings without
+//! a name are for the first registered game that accepts them.
+//!
+//! Games and settings are serialized as those of the game itself, so state
+//! files of a server that hosted a single game can still be loaded.
+
+/// Define an enum of games, with enums of their settings, moves and client
+/// actions, that implements `GameT`. Each game is given by its variant, type
+/// and name:
+///
+/// ```ignore
+/// turnbased_game_server::game_registry! {
+///     pub enum Games {
+///         settings: GameSettings,
+///         moves: GameMove,
+///         client_actions: GameClientAction,
+///         Hanabi(hanabi::Game) = "hanabi",
+///     }
+/// }
+/// ```
+///
+/// The crate using it must depend on `serde`.
+#[macro_export]
+macro_rules! game_registry {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident {
+            settings: $settings:ident,
+            moves: $move:ident,
+            client_actions: $action:ident,
+            $($variant:ident($game:ty) = $key:literal),+ $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+        #[serde(untagged)]
+        $vis enum $name {
+            $($variant($game)),+
+        }
+
+        /// Settings of a room of one of the games.
+        #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+        #[serde(untagged)]
+        $vis enum $settings {
+            $($variant(<$game as $crate::GameT>::Settings)),+
+        }
+
+        /// A move in one of the games.
+        #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+        $vis enum $move {
+            $($variant(<$game as $crate::GameT>::Move)),+
+        }
+
+        /// A client action of one of the games.
+        #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+        $vis enum $action {
+            $($variant(<$game as $crate::GameT>::ClientAction)),+
+        }
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                match self {
+                    $($name::$variant(game) => std::fmt::Display::fmt(game, f)),+
+                }
+            }
+        }
+
+        impl std::fmt::Display for $settings {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                match self {
+                    $($settings::$variant(settings) => f.pad(&format!("{} {settings}", $key))),+
+                }
+            }
+        }
+
+        impl std::str::FromStr for $settings {
+            type Err = &'static str;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                let s = s.trim();
+                let (key, rest) = s.split_once(' ').unwrap_or((s, ""));
+                $(
+                    if key == $key {
+                        return rest
+                            .parse()
+                            .map($settings::$variant)
+                            .map_err(|_| concat!("Invalid settings for ", $key));
+                    }
+                )+
+                $(
+                    if let Ok(settings) = s.parse() {
+                        return Ok($settings::$variant(settings));
+                    }
+                )+
+                Err(concat!("Invalid settings; start them with one of:", $(" ", $key),+))
+            }
+        }
+
+        impl std::str::FromStr for $move {
+            type Err = &'static str;
+
+            /// The move of the first game that accepts it. Rooms parse moves
+            /// with `GameT::parse_move` instead, which knows their game.
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                let mut err = None;
+                $(
+                    match s.parse() {
+                        Ok(mov) => return Ok($move::$variant(mov)),
+                        Err(e) => {
+                            err.get_or_insert(e);
+                        }
+                    }
+                )+
+                Err(err.unwrap())
+            }
+        }
+
+        impl std::str::FromStr for $action {
+            type Err = &'static str;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                let mut err = None;
+                $(
+                    match s.parse() {
+                        Ok(action) => return Ok($action::$variant(action)),
+                        Err(e) => {
+                            err.get_or_insert(e);
+                        }
+                    }
+                )+
+                Err(err.unwrap())
+            }
+        }
+
+        impl $crate::GameT for $name {
+            type Settings = $settings;
+            type Move = $move;
+            type ClientAction = $action;
+
+            fn new(player_names: Vec<String>, settings: $settings) -> Result<Self, &'static str> {
+                match settings {
+                    $($settings::$variant(settings) => {
+                        <$game as $crate::GameT>::new(player_names, settings).map($name::$variant)
+                    })+
+                }
+            }
+            fn new_in_order(
+                player_names: Vec<String>,
+                settings: $settings,
+            ) -> Result<Self, &'static str> {
+                match settings {
+                    $($settings::$variant(settings) => {
+                        <$game as $crate::GameT>::new_in_order(player_names, settings)
+                            .map($name::$variant)
+                    })+
+                }
+            }
+            fn public_settings(settings: &$settings) -> $settings {
+                match settings {
+                    $($settings::$variant(settings) => {
+                        $settings::$variant(<$game as $crate::GameT>::public_settings(settings))
+                    })+
+                }
+            }
+            fn make_move(&mut self, player: &str, mov: $move) -> Result<(), &'static str> {
+                #[allow(unreachable_patterns)]
+                match (self, mov) {
+                    $(($name::$variant(game), $move::$variant(mov)) => {
+                        <$game as $crate::GameT>::make_move(game, player, mov)
+                    })+
+                    _ => Err("This move is for another game"),
+                }
+            }
+            fn do_client_action(&mut self, action: $action) {
+                #[allow(unreachable_patterns)]
+                match (self, action) {
+                    $(($name::$variant(game), $action::$variant(action)) => {
+                        <$game as $crate::GameT>::do_client_action(game, action)
+                    })+
+                    _ => {}
+                }
+            }
+            fn parse_move(&self, s: &str) -> Result<$move, &'static str> {
+                match self {
+                    $($name::$variant(game) => {
+                        <$game as $crate::GameT>::parse_move(game, s).map($move::$variant)
+                    }),+
+                }
+            }
+            fn check_move(&self, player: &str, mov: &$move) -> Result<Vec<String>, &'static str> {
+                #[allow(unreachable_patterns)]
+                match (self, mov) {
+                    $(($name::$variant(game), $move::$variant(mov)) => {
+                        <$game as $crate::GameT>::check_move(game, player, mov)
+                    })+
+                    _ => Err("This move is for another game"),
+                }
+            }
+            fn pin_move(&self, player: &str, mov: $move) -> $move {
+                #[allow(unreachable_patterns)]
+                match (self, mov) {
+                    $(($name::$variant(game), $move::$variant(mov)) => {
+                        $move::$variant(<$game as $crate::GameT>::pin_move(game, player, mov))
+                    })+
+                    (_, mov) => mov,
+                }
+            }
+            fn to_view(&self, player: &str) -> Self {
+                match self {
+                    $($name::$variant(game) => {
+                        $name::$variant(<$game as $crate::GameT>::to_view(game, player))
+                    }),+
+                }
+            }
+            fn to_spectator_view(&self, view: $crate::types::SpectatorView) -> Self {
+                match self {
+                    $($name::$variant(game) => {
+                        $name::$variant(<$game as $crate::GameT>::to_spectator_view(game, view))
+                    }),+
+                }
+            }
+            fn set_accessibility(&mut self, accessibility: $crate::types::Accessibility) {
+                match self {
+                    $($name::$variant(game) => {
+                        <$game as $crate::GameT>::set_accessibility(game, accessibility)
+                    }),+
+                }
+            }
+            fn has_ended(&self) -> bool {
+                match self {
+                    $($name::$variant(game) => <$game as $crate::GameT>::has_ended(game)),+
+                }
+            }
+            fn abandon(&mut self) {
+                match self {
+                    $($name::$variant(game) => <$game as $crate::GameT>::abandon(game)),+
+                }
+            }
+            fn is_abandoned(&self) -> bool {
+                match self {
+                    $($name::$variant(game) => <$game as $crate::GameT>::is_abandoned(game)),+
+                }
+            }
+            fn current_player(&self) -> Option<String> {
+                match self {
+                    $($name::$variant(game) => <$game as $crate::GameT>::current_player(game)),+
+                }
+            }
+            fn skip_turn(&mut self) -> Result<(), &'static str> {
+                match self {
+                    $($name::$variant(game) => <$game as $crate::GameT>::skip_turn(game)),+
+                }
+            }
+            fn time_out(
+                &mut self,
+                on_timeout: $crate::types::TimeoutMove,
+            ) -> Result<(), &'static str> {
+                match self {
+                    $($name::$variant(game) => {
+                        <$game as $crate::GameT>::time_out(game, on_timeout)
+                    }),+
+                }
+            }
+            fn undo(&mut self) -> Result<(), &'static str> {
+                match self {
+                    $($name::$variant(game) => <$game as $crate::GameT>::undo(game)),+
+                }
+            }
+            fn replace_player(&mut self, old: &str, new: &str) -> Result<(), &'static str> {
+                match self {
+                    $($name::$variant(game) => {
+                        <$game as $crate::GameT>::replace_player(game, old, new)
+                    }),+
+                }
+            }
+            fn num_moves(&self) -> usize {
+                match self {
+                    $($name::$variant(game) => <$game as $crate::GameT>::num_moves(game)),+
+                }
+            }
+            fn replay(&self, turn: usize) -> Result<Self, &'static str> {
+                match self {
+                    $($name::$variant(game) => {
+                        <$game as $crate::GameT>::replay(game, turn).map($name::$variant)
+                    }),+
+                }
+            }
+            fn analyze_move(&self, turn: usize) -> Result<String, &'static str> {
+                match self {
+                    $($name::$variant(game) => <$game as $crate::GameT>::analyze_move(game, turn)),+
+                }
+            }
+            fn score(&self) -> usize {
+                match self {
+                    $($name::$variant(game) => <$game as $crate::GameT>::score(game)),+
+                }
+            }
+            fn last_move_description(&self) -> Option<String> {
+                match self {
+                    $($name::$variant(game) => {
+                        <$game as $crate::GameT>::last_move_description(game)
+                    }),+
+                }
+            }
+            fn status(&self) -> String {
+                match self {
+                    $($name::$variant(game) => <$game as $crate::GameT>::status(game)),+
+                }
+            }
+            fn hidden_cards(&self) -> Option<String> {
+                match self {
+                    $($name::$variant(game) => <$game as $crate::GameT>::hidden_cards(game)),+
+                }
+            }
+            fn forget_hidden_cards(&mut self) {
+                match self {
+                    $($name::$variant(game) => <$game as $crate::GameT>::forget_hidden_cards(game)),+
+                }
+            }
+            fn hand_card_ids(&self, player: &str) -> Vec<usize> {
+                match self {
+                    $($name::$variant(game) => {
+                        <$game as $crate::GameT>::hand_card_ids(game, player)
+                    }),+
+                }
+            }
+            fn last_move_touches(&self, player: &str, mov: &$move) -> bool {
+                #[allow(unreachable_patterns)]
+                match (self, mov) {
+                    $(($name::$variant(game), $move::$variant(mov)) => {
+                        <$game as $crate::GameT>::last_move_touches(game, player, mov)
+                    })+
+                    _ => false,
+                }
+            }
+            fn alerts_since(&self, previous: &Self) -> Vec<String> {
+                #[allow(unreachable_patterns)]
+                match (self, previous) {
+                    $(($name::$variant(game), $name::$variant(previous)) => {
+                        <$game as $crate::GameT>::alerts_since(game, previous)
+                    })+
+                    _ => vec![],
+                }
+            }
+            fn keep_display(&mut self, previous: &Self) {
+                #[allow(unreachable_patterns)]
+                match (self, previous) {
+                    $(($name::$variant(game), $name::$variant(previous)) => {
+                        <$game as $crate::GameT>::keep_display(game, previous)
+                    })+
+                    _ => {}
+                }
+            }
+            /// The moves of each game, prefixed by its name if there are
+            /// several.
+            fn move_help() -> &'static str {
+                static HELP: std::sync::OnceLock<String> = std::sync::OnceLock::new();
+                HELP.get_or_init(|| {
+                    let helps = [$(($key, <$game as $crate::GameT>::move_help())),+];
+                    if let [(_, help)] = helps {
+                        return help.to_string();
+                    }
+                    let helps: Vec<_> = helps
+                        .iter()
+                        .map(|(key, help)| format!("{key}: {help}"))
+                        .collect();
+                    helps.join("\n                 ")
+                })
+            }
+        }
+    };
+}
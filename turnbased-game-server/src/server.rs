@@ -1,26 +1,151 @@
+use crate::style::strip_styles;
 use crate::types::*;
 use crate::GameT;
+use argon2::password_hash::{PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use axum::extract::ws::{
+    rejection::WebSocketUpgradeRejection, Message, WebSocket, WebSocketUpgrade,
+};
+use axum::extract::{ConnectInfo, Path, State};
+use axum::http::{Method, StatusCode};
+use axum::response::{Html, IntoResponse};
+use axum::routing::get;
+use axum::{Extension, Json, Router};
 use futures_channel::mpsc::{unbounded, UnboundedSender};
-use futures_util::{future, pin_mut, stream::TryStreamExt, StreamExt};
-use std::collections::HashMap;
+use futures_util::{pin_mut, stream::TryStreamExt, StreamExt};
+use hyper::server::conn::http1;
+use hyper_util::rt::{TokioIo, TokioTimer};
+use hyper_util::service::TowerToHyperService;
+use itertools::Itertools;
+use rand::{distributions::Alphanumeric, Rng};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::net::IpAddr;
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
-use tokio::net::{TcpListener, TcpStream};
-use tokio_tungstenite::tungstenite::protocol::Message;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::task::{spawn_blocking, JoinHandle};
+use tokio_rustls::{rustls, TlsAcceptor};
+use tracing::Instrument;
 
+#[derive(Serialize, Deserialize)]
 struct User {
     //userid: UserId,
     // TODO: Fill this
     //rooms: Vec<RoomId>,
+    #[serde(skip)]
     sockets: Vec<ClientId>,
+    presence: PresenceSharing,
+    accessibility: Accessibility,
+    /// Unix timestamp until which the clocks of the user are paused.
+    vacation_until: Option<u64>,
+    /// Where to notify the user of their turn in correspondence rooms.
+    contact: Option<String>,
+    /// Sessions that can be resumed, oldest first.
+    #[serde(default)]
+    sessions: Vec<Session>,
+    /// Password needed to log in as the user, if they registered.
+    #[serde(default)]
+    password: Option<PasswordHash>,
 }
 
+/// A password hashed with Argon2id, as a PHC string that includes the salt
+/// and the parameters.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+struct PasswordHash(String);
+
+impl PasswordHash {
+    /// Hash the password with a new salt. This takes a while and 19MiB of
+    /// memory, so it runs on a blocking thread without the state locked.
+    fn new(password: &str) -> Self {
+        let salt: [u8; 16] = rand::thread_rng().gen();
+        let salt = SaltString::encode_b64(&salt).unwrap();
+        let hash = Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .unwrap();
+        Self(hash.to_string())
+    }
+
+    /// Whether this is the hash of the password. Takes as long as `new`.
+    fn matches(&self, password: &str) -> bool {
+        argon2::PasswordHash::new(&self.0).is_ok_and(|hash| {
+            Argon2::default()
+                .verify_password(password.as_bytes(), &hash)
+                .is_ok()
+        })
+    }
+}
+
+/// The password of a `Login` or `Register` action, hashed before the action
+/// is handled.
+enum Hashed {
+    /// The password to log in with matches this hash of the user.
+    Matches(PasswordHash),
+    /// The hash of the password to register.
+    New(PasswordHash),
+}
+
+/// A login of a user, which a reconnecting client resumes with its token.
+#[derive(Serialize, Deserialize)]
+struct Session {
+    token: String,
+    /// The room the session was watching when its socket was closed.
+    roomid: Option<RoomId>,
+}
+
+/// Maximum number of sessions kept per user. Logging in more often drops
+/// the oldest ones.
+const MAX_SESSIONS: usize = 10;
+
+/// Number of rooms sent per page of the room list.
+const ROOM_LIST_PAGE_SIZE: usize = 20;
+
+/// The browser client, served to plain HTTP requests on the websocket port.
+const WEB_CLIENT: &str = include_str!("web/index.html");
+
+/// Path of the websocket of the browser client, which sends the lines the
+/// user types and gets the text the terminal client would print.
+const TEXT_PATH: &str = "/text";
+
+/// Time a new connection has for its TLS handshake, and each request on it
+/// for its headers.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
 #[derive(Clone)]
-struct Sink(UnboundedSender<Message>);
+struct Sink {
+    sender: UnboundedSender<Message>,
+    /// Whether the socket speaks text instead of JSON, see `TEXT_PATH`.
+    text: bool,
+}
 
 impl Sink {
     fn send(&self, response: Response<impl GameT>) {
-        let message = Message::Binary(serde_json::to_vec(&response).unwrap());
-        self.0.unbounded_send(message).unwrap();
+        let message = match (self.text, &response) {
+            // The browser client keeps the token, hidden from the user in an
+            // escape sequence, to resume the session after a reconnect.
+            (true, Response::Session(token)) => {
+                Message::Text(format!("\x1b]session;{token}\x07").into())
+            }
+            (true, _) => Message::Text(response.to_string().into()),
+            (false, _) => Message::Binary(serde_json::to_vec(&response).unwrap().into()),
+        };
+        // The socket may already be closing, e.g. after a ban.
+        let _ = self.sender.unbounded_send(message);
+    }
+
+    fn ping(&self) {
+        let _ = self
+            .sender
+            .unbounded_send(Message::Ping(Default::default()));
+    }
+
+    /// Close the socket once the messages sent so far are delivered.
+    fn close(&self) {
+        self.sender.close_channel();
     }
 }
 
@@ -30,15 +155,304 @@ struct Client {
     userid: Option<UserId>,
     /// The room the socket is watching.
     roomid: Option<RoomId>,
+    /// Whether the client said hello with the protocol version of the server.
+    greeted: bool,
+    /// Token of the session the user logged in with.
+    session: Option<String>,
+    /// Unix timestamps of the actions the socket sent in the last minute.
+    action_times: VecDeque<u64>,
+    /// Unix timestamp of the last message from the socket, including pongs.
+    last_seen: u64,
+}
+
+/// Capacity and time limits of the server.
+#[derive(Clone, Copy, Debug)]
+pub struct Limits {
+    /// Maximum number of rooms that have not ended.
+    pub max_rooms: usize,
+    /// Maximum number of rooms that have not ended created by a single user.
+    pub max_rooms_per_user: usize,
+    /// Maximum number of open connections from a single IP address,
+    /// including plain HTTP ones.
+    pub max_connections_per_ip: usize,
+    /// Maximum number of watchers of a room that do not play in it.
+    pub max_spectators_per_room: usize,
+    /// Seconds without moves after which a started game is abandoned.
+    pub abandon_after: u64,
+    /// Seconds after which a started game is abandoned when one of its
+    /// players lost their connection and did not come back.
+    pub abandon_disconnected_after: u64,
+    /// Maximum number of actions a single socket may send per minute.
+    /// Sockets that send twice as many get their IP address banned.
+    pub max_actions_per_minute: usize,
+    /// Seconds for which connections from a banned IP address are refused.
+    pub ban_secs: u64,
+    /// Seconds without activity after which a room that is waiting for
+    /// players is removed.
+    pub expire_pending_after: u64,
+    /// Seconds after the end of its game after which a room is removed.
+    /// The game stays in the history of its players.
+    pub keep_ended_for: u64,
+    /// Seconds between pings to each socket.
+    pub ping_interval: u64,
+    /// Seconds without any message, including pongs, after which a socket is
+    /// considered dead and closed.
+    pub connection_timeout: u64,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_rooms: 1000,
+            max_rooms_per_user: 5,
+            max_connections_per_ip: 20,
+            max_spectators_per_room: 50,
+            abandon_after: 24 * 60 * 60,
+            abandon_disconnected_after: 10 * 60,
+            max_actions_per_minute: 120,
+            ban_secs: 10 * 60,
+            expire_pending_after: 24 * 60 * 60,
+            keep_ended_for: 7 * 24 * 60 * 60,
+            ping_interval: 30,
+            connection_timeout: 90,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+struct ServerRoom<Game: GameT> {
+    room: Room<Game>,
+    /// The user who created the room.
+    creator: UserId,
+    /// All sockets watching the room.
+    #[serde(skip)]
+    watchers: Vec<ClientId>,
+    /// Human-readable activity feed, sent to watchers alongside the board.
+    feed: Vec<FeedEntry>,
+    /// Whether players see spectator chat while the game is in progress.
+    shared_spectator_chat: bool,
+    /// Unix timestamp of the last change to the game.
+    last_activity: u64,
+    /// Turn reminders, if the host enabled them.
+    reminders: Option<ReminderPolicy>,
+    /// Reminders sent for the current turn.
+    reminders_sent: u64,
+    /// Whether the host was offered to skip or substitute the current player.
+    offered: bool,
+    /// Unix timestamp at which the game ended.
+    ended_at: Option<u64>,
+    /// Seconds each move took, in order.
+    move_secs: Vec<u64>,
+    /// Remaining time bank of each player in a correspondence room. Players
+    /// without an entry have the full bank.
+    time_banks: HashMap<UserId, u64>,
+    /// Players that are computer players, whose turns the server makes.
+    bots: Vec<UserId>,
+    /// Every action received for this room, including rejected ones.
+    audit_log: Vec<AuditEntry>,
+    /// Password that users who are not players need to watch or join the
+    /// room, which hides it from their room list.
+    #[serde(default)]
+    password: Option<String>,
+    /// Players who voted to kick each player, in the order they did.
+    #[serde(default)]
+    kick_votes: HashMap<UserId, Vec<UserId>>,
+    /// Unix timestamp at which each player last had the room open.
+    #[serde(default)]
+    last_seen: HashMap<UserId, u64>,
+    /// Unix timestamp at which each player lost their connection while they
+    /// had the room open. After a restart, everyone is given the full grace
+    /// period to come back.
+    #[serde(skip)]
+    disconnected: HashMap<UserId, u64>,
+    /// Moves that players queued for their next turn.
+    #[serde(default)]
+    premoves: HashMap<UserId, Game::Move>,
+    /// Notes of each player, by the id of the card they are on.
+    #[serde(default)]
+    notes: HashMap<UserId, BTreeMap<usize, String>>,
+}
+
+impl<Game: GameT> ServerRoom<Game> {
+    /// Whether the user created or plays in the room.
+    fn is_member(&self, userid: &UserId) -> bool {
+        self.creator == *userid || self.room.players.contains(userid)
+    }
+
+    /// Name for a new bot that does not play in the room yet.
+    fn new_bot_name(&self) -> UserId {
+        (1..)
+            .map(|id| format!("Bot{id}"))
+            .find(|name| !self.room.players.contains(name))
+            .unwrap()
+    }
+}
+
+/// A room in which bots play games on a loop, which anyone can watch without
+/// logging in.
+#[derive(Clone, Debug)]
+pub struct Demo<Settings> {
+    pub players: usize,
+    pub settings: Settings,
+    /// Seconds between two moves, and before the next game starts.
+    pub move_secs: u64,
+}
+
+/// Certificate chain and private key with which the server terminates TLS
+/// itself, both PEM files.
+#[derive(Clone, Debug)]
+pub struct Tls {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+}
+
+impl Tls {
+    fn acceptor(&self) -> TlsAcceptor {
+        let read = |path: &PathBuf| {
+            std::fs::read(path)
+                .unwrap_or_else(|err| panic!("Could not read {}: {err}", path.display()))
+        };
+        let certs = rustls_pemfile::certs(&mut &read(&self.cert)[..])
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap_or_else(|err| panic!("Invalid TLS certificate: {err}"));
+        let key = rustls_pemfile::private_key(&mut &read(&self.key)[..])
+            .unwrap_or_else(|err| panic!("Invalid TLS key: {err}"))
+            .unwrap_or_else(|| panic!("No private key in {}", self.key.display()));
+        let config = rustls::ServerConfig::builder()
+            .with_no_client_auth()
+            .with_single_cert(certs, key)
+            .unwrap_or_else(|err| panic!("Could not set up TLS: {err}"));
+        Arc::new(config).into()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct FeedEntry {
+    text: String,
+    /// Chat of a spectator, which may be hidden from players.
+    from_spectator: bool,
 }
 
 struct ServerState<Game: GameT> {
     /// All users in the server.
     users: HashMap<UserId, User>,
     /// All rooms in the server.
-    rooms: Vec<(Room<Game>, Vec<ClientId>)>,
+    rooms: Rooms<Game>,
+    /// Every finished game except those of the demo room, in the order they
+    /// ended.
+    archive: Vec<HistoryEntry<Game>>,
     /// All currently open sockets.
     clients: HashMap<ClientId, Client>,
+    limits: Limits,
+    /// Shell command that notifies offline users of their turn. It gets the
+    /// details in the `TURNBASED_USER`, `TURNBASED_CONTACT`, `TURNBASED_ROOM`
+    /// and `TURNBASED_MESSAGE` environment variables.
+    notify_command: Option<String>,
+    /// The room in which bots play, if any.
+    demo: Option<RoomId>,
+    /// File to which the users and rooms are saved, so they survive a restart.
+    state_file: Option<PathBuf>,
+    /// IP addresses that flooded the server, with the unix timestamp until
+    /// which they are refused.
+    bans: HashMap<IpAddr, u64>,
+    /// Number of open connections from each IP address, including plain
+    /// HTTP ones and those still in their handshake.
+    connections: HashMap<IpAddr, usize>,
+    /// Whether the server saved its state to shut down, after which actions
+    /// are refused.
+    shutting_down: bool,
+}
+
+/// The rooms of the server by id. Ids are not reused once a room is
+/// removed, so that clients never end up in a newer room with the same id.
+struct Rooms<Game: GameT> {
+    rooms: BTreeMap<RoomId, ServerRoom<Game>>,
+    /// Id of the next room that is created.
+    next_roomid: usize,
+}
+
+impl<Game: GameT> Rooms<Game> {
+    /// The rooms of a state file, which only servers that removed rooms
+    /// saved the next id in.
+    fn new(rooms: Vec<ServerRoom<Game>>, next_roomid: Option<usize>) -> Self {
+        let next_roomid = next_roomid.unwrap_or_else(|| {
+            rooms
+                .iter()
+                .map(|room| room.room.roomid.0 + 1)
+                .max()
+                .unwrap_or(0)
+        });
+        Self {
+            rooms: rooms
+                .into_iter()
+                .map(|room| (room.room.roomid, room))
+                .collect(),
+            next_roomid,
+        }
+    }
+
+    /// Take the id of a new room.
+    fn next_roomid(&mut self) -> RoomId {
+        self.next_roomid += 1;
+        RoomId(self.next_roomid - 1)
+    }
+
+    /// Ids of all rooms, in the order they were created.
+    fn ids(&self) -> Vec<RoomId> {
+        self.rooms.keys().copied().collect()
+    }
+}
+
+impl<Game: GameT> Default for Rooms<Game> {
+    fn default() -> Self {
+        Self::new(vec![], None)
+    }
+}
+
+impl<Game: GameT> std::ops::Deref for Rooms<Game> {
+    type Target = BTreeMap<RoomId, ServerRoom<Game>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.rooms
+    }
+}
+
+impl<Game: GameT> std::ops::DerefMut for Rooms<Game> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.rooms
+    }
+}
+
+impl<Game: GameT> std::ops::Index<RoomId> for Rooms<Game> {
+    type Output = ServerRoom<Game>;
+
+    fn index(&self, roomid: RoomId) -> &Self::Output {
+        &self.rooms[&roomid]
+    }
+}
+
+impl<Game: GameT> std::ops::IndexMut<RoomId> for Rooms<Game> {
+    fn index_mut(&mut self, roomid: RoomId) -> &mut Self::Output {
+        self.rooms.get_mut(&roomid).unwrap()
+    }
+}
+
+/// What is saved of the server state. Connections are not saved: clients
+/// reconnect and log in again after a restart.
+#[derive(Serialize, Deserialize)]
+struct Snapshot<Users, Rooms, Archive> {
+    users: Users,
+    rooms: Rooms,
+    demo: Option<RoomId>,
+    /// Missing in state files of servers without an archive, in which case
+    /// it is rebuilt from the rooms.
+    #[serde(default)]
+    archive: Option<Archive>,
+    /// Missing in state files of servers that never removed rooms, in which
+    /// case it follows the last room.
+    #[serde(default)]
+    next_roomid: Option<usize>,
 }
 
 #[derive(Clone)]
@@ -48,17 +462,172 @@ struct Server<Game: GameT> {
 
 impl<Game: GameT> ServerState<Game> {
     fn room(&self, roomid: RoomId) -> &Room<Game> {
-        &self.rooms[roomid.0].0
+        &self.rooms[roomid].room
     }
     fn room_mut(&mut self, roomid: RoomId) -> &mut Room<Game> {
-        &mut self.rooms[roomid.0].0
+        &mut self.rooms[roomid].room
     }
 
     fn watchers(&self, roomid: RoomId) -> &Vec<ClientId> {
-        &self.rooms[roomid.0].1
+        &self.rooms[roomid].watchers
     }
     fn watchers_mut(&mut self, roomid: RoomId) -> &mut Vec<ClientId> {
-        &mut self.rooms[roomid.0].1
+        &mut self.rooms[roomid].watchers
+    }
+
+    /// Append an entry to the activity feed of the room and send it to all watchers.
+    fn log_activity(&mut self, roomid: RoomId, entry: String) {
+        self.push_feed(
+            roomid,
+            FeedEntry {
+                text: entry,
+                from_spectator: false,
+            },
+        );
+    }
+
+    /// Entries are plain text, so that they read well in any client.
+    fn push_feed(&mut self, roomid: RoomId, mut entry: FeedEntry) {
+        entry.text = strip_styles(&entry.text);
+        let seq = self.next_seq(roomid);
+        for watching_client in self.watchers(roomid) {
+            let entries = if self.can_see(roomid, *watching_client, &entry) {
+                vec![entry.text.clone()]
+            } else {
+                vec![]
+            };
+            self.client(*watching_client)
+                .sink
+                .send(Response::<Game>::Feed {
+                    roomid,
+                    seq,
+                    entries,
+                });
+        }
+        self.rooms[roomid].feed.push(entry);
+    }
+
+    /// Number the next broadcast for the room.
+    fn next_seq(&mut self, roomid: RoomId) -> u64 {
+        let room = self.room_mut(roomid);
+        room.seq += 1;
+        room.seq
+    }
+
+    /// Send the client all entries of the feed of the room it may see.
+    fn send_feed(&self, roomid: RoomId, clientid: ClientId) {
+        let entries: Vec<_> = self.rooms[roomid]
+            .feed
+            .iter()
+            .filter(|entry| self.can_see(roomid, clientid, entry))
+            .map(|entry| entry.text.clone())
+            .collect();
+        if !entries.is_empty() {
+            self.client(clientid).sink.send(Response::<Game>::Feed {
+                roomid,
+                seq: self.room(roomid).seq,
+                entries,
+            });
+        }
+    }
+
+    /// Players do not see spectator chat during the game, unless it is shared.
+    fn can_see(&self, roomid: RoomId, clientid: ClientId, entry: &FeedEntry) -> bool {
+        let room = &self.rooms[roomid];
+        !entry.from_spectator
+            || room.shared_spectator_chat
+            || matches!(room.room.state, RoomState::Ended(_))
+            || !self.is_player(roomid, clientid)
+    }
+
+    fn is_player(&self, roomid: RoomId, clientid: ClientId) -> bool {
+        self.client(clientid)
+            .userid
+            .as_ref()
+            .is_some_and(|userid| self.room(roomid).players.contains(userid))
+    }
+
+    /// Rooms whose game has not ended yet.
+    fn open_rooms(&self) -> impl Iterator<Item = &ServerRoom<Game>> {
+        self.rooms
+            .values()
+            .filter(|room| !matches!(room.room.state, RoomState::Ended(_)))
+    }
+
+    /// Number of watchers of the room that do not play in it.
+    fn spectators(&self, roomid: RoomId) -> usize {
+        self.watchers(roomid)
+            .iter()
+            .filter(|clientid| !self.is_player(roomid, **clientid))
+            .count()
+    }
+
+    /// Count a new connection from the address of the client, unless the
+    /// address is banned or has too many connections open.
+    fn open_connection(&mut self, clientid: ClientId) -> Result<(), LimitExceeded> {
+        if let Some(until) = self.banned_until(clientid) {
+            return Err(LimitExceeded::Banned(until));
+        }
+        let max_connections = self.limits.max_connections_per_ip;
+        let connections = self.connections.entry(clientid.ip()).or_default();
+        if *connections >= max_connections {
+            return Err(LimitExceeded::ConnectionsPerIp(max_connections));
+        }
+        *connections += 1;
+        Ok(())
+    }
+
+    /// Stop counting a connection counted by `open_connection`.
+    fn close_connection(&mut self, clientid: ClientId) {
+        let connections = self.connections.get_mut(&clientid.ip()).unwrap();
+        *connections -= 1;
+        if *connections == 0 {
+            self.connections.remove(&clientid.ip());
+        }
+    }
+
+    /// Count an action of the client. Refuses it when the client sent more
+    /// than `max_actions_per_minute` actions in the last minute, and bans
+    /// its address when it keeps going to twice that.
+    fn rate_limit(&mut self, clientid: ClientId) -> Option<LimitExceeded> {
+        let max_actions = self.limits.max_actions_per_minute;
+        let now = now();
+        let action_times = &mut self.client_mut(clientid).action_times;
+        while action_times.front().is_some_and(|time| time + 60 <= now) {
+            action_times.pop_front();
+        }
+        action_times.push_back(now);
+        let actions = action_times.len();
+        if actions <= max_actions {
+            return None;
+        }
+        if actions <= 2 * max_actions {
+            return Some(LimitExceeded::ActionsPerMinute(max_actions));
+        }
+        let until = now + self.limits.ban_secs;
+        tracing::warn!(address = %clientid.ip(), "banned for flooding");
+        self.bans.retain(|_, until| *until > now);
+        self.bans.insert(clientid.ip(), until);
+        for (other, client) in &self.clients {
+            if other.ip() == clientid.ip() {
+                client
+                    .sink
+                    .send(Response::<Game>::LimitExceeded(LimitExceeded::Banned(
+                        until,
+                    )));
+                client.sink.close();
+            }
+        }
+        Some(LimitExceeded::Banned(until))
+    }
+
+    /// Unix timestamp until which connections from the address of the
+    /// client are refused, if it is banned.
+    fn banned_until(&self, clientid: ClientId) -> Option<u64> {
+        self.bans
+            .get(&clientid.ip())
+            .copied()
+            .filter(|until| *until > now())
     }
 
     fn client(&self, clientid: ClientId) -> &Client {
@@ -68,35 +637,299 @@ impl<Game: GameT> ServerState<Game> {
         self.clients.get_mut(&clientid).unwrap()
     }
 
-    fn room_list(&self) -> Response<Game> {
+    fn room_list(&self, userid: &UserId) -> Response<Game> {
+        self.filtered_room_list(userid, None, None, false, 0)
+    }
+
+    /// A page of the room list, with only correspondence rooms or only other
+    /// rooms if `correspondence` is given, only rooms with the given status,
+    /// and only rooms the user plays in if `involving_user` is set.
+    fn filtered_room_list(
+        &self,
+        userid: &UserId,
+        correspondence: Option<bool>,
+        status: Option<RoomStatus>,
+        involving_user: bool,
+        page: usize,
+    ) -> Response<Game> {
+        let rooms: Vec<_> = self
+            .rooms
+            .values()
+            .filter(|room| room.password.is_none() || room.is_member(userid))
+            .map(|room| &room.room)
+            .filter(|room| correspondence.is_none_or(|c| room.correspondence.is_some() == c))
+            .filter(|room| status.is_none_or(|s| room.state.status() == s))
+            .filter(|room| !involving_user || room.players.contains(userid))
+            .collect();
+        let pages = rooms.len().div_ceil(ROOM_LIST_PAGE_SIZE).max(1);
+        let page = page.min(pages - 1);
         Response::RoomList(
-            self.rooms
+            rooms
                 .iter()
-                .map(|room| room.0.to_list_item())
+                .skip(page * ROOM_LIST_PAGE_SIZE)
+                .take(ROOM_LIST_PAGE_SIZE)
+                .map(|room| room.to_list_item())
                 .collect(),
+            self.teammates(userid),
+            RoomListPage {
+                page,
+                pages,
+                rooms: rooms.len(),
+            },
         )
     }
 
+    /// The room as seen by the user, rendered with their accessibility preferences.
+    fn view(&self, roomid: RoomId, userid: &UserId) -> Room<Game> {
+        let accessibility = self
+            .users
+            .get(userid)
+            .map_or_else(Default::default, |user| user.accessibility);
+        let mut view = self
+            .room(roomid)
+            .to_view(userid)
+            .with_accessibility(accessibility);
+        // The bots of the demo room are never connected.
+        if self.demo != Some(roomid) {
+            let room = &self.rooms[roomid];
+            view.seats = room
+                .room
+                .players
+                .iter()
+                .filter(|player| !room.bots.contains(player))
+                .map(|player| (player.clone(), self.seat_status(roomid, player)))
+                .collect();
+        }
+        let room = &self.rooms[roomid];
+        if let (RoomState::Started(Some(game)), Some(notes)) =
+            (&room.room.state, room.notes.get(userid))
+        {
+            view.notes = game
+                .hand_card_ids(userid)
+                .iter()
+                .enumerate()
+                .filter_map(|(idx, id)| Some((idx + 1, notes.get(id)?.clone())))
+                .collect();
+        }
+        view
+    }
+
+    /// Whether the player has the room open, and when they were last heard
+    /// from there.
+    fn seat_status(&self, roomid: RoomId, userid: &UserId) -> SeatStatus {
+        let last_seen = self
+            .watchers(roomid)
+            .iter()
+            .map(|clientid| self.client(*clientid))
+            .filter(|client| client.userid.as_ref() == Some(userid))
+            .map(|client| client.last_seen)
+            .max();
+        SeatStatus {
+            online: last_seen.is_some(),
+            last_seen: last_seen.or_else(|| self.rooms[roomid].last_seen.get(userid).copied()),
+        }
+    }
+
+    /// Presence of all users who played in a room with the user, as far as
+    /// they share it.
+    fn teammates(&self, userid: &UserId) -> Vec<Presence> {
+        let mut teammates: Vec<&UserId> = self
+            .rooms
+            .values()
+            .filter(|room| room.room.players.contains(userid))
+            .flat_map(|room| &room.room.players)
+            .filter(|&player| player != userid)
+            .collect();
+        teammates.sort();
+        teammates.dedup();
+        teammates
+            .into_iter()
+            .filter_map(|teammate| {
+                let sharing = self
+                    .users
+                    .get(teammate)
+                    .map_or_else(Default::default, |user| user.presence);
+                if sharing == PresenceSharing::Off {
+                    return None;
+                }
+                let clients = self.user_clients(teammate);
+                Some(Presence {
+                    userid: teammate.clone(),
+                    online: !clients.is_empty(),
+                    roomid: match sharing {
+                        PresenceSharing::Room => clients
+                            .iter()
+                            .find_map(|clientid| self.client(*clientid).roomid),
+                        _ => None,
+                    },
+                })
+            })
+            .collect()
+    }
+
+    /// Handle the action and record it in the audit log of the room it
+    /// concerns. `hashed` is its password after hashing, if it has one.
     fn handle_action(
         &mut self,
         clientid: ClientId,
         action: Action<Game>,
+        hashed: Option<Hashed>,
+    ) -> Option<Response<Game>> {
+        if self.shutting_down {
+            return Some(Response::ServerRestarting);
+        }
+        if let Some(limit) = self.rate_limit(clientid) {
+            return Some(Response::LimitExceeded(limit));
+        }
+        if let Action::Hello { protocol_version } = action {
+            if protocol_version != PROTOCOL_VERSION {
+                return Some(Response::Error(version_mismatch(Some(protocol_version))));
+            }
+            self.client_mut(clientid).greeted = true;
+            return None;
+        }
+        if !self.client(clientid).greeted {
+            return Some(Response::Error(version_mismatch(None)));
+        }
+        let explicit_roomid = match &action {
+            Action::AuditLog(_) => return self.do_action(clientid, action, hashed),
+            Action::WatchRoom(roomid, _) | Action::JoinRoom(Some(roomid), _) => Some(*roomid),
+            _ => None,
+        };
+        let roomid_before = self.client(clientid).roomid;
+        let _span = explicit_roomid
+            .or(roomid_before)
+            .map(|roomid| tracing::info_span!("room", room = roomid.0).entered());
+        let userid = self.client(clientid).userid.clone();
+        let description = match &action {
+            // Players can read the audit log, so the token is left out.
+            Action::Resume(_) => "Resume".into(),
+            // And so are passwords.
+            Action::Login(userid, Some(_), _) => format!("Login({userid:?}) with password"),
+            Action::Register(_) => "Register".into(),
+            Action::NewRoom {
+                password: Some(_), ..
+            } => "NewRoom with password".into(),
+            Action::WatchRoom(roomid, Some(_)) => format!("WatchRoom({roomid:?}) with password"),
+            Action::JoinRoom(roomid, Some(_)) => format!("JoinRoom({roomid:?}) with password"),
+            // The feed decides who may read chat messages, not the audit log.
+            Action::Chat(_) => "Chat".into(),
+            // Notes are private to the player who wrote them.
+            Action::SetNote { card_idx, .. } => format!("SetNote({card_idx})"),
+            // Premoves and probes would reveal plans to the other players.
+            Action::Premove(Some(_)) => "Premove".into(),
+            Action::ProbeMove(_) => "ProbeMove".into(),
+            // And notification addresses are personal.
+            Action::Notify(Some(_)) => "Notify".into(),
+            _ => format!("{action:?}"),
+        };
+
+        let response = self.do_action(clientid, action, hashed);
+
+        let error = match &response {
+            Some(Response::Error(err)) => Some(err.clone()),
+            Some(Response::LimitExceeded(limit)) => Some(limit.to_string()),
+            Some(Response::GameFinished(finished)) => Some(finished.to_string()),
+            _ => None,
+        };
+        let user = userid.as_deref();
+        match &error {
+            Some(err) => tracing::info!(user, "refused {description}: {err}"),
+            None => tracing::debug!(user, "{description}"),
+        }
+        let roomid = explicit_roomid
+            .or(self.client(clientid).roomid)
+            .or(roomid_before);
+        if let Some(room) = roomid.and_then(|roomid| self.rooms.get_mut(&roomid)) {
+            room.audit_log.push(AuditEntry {
+                time: now(),
+                userid,
+                action: description,
+                error,
+            });
+        }
+        response
+    }
+
+    fn do_action(
+        &mut self,
+        clientid: ClientId,
+        action: Action<Game>,
+        hashed: Option<Hashed>,
     ) -> Option<Response<Game>> {
         use Response::*;
 
-        match action {
-            Action::Login(login_userid) => {
-                self.logout(clientid);
-                self.clients.get_mut(&clientid).unwrap().userid = Some(login_userid.clone());
-                self.users
-                    .insert(login_userid.clone(), User { sockets: vec![] });
-                self.client(clientid)
-                    .sink
-                    .send(Response::<Game>::LoggedIn(login_userid));
-                return Some(self.room_list());
+        if let Action::Login(login_userid, password, accessibility) = action {
+            let registered = self
+                .users
+                .get(&login_userid)
+                .and_then(|user| user.password.as_ref());
+            if let Some(hash) = registered {
+                match (password, hashed) {
+                    (None, _) => return Some(Error("This user has a password; log in with login <username> password <password>".into())),
+                    // The password may have changed while it was hashed.
+                    (Some(_), Some(Hashed::Matches(matched))) if matched == *hash => {}
+                    (Some(_), _) => return Some(Error("Wrong password".into())),
+                }
+            } else if let Some(roomid) = self.seat(&login_userid) {
+                // Without a password, only the sessions of the user can take
+                // their seat, so that nobody else makes their moves.
+                return Some(Error(format!(
+                    "{login_userid} plays in room {roomid}; log in where you joined it, and register a password to log in anywhere else"
+                )));
             }
-            _ => {}
-        };
+            self.logout(clientid);
+            self.clients.get_mut(&clientid).unwrap().userid = Some(login_userid.clone());
+            self.users
+                .entry(login_userid.clone())
+                .or_insert_with(|| User {
+                    sockets: vec![],
+                    presence: Default::default(),
+                    accessibility,
+                    vacation_until: None,
+                    contact: None,
+                    sessions: vec![],
+                    password: None,
+                })
+                .accessibility = accessibility;
+            self.client(clientid)
+                .sink
+                .send(Response::<Game>::LoggedIn(login_userid.clone()));
+            self.start_session(clientid, &login_userid);
+            return Some(self.room_list(&login_userid));
+        }
+
+        if let Action::Resume(token) = action {
+            let Some((userid, roomid)) = self.find_session(&token) else {
+                self.client(clientid).sink.send(Response::<Game>::Error(
+                    "Your session expired; please log in again".into(),
+                ));
+                return Some(NotLoggedIn);
+            };
+            self.logout(clientid);
+            let client = self.client_mut(clientid);
+            client.userid = Some(userid.clone());
+            client.session = Some(token.clone());
+            self.client(clientid)
+                .sink
+                .send(Response::<Game>::LoggedIn(userid.clone()));
+            self.client(clientid)
+                .sink
+                .send(Response::<Game>::Session(token));
+            let Some(roomid) = roomid.filter(|roomid| self.rooms.contains_key(roomid)) else {
+                return Some(self.room_list(&userid));
+            };
+            // Watching sends the activity missed while disconnected.
+            self.watch_room(clientid, roomid);
+            return Some(Room(self.view(roomid, &userid)));
+        }
+
+        if let (Action::WatchRoom(roomid, _), None) = (&action, &self.client(clientid).userid) {
+            if self.demo == Some(*roomid) {
+                self.watch_room(clientid, *roomid);
+                return Some(Room(self.view(*roomid, &UserId::new())));
+            }
+        }
 
         // Remaining actions require a user to be logged in.
         let Client { userid, roomid, .. } = self.client(clientid);
@@ -112,47 +945,203 @@ impl<Game: GameT> ServerState<Game> {
             }
             Action::LeaveRoom => {
                 self.leave_room(clientid);
-                return Some(self.room_list());
+                return Some(self.room_list(&userid));
+            }
+            Action::AuditLog(roomid) => {
+                let Some(room) = self.rooms.get(&roomid) else {
+                    return Some(Error("Invalid room ID".into()));
+                };
+                if !room.room.players.contains(&userid) {
+                    return Some(Error(
+                        "Only players of the room can view its audit log".into(),
+                    ));
+                }
+                return Some(AuditLog(roomid, room.audit_log.clone()));
+            }
+            Action::SharePresence(sharing) => {
+                self.users.get_mut(&userid).unwrap().presence = sharing;
+                return Some(PresenceSharing(sharing));
+            }
+            Action::Vacation(secs) => {
+                let until = secs.map(|secs| now() + secs);
+                self.users.get_mut(&userid).unwrap().vacation_until = until;
+                let entry = match until {
+                    Some(until) => {
+                        format!("{userid} is on vacation until {}", format_timestamp(until))
+                    }
+                    None => format!("{userid} is back from vacation"),
+                };
+                for roomid in self.correspondence_rooms(&userid) {
+                    if self.room(roomid).state.current_player().as_ref() == Some(&userid) {
+                        // The vacation does not pause the time already spent.
+                        self.charge_time_bank(roomid, &userid);
+                        self.rooms[roomid].last_activity = now();
+                    }
+                    self.log_activity(roomid, entry.clone());
+                }
+                return Some(Vacation(until));
+            }
+            Action::Notify(contact) => {
+                self.users.get_mut(&userid).unwrap().contact = contact.clone();
+                return Some(Notify(contact));
+            }
+            Action::Register(password) => {
+                if password.is_empty() {
+                    return Some(Error("The password must not be empty".into()));
+                }
+                for other in self.user_clients(&userid) {
+                    if other == clientid {
+                        continue;
+                    }
+                    self.logout(other);
+                    self.client(other)
+                        .sink
+                        .send(Response::<Game>::Error(format!(
+                            "{userid} registered a password; please log in again"
+                        )));
+                    self.client(other).sink.send(NotLoggedIn::<Game>);
+                }
+                let Some(Hashed::New(hash)) = hashed else {
+                    return Some(Error("The password was not hashed".into()));
+                };
+                let token = self.client(clientid).session.clone();
+                let user = self.users.get_mut(&userid).unwrap();
+                user.sessions
+                    .retain(|session| Some(&session.token) == token.as_ref());
+                user.password = Some(hash);
+                return Some(Registered);
+            }
+            Action::ListRooms {
+                correspondence,
+                status,
+                involving_user,
+                page,
+            } => {
+                return Some(self.filtered_room_list(
+                    &userid,
+                    correspondence,
+                    status,
+                    involving_user,
+                    page,
+                ));
+            }
+            Action::History(user) => {
+                let user = user.unwrap_or(userid);
+                let history = self.history(&user);
+                return Some(History(user, history));
+            }
+            Action::Leaderboard => {
+                return Some(Leaderboard(self.leaderboard()));
             }
             Action::NewRoom {
                 min_players,
                 max_players,
+                turn_timer,
+                password,
+                seating,
+                hide_deck,
                 settings,
             } => {
-                let roomid = RoomId(self.rooms.len());
-                self.rooms.push((
-                    crate::types::Room {
-                        roomid,
-                        settings,
-                        players: vec![userid.clone()],
-                        state: RoomState::WaitingForPlayers {
-                            min_players,
-                            max_players,
+                let limits = self.limits;
+                if self.open_rooms().count() >= limits.max_rooms {
+                    return Some(LimitExceeded(crate::types::LimitExceeded::Rooms(
+                        limits.max_rooms,
+                    )));
+                }
+                if self
+                    .open_rooms()
+                    .filter(|room| room.creator == userid)
+                    .count()
+                    >= limits.max_rooms_per_user
+                {
+                    return Some(LimitExceeded(crate::types::LimitExceeded::RoomsPerUser(
+                        limits.max_rooms_per_user,
+                    )));
+                }
+                let roomid = self.rooms.next_roomid();
+                self.rooms.insert(
+                    roomid,
+                    ServerRoom {
+                        room: crate::types::Room {
+                            roomid,
+                            settings,
+                            players: vec![userid.clone()],
+                            state: RoomState::WaitingForPlayers {
+                                min_players,
+                                max_players,
+                            },
+                            seq: 0,
+                            pace: Pace::default(),
+                            correspondence: None,
+                            spectator_view: Default::default(),
+                            turn_timer,
+                            turn_deadline: None,
+                            undo_votes: vec![],
+                            private: password.is_some(),
+                            seating,
+                            hide_deck,
+                            review: None,
+                            seats: Default::default(),
+                            notes: Default::default(),
                         },
+                        creator: userid.clone(),
+                        watchers: vec![],
+                        feed: vec![],
+                        shared_spectator_chat: false,
+                        last_activity: now(),
+                        reminders: None,
+                        reminders_sent: 0,
+                        offered: false,
+                        ended_at: None,
+                        move_secs: vec![],
+                        time_banks: HashMap::new(),
+                        bots: vec![],
+                        audit_log: vec![],
+                        password,
+                        kick_votes: HashMap::new(),
+                        last_seen: HashMap::new(),
+                        disconnected: HashMap::new(),
+                        premoves: HashMap::new(),
+                        notes: HashMap::new(),
                     },
-                    vec![clientid],
-                ));
+                );
                 self.leave_room(clientid);
                 self.client_mut(clientid).roomid = Some(roomid);
-                return Some(Room(self.room(roomid).to_view(&userid)));
+                self.watchers_mut(roomid).push(clientid);
+                self.log_activity(roomid, format!("{userid} created the room"));
+                return Some(Room(self.view(roomid, &userid)));
             }
-            Action::WatchRoom(roomid) => {
-                if self.rooms.get(roomid.0).is_none() {
+            Action::WatchRoom(roomid, password) => {
+                if self.rooms.get(&roomid).is_none() {
                     return Some(Error("Invalid room ID".into()));
                 }
+                if let Err(err) = self.check_password(clientid, roomid, password.as_deref()) {
+                    return Some(Error(err.into()));
+                }
+                let max_spectators = self.limits.max_spectators_per_room;
+                if self.client(clientid).roomid != Some(roomid)
+                    && !self.room(roomid).players.contains(&userid)
+                    && self.spectators(roomid) >= max_spectators
+                {
+                    return Some(LimitExceeded(
+                        crate::types::LimitExceeded::SpectatorsPerRoom(max_spectators),
+                    ));
+                }
                 self.watch_room(clientid, roomid);
-                return Some(Room(self.room(roomid).to_view(&userid)));
+                return Some(Room(self.view(roomid, &userid)));
             }
-            Action::JoinRoom(joined_roomid) => {
-                eprintln!("JoinRoom {joined_roomid:?}");
+            Action::JoinRoom(joined_roomid, ref password) => {
                 roomid = match (joined_roomid, roomid) {
                     (Some(roomid), _) | (_, Some(roomid)) => Some(roomid),
                     _ => return Some(Error("Pass a room ID".into())),
                 };
                 let roomid = roomid.unwrap();
-                if self.rooms.get(roomid.0).is_none() {
+                if self.rooms.get(&roomid).is_none() {
                     return Some(Error("Invalid room ID".into()));
                 }
+                if let Err(err) = self.check_password(clientid, roomid, password.as_deref()) {
+                    return Some(Error(err.into()));
+                }
                 self.watch_room(clientid, roomid);
                 let room = self.room_mut(roomid);
                 // if user is not yet in room, join.
@@ -164,7 +1153,10 @@ impl<Game: GameT> ServerState<Game> {
                         return Some(Error("Room is already full".into()));
                     }
                     room.players.push(userid.clone());
-                    if room.players.len() == max_players {
+                    let full = room.players.len() == max_players;
+                    self.rooms[roomid].last_activity = now();
+                    self.log_activity(roomid, format!("{userid} joined the game"));
+                    if full {
                         if let Err(err) = self.start_game(&userid, roomid) {
                             return Some(Error(err.into()));
                         }
@@ -178,48 +1170,1387 @@ impl<Game: GameT> ServerState<Game> {
         let Some(roomid) = roomid else {
             return Some(Error("First join a room".into()));
         };
+        if self.demo == Some(roomid) && !matches!(action, Action::Resync | Action::Chat(_)) {
+            return Some(Error("Only the bots play in the demo room".into()));
+        }
 
         match action {
+            Action::Resync => {
+                self.send_feed(roomid, clientid);
+                return Some(Room(self.view(roomid, &userid)));
+            }
+            Action::UpdateSettings(settings) => {
+                if self.rooms[roomid].creator != userid {
+                    return Some(Error("Only the host can change the settings".into()));
+                }
+                if !matches!(self.room(roomid).state, RoomState::WaitingForPlayers { .. }) {
+                    return Some(Error(
+                        "The settings can only be changed before the game starts".into(),
+                    ));
+                }
+                self.log_activity(
+                    roomid,
+                    format!(
+                        "{userid} changed the settings to {}",
+                        Game::public_settings(&settings)
+                    ),
+                );
+                let room = &mut self.rooms[roomid];
+                room.room.settings = settings;
+                room.last_activity = now();
+            }
+            Action::SeatOrder(order) => {
+                if self.rooms[roomid].creator != userid {
+                    return Some(Error("Only the host can seat the players".into()));
+                }
+                let room = self.room_mut(roomid);
+                if !matches!(room.state, RoomState::WaitingForPlayers { .. }) {
+                    return Some(Error(
+                        "The players can only be seated before the game starts".into(),
+                    ));
+                }
+                if room.seating != Seating::Host {
+                    return Some(Error(
+                        "The host does not set the seating of this room".into(),
+                    ));
+                }
+                if order.len() != room.players.len()
+                    || !room.players.iter().all(|player| order.contains(player))
+                {
+                    return Some(Error("List each player of the room once".into()));
+                }
+                room.players = order;
+                let entry = format!("{userid} seated the players: {}", room.players.join(", "));
+                self.log_activity(roomid, entry);
+            }
             Action::StartGame => {
                 if let Err(err) = self.start_game(&userid, roomid) {
                     return Some(Error(err.into()));
                 }
             }
+            Action::Review(step) => {
+                let room = self.room_mut(roomid);
+                if !room.players.contains(&userid) {
+                    return Some(Error("Only players can step through the review".into()));
+                }
+                let RoomState::Ended(Some(game)) = &room.state else {
+                    return Some(Error("The game has not ended yet".into()));
+                };
+                let moves = game.num_moves();
+                let current = room.review.unwrap_or(moves);
+                let turn = match step {
+                    ReviewStep::Move(turn) if turn > moves => {
+                        return Some(Error(format!("The game has only {moves} moves")));
+                    }
+                    ReviewStep::Move(turn) => turn,
+                    ReviewStep::Next if current == moves => {
+                        return Some(Error("The review is at the last move".into()));
+                    }
+                    ReviewStep::Next => current + 1,
+                    ReviewStep::Previous if current == 0 => {
+                        return Some(Error("The review is at the start of the game".into()));
+                    }
+                    ReviewStep::Previous => current - 1,
+                    ReviewStep::End => moves,
+                };
+                if let Err(err) = game.replay(turn) {
+                    return Some(Error(err.into()));
+                }
+                // The final position needs no replay.
+                room.review = (turn < moves).then_some(turn);
+                self.rooms[roomid].last_activity = now();
+            }
+            Action::AddBot => {
+                if self.rooms[roomid].creator != userid {
+                    return Some(Error("Only the host can add bots".into()));
+                }
+                let room = self.room_mut(roomid);
+                let RoomState::WaitingForPlayers { max_players, .. } = room.state else {
+                    return Some(Error("Room is not waiting for players".into()));
+                };
+                if room.players.len() == max_players {
+                    return Some(Error("Room is already full".into()));
+                }
+                let bot = self.rooms[roomid].new_bot_name();
+                let room = self.room_mut(roomid);
+                room.players.push(bot.clone());
+                let full = room.players.len() == max_players;
+                self.rooms[roomid].bots.push(bot.clone());
+                self.log_activity(roomid, format!("{userid} added {bot} to the game"));
+                if full {
+                    if let Err(err) = self.start_game(&userid, roomid) {
+                        return Some(Error(err.into()));
+                    }
+                }
+            }
             Action::MakeMove(mov) => {
+                if self.rooms[roomid].bots.contains(&userid) {
+                    return Some(Error("This seat is played by a bot".into()));
+                }
                 let room = self.room_mut(roomid);
                 if !room.players.contains(&userid) {
                     return Some(Error("User did not join room".into()));
                 }
+                if let RoomState::Ended(game) = &room.state {
+                    let game = game.as_ref().unwrap();
+                    return Some(GameFinished(crate::types::GameFinished {
+                        roomid,
+                        score: game.score(),
+                        abandoned: game.is_abandoned(),
+                    }));
+                }
                 if let Err(err) = room.state.make_move(&userid, mov) {
                     return Some(Error(err.into()));
                 }
+                self.charge_time_bank(roomid, &userid);
+                self.after_move(roomid);
             }
-            _ => {}
-        };
-
-        let room = self.room(roomid);
-        for watching_client in self.watchers(roomid) {
-            let client = self.client(*watching_client);
-            client
-                .sink
-                .send(Room(room.to_view(client.userid.as_ref().unwrap())));
-        }
-        // Client is already updated in the loop above.
-        None
-    }
-
-    fn watch_room(&mut self, clientid: std::net::SocketAddr, roomid: RoomId) {
-        self.leave_room(clientid);
-        self.client_mut(clientid).roomid = Some(roomid);
-        self.watchers_mut(roomid).push(clientid);
-    }
-
-    fn disconnect(&mut self, clientid: std::net::SocketAddr) {
-        eprintln!("{} disconnected", &clientid);
-        let Client { userid, roomid, .. } = self.clients.remove(&clientid).unwrap();
-        if let Some(room) = roomid {
-            self.watchers_mut(room).retain(|x| x != &clientid);
-        }
+            Action::Premove(mov) => {
+                let room = &mut self.rooms[roomid];
+                if !room.room.players.contains(&userid) || room.bots.contains(&userid) {
+                    return Some(Error("User did not join room".into()));
+                }
+                if !matches!(room.room.state, RoomState::Started(_)) {
+                    return Some(Error("Game is not in progress".into()));
+                }
+                let Some(mov) = mov else {
+                    if room.premoves.remove(&userid).is_none() {
+                        return Some(Error("You have no premove".into()));
+                    }
+                    return Some(Premove(roomid, PremoveStatus::Cancelled));
+                };
+                if room.room.state.current_player().as_ref() == Some(&userid) {
+                    return Some(Error("It is your turn; make the move instead".into()));
+                }
+                room.premoves.insert(userid, mov);
+                return Some(Premove(roomid, PremoveStatus::Queued));
+            }
+            Action::SetNote { card_idx, text } => {
+                let room = &mut self.rooms[roomid];
+                if !room.room.players.contains(&userid) || room.bots.contains(&userid) {
+                    return Some(Error("User did not join room".into()));
+                }
+                let RoomState::Started(Some(game)) = &room.room.state else {
+                    return Some(Error("Game is not in progress".into()));
+                };
+                let ids = game.hand_card_ids(&userid);
+                let Some(&id) = card_idx.checked_sub(1).and_then(|idx| ids.get(idx)) else {
+                    return Some(Error("There is no card at that position".into()));
+                };
+                let notes = room.notes.entry(userid.clone()).or_default();
+                if text.is_empty() {
+                    notes.remove(&id);
+                } else {
+                    notes.insert(id, text);
+                }
+                return Some(Room(self.view(roomid, &userid)));
+            }
+            Action::ProbeMove(mov) => {
+                return Some(MoveProbe(
+                    self.probe_move(roomid, &userid, mov).map_err(String::from),
+                ));
+            }
+            Action::Undo(agree) => {
+                let room = &mut self.rooms[roomid];
+                if !room.room.players.contains(&userid) {
+                    return Some(Error("User did not join room".into()));
+                }
+                if !matches!(room.room.state, RoomState::Started(_)) {
+                    return Some(Error("Game is not in progress".into()));
+                }
+                if room.room.game().map_or(0, GameT::num_moves) == 0 {
+                    return Some(Error("There is no move to undo".into()));
+                }
+                let votes = &mut room.room.undo_votes;
+                if !agree {
+                    if votes.is_empty() {
+                        return Some(Error("Nobody asked to undo the last move".into()));
+                    }
+                    votes.clear();
+                    self.log_activity(roomid, format!("{userid} refused to undo the last move"));
+                    return None;
+                }
+                if votes.contains(&userid) {
+                    return Some(Error("You already agreed to undo the last move".into()));
+                }
+                let entry = if votes.is_empty() {
+                    format!("{userid} asked to undo the last move")
+                } else {
+                    format!("{userid} agreed to undo the last move")
+                };
+                votes.push(userid.clone());
+                // Bots agree to everything.
+                let agreed = room
+                    .room
+                    .players
+                    .iter()
+                    .all(|player| room.bots.contains(player) || votes.contains(player));
+                if !agreed {
+                    self.log_activity(roomid, entry);
+                    return None;
+                }
+                votes.clear();
+                if let Err(err) = self.undo(roomid) {
+                    return Some(Error(err.into()));
+                }
+                self.log_activity(roomid, "The last move was undone".into());
+                self.play_bots(roomid);
+            }
+            Action::Reminders(policy) => {
+                if self.rooms[roomid].creator != userid {
+                    return Some(Error("Only the host can change reminders".into()));
+                }
+                // JSON clients do not go through `parse_hours`.
+                if policy.is_some_and(|policy| policy.remind_after == 0) {
+                    return Some(Error("Reminders must be at least a second apart".into()));
+                }
+                self.rooms[roomid].reminders = policy;
+                let entry = match policy {
+                    Some(policy) => format!("{userid} set turn reminders {policy}"),
+                    None => format!("{userid} turned off turn reminders"),
+                };
+                self.log_activity(roomid, entry);
+                return None;
+            }
+            Action::Correspondence(correspondence) => {
+                if self.rooms[roomid].creator != userid {
+                    return Some(Error("Only the host can change the time banks".into()));
+                }
+                let room = &mut self.rooms[roomid];
+                room.room.correspondence = correspondence;
+                room.time_banks.clear();
+                let entry = match correspondence {
+                    Some(c) => format!("{userid} made this a correspondence room: {c}"),
+                    None => format!("{userid} made this a live room"),
+                };
+                self.start_turn(roomid);
+                self.log_activity(roomid, entry);
+            }
+            Action::SkipTurn => {
+                let current = match self.check_overdue(roomid, &userid) {
+                    Ok(current) => current,
+                    Err(err) => return Some(Error(err.into())),
+                };
+                if let Err(err) = self.room_mut(roomid).state.skip_turn() {
+                    return Some(Error(err.into()));
+                }
+                self.charge_time_bank(roomid, &current);
+                self.log_activity(roomid, format!("{userid} skipped the turn of {current}"));
+                self.after_move(roomid);
+            }
+            Action::Substitute(substitute) => {
+                let current = match self.check_overdue(roomid, &userid) {
+                    Ok(current) => current,
+                    Err(err) => return Some(Error(err.into())),
+                };
+                let room = self.room_mut(roomid);
+                if room.players.contains(&substitute) {
+                    return Some(Error("User already plays in this room".into()));
+                }
+                let RoomState::Started(Some(game)) = &mut room.state else {
+                    return Some(Error("Game is not in progress".into()));
+                };
+                if let Err(err) = game.replace_player(&current, &substitute) {
+                    return Some(Error(err.into()));
+                }
+                for player in &mut room.players {
+                    if *player == current {
+                        *player = substitute.clone();
+                    }
+                }
+                self.start_turn(roomid);
+                self.log_activity(roomid, format!("{substitute} replaced {current}"));
+            }
+            Action::VoteKick(kicked) => {
+                let room = &mut self.rooms[roomid];
+                if !room.room.players.contains(&userid) || room.bots.contains(&userid) {
+                    return Some(Error("User did not join room".into()));
+                }
+                if !room.room.players.contains(&kicked) || room.bots.contains(&kicked) {
+                    return Some(Error("Only players who are not bots can be kicked".into()));
+                }
+                if kicked == userid {
+                    return Some(Error("Use forfeit to leave the game".into()));
+                }
+                if !matches!(room.room.state, RoomState::Started(_)) {
+                    return Some(Error("Game is not in progress".into()));
+                }
+                let votes = room.kick_votes.entry(kicked.clone()).or_default();
+                if votes.contains(&userid) {
+                    return Some(Error("You already voted to kick this player".into()));
+                }
+                votes.push(userid.clone());
+                let votes = votes.len();
+                let voters = room
+                    .room
+                    .players
+                    .iter()
+                    .filter(|player| **player != kicked && !room.bots.contains(player))
+                    .count();
+                self.log_activity(
+                    roomid,
+                    format!("{userid} voted to kick {kicked} ({votes}/{voters})"),
+                );
+                if votes * 2 <= voters {
+                    return None;
+                }
+                if let Err(err) = self.kick(roomid, &kicked) {
+                    return Some(Error(err.into()));
+                }
+            }
+            Action::Forfeit => {
+                if !self.room(roomid).players.contains(&userid) {
+                    return Some(Error("User did not join room".into()));
+                }
+                if let Err(err) = self.abandon(roomid, format!("{userid} forfeited the game")) {
+                    return Some(Error(err.into()));
+                }
+                return None;
+            }
+            Action::Chat(message) => {
+                if self.is_player(roomid, clientid) {
+                    self.log_activity(roomid, format!("{userid}: {message}"));
+                } else {
+                    self.push_feed(
+                        roomid,
+                        FeedEntry {
+                            text: format!("{userid} (spectator): {message}"),
+                            from_spectator: true,
+                        },
+                    );
+                }
+                // Chat does not change the board.
+                return None;
+            }
+            Action::ShareSpectatorChat(shared) => {
+                if !self.is_player(roomid, clientid) {
+                    return Some(Error("Only players can change the spectator chat".into()));
+                }
+                self.rooms[roomid].shared_spectator_chat = shared;
+                let visibility = if shared { "shared" } else { "private" };
+                self.log_activity(roomid, format!("{userid} made spectator chat {visibility}"));
+                return None;
+            }
+            Action::SpectatorView(view) => {
+                if self.rooms[roomid].creator != userid {
+                    return Some(Error("Only the host can change the spectator view".into()));
+                }
+                let room = self.room_mut(roomid);
+                if !matches!(room.state, RoomState::WaitingForPlayers { .. }) {
+                    return Some(Error(
+                        "The spectator view can only be changed before the game starts".into(),
+                    ));
+                }
+                room.spectator_view = view;
+                self.log_activity(roomid, format!("{userid} set the spectator view to {view}"));
+            }
+            _ => {}
+        };
+
+        self.broadcast_room(roomid);
+        // Client is already updated in the broadcast above.
+        None
+    }
+
+    /// Whether the move would be accepted, with its warnings, by making it
+    /// on a copy of the room.
+    fn probe_move(
+        &self,
+        roomid: RoomId,
+        userid: &UserId,
+        mov: Game::Move,
+    ) -> Result<Vec<String>, &'static str> {
+        if self.rooms[roomid].bots.contains(userid) {
+            return Err("This seat is played by a bot");
+        }
+        let room = self.room(roomid);
+        if !room.players.contains(userid) {
+            return Err("User did not join room");
+        }
+        let warnings = match room.game() {
+            Some(game) if !game.has_ended() => game.check_move(userid, &mov)?,
+            _ => vec![],
+        };
+        room.state.clone().make_move(userid, mov)?;
+        Ok(warnings)
+    }
+
+    /// Log a move that was just made and let the bots that are next move.
+    fn after_move(&mut self, roomid: RoomId) {
+        self.record_move(roomid);
+        self.play_bots(roomid);
+    }
+
+    /// Take back the last move, and the moves of bots before it, so that a
+    /// player is to move again.
+    fn undo(&mut self, roomid: RoomId) -> Result<(), &'static str> {
+        let room = &mut self.rooms[roomid];
+        room.room.state.undo()?;
+        // Premoves were queued for a board that is gone.
+        room.premoves.clear();
+        while let Some(current) = room.room.state.current_player() {
+            if !room.bots.contains(&current) || room.room.state.undo().is_err() {
+                break;
+            }
+        }
+        self.start_turn(roomid);
+        Ok(())
+    }
+
+    /// Give the seat of the kicked player to a new bot, or abandon the game
+    /// if the game cannot replace players.
+    fn kick(&mut self, roomid: RoomId, kicked: &UserId) -> Result<(), &'static str> {
+        let bot = self.rooms[roomid].new_bot_name();
+        let room = &mut self.rooms[roomid];
+        room.kick_votes.remove(kicked);
+        room.premoves.remove(kicked);
+        room.notes.remove(kicked);
+        let RoomState::Started(Some(game)) = &mut room.room.state else {
+            return Err("Game is not in progress");
+        };
+        if game.replace_player(kicked, &bot).is_err() {
+            return self.abandon(roomid, format!("{kicked} was kicked"));
+        }
+        for player in &mut room.room.players {
+            if player == kicked {
+                *player = bot.clone();
+            }
+        }
+        room.bots.push(bot.clone());
+        self.log_activity(roomid, format!("{kicked} was kicked and {bot} took over"));
+        self.start_turn(roomid);
+        self.play_bots(roomid);
+        Ok(())
+    }
+
+    /// Make the moves of bots, and the premoves of players, for as long as
+    /// it is their turn.
+    fn play_bots(&mut self, roomid: RoomId) {
+        while let Some(current) = self.room(roomid).state.current_player() {
+            if let Some(mov) = self.rooms[roomid].premoves.remove(&current) {
+                if let Err(err) = self.room_mut(roomid).state.make_move(&current, mov) {
+                    self.send_premove_status(roomid, &current, PremoveStatus::Refused(err.into()));
+                    return;
+                }
+                self.charge_time_bank(roomid, &current);
+                self.record_move(roomid);
+                continue;
+            }
+            if !self.rooms[roomid].bots.contains(&current) {
+                return;
+            }
+            if self.room_mut(roomid).state.skip_turn().is_err() {
+                let _ = self.abandon(roomid, format!("{current} got stuck"));
+                return;
+            }
+            self.record_move(roomid);
+        }
+    }
+
+    /// Log a move that was just made and handle the end of the game.
+    fn record_move(&mut self, roomid: RoomId) {
+        let room = &mut self.rooms[roomid];
+        // Agreement to undo was about the previous move.
+        room.room.undo_votes.clear();
+        room.move_secs
+            .push(now().saturating_sub(room.last_activity));
+        room.room.pace = Pace::new(&room.move_secs);
+        self.start_turn(roomid);
+        if let Some(entry) = self.room(roomid).state.last_move_description() {
+            tracing::debug!("{entry}");
+            self.log_activity(roomid, entry);
+        }
+        self.drop_touched_premoves(roomid);
+        if let RoomState::Ended(game) = &self.room(roomid).state {
+            tracing::info!(score = game.as_ref().map(GameT::score), "game ended");
+            self.rooms[roomid].premoves.clear();
+            self.rooms[roomid].ended_at = Some(now());
+            self.log_activity(roomid, "The game has ended".into());
+            self.announce_record(roomid);
+            self.archive(roomid);
+        }
+    }
+
+    /// Cancel the premoves that refer to what the last move touched.
+    fn drop_touched_premoves(&mut self, roomid: RoomId) {
+        let room = &mut self.rooms[roomid];
+        let Some(game) = room.room.game() else {
+            return;
+        };
+        let touched: Vec<UserId> = room
+            .premoves
+            .iter()
+            .filter(|(player, mov)| game.last_move_touches(player, mov))
+            .map(|(player, _)| player.clone())
+            .collect();
+        for player in touched {
+            self.rooms[roomid].premoves.remove(&player);
+            self.send_premove_status(roomid, &player, PremoveStatus::Touched);
+        }
+    }
+
+    /// Tell all sockets of the player what happened to their premove.
+    fn send_premove_status(&self, roomid: RoomId, userid: &UserId, status: PremoveStatus) {
+        for clientid in self.user_clients(userid) {
+            self.client(clientid)
+                .sink
+                .send(Response::<Game>::Premove(roomid, status.clone()));
+        }
+    }
+
+    /// Restart the inactivity, reminder and turn clocks of the room.
+    fn start_turn(&mut self, roomid: RoomId) {
+        let room = &mut self.rooms[roomid];
+        room.last_activity = now();
+        room.reminders_sent = 0;
+        room.offered = false;
+        let playing = room.room.state.current_player().is_some();
+        room.room.turn_deadline = room
+            .room
+            .turn_timer
+            .filter(|_| playing)
+            .map(|timer| now() + timer.secs);
+        self.notify_turn(roomid);
+    }
+
+    /// Move for the players whose turn timer ran out.
+    fn enforce_turn_timers(&mut self) {
+        for roomid in self.rooms.ids() {
+            let _span = tracing::info_span!("room", room = roomid.0).entered();
+            let room = self.room(roomid);
+            let (Some(timer), Some(deadline)) = (room.turn_timer, room.turn_deadline) else {
+                continue;
+            };
+            if now() < deadline {
+                continue;
+            }
+            let Some(current) = room.state.current_player() else {
+                continue;
+            };
+            if self
+                .room_mut(roomid)
+                .state
+                .time_out(timer.on_timeout)
+                .is_err()
+            {
+                let _ = self.abandon(roomid, format!("{current} ran out of time"));
+                continue;
+            }
+            self.log_activity(roomid, format!("{current} ran out of time"));
+            self.after_move(roomid);
+            self.broadcast_room(roomid);
+        }
+    }
+
+    /// A room that has not ended in which the user has a seat.
+    fn seat(&self, userid: &UserId) -> Option<RoomId> {
+        self.rooms
+            .values()
+            .filter(|room| !matches!(room.room.state, RoomState::Ended(_)))
+            .find(|room| room.room.players.contains(userid))
+            .map(|room| room.room.roomid)
+    }
+
+    /// Started correspondence rooms the user plays in.
+    fn correspondence_rooms(&self, userid: &UserId) -> Vec<RoomId> {
+        self.rooms
+            .values()
+            .filter(|room| room.room.correspondence.is_some())
+            .filter(|room| matches!(room.room.state, RoomState::Started(_)))
+            .filter(|room| room.room.players.contains(userid))
+            .map(|room| room.room.roomid)
+            .collect()
+    }
+
+    /// Remaining time bank of the player in a correspondence room.
+    fn time_bank(&self, roomid: RoomId, userid: &UserId) -> Option<u64> {
+        let room = &self.rooms[roomid];
+        let correspondence = room.room.correspondence?;
+        Some(*room.time_banks.get(userid).unwrap_or(&correspondence.bank))
+    }
+
+    fn on_vacation(&self, userid: &UserId) -> bool {
+        self.users
+            .get(userid)
+            .and_then(|user| user.vacation_until)
+            .is_some_and(|until| now() < until)
+    }
+
+    /// Spend the time the player took for the current turn beyond the free
+    /// time per move from their time bank.
+    fn charge_time_bank(&mut self, roomid: RoomId, userid: &UserId) {
+        let Some(bank) = self.time_bank(roomid, userid) else {
+            return;
+        };
+        let room = &mut self.rooms[roomid];
+        let per_move = room.room.correspondence.unwrap().per_move;
+        let used = now()
+            .saturating_sub(room.last_activity)
+            .saturating_sub(per_move);
+        if used == 0 {
+            return;
+        }
+        let left = bank.saturating_sub(used);
+        room.time_banks.insert(userid.clone(), left);
+        self.log_activity(
+            roomid,
+            format!(
+                "{userid} used {} of their time bank ({} left)",
+                format_duration(used.min(bank)),
+                format_duration(left)
+            ),
+        );
+    }
+
+    /// Pause the clocks of players on vacation, and skip the turn of players
+    /// who ran out of time in correspondence rooms.
+    fn enforce_time_banks(&mut self) {
+        for roomid in self.rooms.ids() {
+            let _span = tracing::info_span!("room", room = roomid.0).entered();
+            let room = &self.rooms[roomid];
+            let Some(correspondence) = room.room.correspondence else {
+                continue;
+            };
+            let Some(current) = room.room.state.current_player() else {
+                continue;
+            };
+            if self.on_vacation(&current) {
+                self.rooms[roomid].last_activity = now();
+                continue;
+            }
+            let bank = self.time_bank(roomid, &current).unwrap();
+            let waiting = now().saturating_sub(self.rooms[roomid].last_activity);
+            if waiting <= correspondence.per_move + bank {
+                continue;
+            }
+            if self.room_mut(roomid).state.skip_turn().is_err() {
+                let _ = self.abandon(roomid, format!("{current} ran out of time"));
+                continue;
+            }
+            self.rooms[roomid].time_banks.insert(current.clone(), 0);
+            self.log_activity(roomid, format!("{current} ran out of time and was skipped"));
+            self.after_move(roomid);
+            self.broadcast_room(roomid);
+        }
+    }
+
+    /// Notify the current player of a correspondence room of their turn with
+    /// the notification command, if they are offline and left a contact.
+    fn notify_turn(&self, roomid: RoomId) {
+        let room = self.room(roomid);
+        let (Some(command), Some(_)) = (&self.notify_command, room.correspondence) else {
+            return;
+        };
+        let Some(current) = room.state.current_player() else {
+            return;
+        };
+        let Some(user) = self.users.get(&current) else {
+            return;
+        };
+        let Some(contact) = &user.contact else {
+            return;
+        };
+        if !self.user_clients(&current).is_empty() || self.on_vacation(&current) {
+            return;
+        }
+        let message = format!("It is your turn in room {roomid}");
+        let child = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .env("TURNBASED_USER", &current)
+            .env("TURNBASED_CONTACT", contact)
+            .env("TURNBASED_ROOM", roomid.to_string())
+            .env("TURNBASED_MESSAGE", message)
+            .spawn();
+        match child {
+            // Reap the command in the background.
+            Ok(mut child) => {
+                std::thread::spawn(move || child.wait());
+            }
+            Err(err) => tracing::error!("failed to run the notification command: {err}"),
+        }
+    }
+
+    /// Check that the user hosts the room and its current player took long
+    /// enough to be skipped or substituted. Returns the current player.
+    fn check_overdue(&self, roomid: RoomId, userid: &UserId) -> Result<UserId, &'static str> {
+        let room = &self.rooms[roomid];
+        if &room.creator != userid {
+            return Err("Only the host can skip or substitute players");
+        }
+        let current = room
+            .room
+            .state
+            .current_player()
+            .ok_or("Game is not in progress")?;
+        let offer_after = room
+            .reminders
+            .and_then(|policy| policy.offer_after)
+            .ok_or("Reminders do not allow skipping or substituting players")?;
+        if now() < room.last_activity + offer_after {
+            return Err("The current player did not take long enough yet");
+        }
+        Ok(current)
+    }
+
+    /// All sockets the user is logged in on.
+    fn user_clients(&self, userid: &UserId) -> Vec<ClientId> {
+        self.clients
+            .iter()
+            .filter(|(_, client)| client.userid.as_ref() == Some(userid))
+            .map(|(clientid, _)| *clientid)
+            .collect()
+    }
+
+    /// Send due turn reminders and skip or substitute offers.
+    fn send_reminders(&mut self) {
+        let now = now();
+        for roomid in self.rooms.ids() {
+            let _span = tracing::info_span!("room", room = roomid.0).entered();
+            let room = &self.rooms[roomid];
+            let Some(policy) = room.reminders else {
+                continue;
+            };
+            let Some(current) = room.room.state.current_player() else {
+                continue;
+            };
+            let waiting = now.saturating_sub(room.last_activity);
+            let due = waiting / policy.remind_after;
+            if due > room.reminders_sent {
+                self.rooms[roomid].reminders_sent = due;
+                let reminder = format!(
+                    "Reminder: it is your turn in room {roomid} (waiting {})",
+                    format_duration(waiting)
+                );
+                for clientid in self.user_clients(&current) {
+                    let client = self.client(clientid);
+                    client.sink.send(Response::<Game>::Feed {
+                        roomid,
+                        seq: self.room(roomid).seq,
+                        entries: vec![reminder.clone()],
+                    });
+                    if client.roomid == Some(roomid) {
+                        // Resending the board rings the bell of the client.
+                        client
+                            .sink
+                            .send(Response::Room(self.view(roomid, &current)));
+                    }
+                }
+                if policy.chat {
+                    self.log_activity(
+                        roomid,
+                        format!(
+                            "Reminder: it is {current}'s turn (waiting {})",
+                            format_duration(waiting)
+                        ),
+                    );
+                }
+            }
+            let room = &self.rooms[roomid];
+            if policy
+                .offer_after
+                .is_some_and(|offer_after| waiting >= offer_after)
+                && !room.offered
+            {
+                self.rooms[roomid].offered = true;
+                let offer = format!(
+                    "{current} did not move in room {roomid} for {}: use skip or substitute <user>",
+                    format_duration(waiting)
+                );
+                for clientid in self.user_clients(&self.rooms[roomid].creator) {
+                    self.client(clientid).sink.send(Response::<Game>::Feed {
+                        roomid,
+                        seq: self.room(roomid).seq,
+                        entries: vec![offer.clone()],
+                    });
+                }
+            }
+        }
+    }
+
+    /// Create the room in which bots play, with the bots as players.
+    fn create_demo_room(&mut self, demo: &Demo<Game::Settings>) {
+        let roomid = self.rooms.next_roomid();
+        let players: Vec<UserId> = (1..=demo.players).map(|id| format!("Bot{id}")).collect();
+        self.rooms.insert(
+            roomid,
+            ServerRoom {
+                room: crate::types::Room {
+                    roomid,
+                    settings: demo.settings.clone(),
+                    players: players.clone(),
+                    state: RoomState::WaitingForPlayers {
+                        min_players: demo.players,
+                        max_players: demo.players,
+                    },
+                    seq: 0,
+                    pace: Pace::default(),
+                    correspondence: None,
+                    spectator_view: Default::default(),
+                    turn_timer: None,
+                    turn_deadline: None,
+                    undo_votes: vec![],
+                    private: false,
+                    seating: Seating::default(),
+                    hide_deck: false,
+                    review: None,
+                    seats: Default::default(),
+                    notes: Default::default(),
+                },
+                creator: players[0].clone(),
+                watchers: vec![],
+                feed: vec![],
+                shared_spectator_chat: true,
+                last_activity: now(),
+                reminders: None,
+                reminders_sent: 0,
+                offered: false,
+                ended_at: None,
+                move_secs: vec![],
+                time_banks: HashMap::new(),
+                bots: vec![],
+                audit_log: vec![],
+                password: None,
+                kick_votes: HashMap::new(),
+                last_seen: HashMap::new(),
+                disconnected: HashMap::new(),
+                premoves: HashMap::new(),
+                notes: HashMap::new(),
+            },
+        );
+        self.demo = Some(roomid);
+    }
+
+    /// Make the next bot move in the demo room, or start a new game once the
+    /// previous one ended.
+    fn step_demo(&mut self, demo: &Demo<Game::Settings>) {
+        let Some(roomid) = self.demo else {
+            return;
+        };
+        let _span = tracing::info_span!("room", room = roomid.0).entered();
+        let room = &mut self.rooms[roomid];
+        match room.room.state {
+            RoomState::Started(_) => {
+                if room.room.state.skip_turn().is_err() {
+                    let _ = self.abandon(roomid, "The bots got stuck".into());
+                    return;
+                }
+                self.after_move(roomid);
+            }
+            RoomState::WaitingForPlayers { .. } | RoomState::Ended(_) => {
+                // Only the current game is kept, so the room does not grow.
+                room.room.state = RoomState::WaitingForPlayers {
+                    min_players: demo.players,
+                    max_players: demo.players,
+                };
+                room.feed.clear();
+                room.move_secs.clear();
+                room.audit_log.clear();
+                room.ended_at = None;
+                if let Err(err) = room.room.start_game() {
+                    tracing::error!("failed to start the demo game: {err}");
+                    self.demo = None;
+                    return;
+                }
+                self.start_turn(roomid);
+                self.log_activity(roomid, "A new demo game started".into());
+            }
+        }
+        self.broadcast_room(roomid);
+    }
+
+    /// Write the users and rooms to the state file, if there is one. The
+    /// file is replaced at once, so a crash while saving keeps the old one.
+    fn save(&self) {
+        let Some(path) = &self.state_file else {
+            return;
+        };
+        let snapshot = Snapshot {
+            users: &self.users,
+            rooms: self.rooms.values().collect::<Vec<_>>(),
+            demo: self.demo,
+            archive: Some(&self.archive),
+            next_roomid: Some(self.rooms.next_roomid),
+        };
+        let tmp = path.with_extension("tmp");
+        let result = serde_json::to_vec(&snapshot)
+            .map_err(std::io::Error::from)
+            .and_then(|data| std::fs::write(&tmp, data))
+            .and_then(|()| std::fs::rename(&tmp, path));
+        if let Err(err) = result {
+            tracing::error!("failed to save the state to {}: {err}", path.display());
+        }
+    }
+
+    /// Restore the users and rooms from the state file, if it exists.
+    fn load(&mut self) {
+        let Some(path) = &self.state_file else {
+            return;
+        };
+        let data = match std::fs::read(path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return,
+            Err(err) => {
+                tracing::error!("failed to read the state from {}: {err}", path.display());
+                std::process::exit(1);
+            }
+        };
+        let snapshot: Snapshot<HashMap<UserId, User>, Vec<ServerRoom<Game>>, Vec<_>> =
+            match serde_json::from_slice(&data) {
+                Ok(snapshot) => snapshot,
+                Err(err) => {
+                    tracing::error!("failed to parse the state in {}: {err}", path.display());
+                    std::process::exit(1);
+                }
+            };
+        tracing::info!(
+            "loaded {} users and {} rooms from {}",
+            snapshot.users.len(),
+            snapshot.rooms.len(),
+            path.display()
+        );
+        self.users = snapshot.users;
+        self.rooms = Rooms::new(snapshot.rooms, snapshot.next_roomid);
+        // Nobody is connected yet, so give all players time to come back.
+        let now = now();
+        for room in self.rooms.values_mut() {
+            // Feeds saved by older versions may still contain styles.
+            for entry in &mut room.feed {
+                entry.text = strip_styles(&entry.text);
+            }
+            if matches!(room.room.state, RoomState::Started(_)) {
+                room.disconnected = room
+                    .room
+                    .players
+                    .iter()
+                    .map(|player| (player.clone(), now))
+                    .collect();
+            }
+        }
+        self.demo = snapshot.demo;
+        match snapshot.archive {
+            Some(archive) => self.archive = archive,
+            None => {
+                let mut ended: Vec<_> = self
+                    .rooms
+                    .ids()
+                    .into_iter()
+                    .filter(|&roomid| self.rooms[roomid].ended_at.is_some())
+                    .collect();
+                ended.sort_by_key(|roomid| self.rooms[*roomid].ended_at);
+                for roomid in ended {
+                    self.archive(roomid);
+                }
+            }
+        }
+    }
+
+    /// Send the current view of the room to all its watchers.
+    fn broadcast_room(&mut self, roomid: RoomId) {
+        self.next_seq(roomid);
+        for watching_client in self.watchers(roomid) {
+            let client = self.client(*watching_client);
+            client.sink.send(Response::Room(
+                // Anonymous watchers of the demo room get the spectator view.
+                self.view(roomid, &client.userid.clone().unwrap_or_default()),
+            ));
+        }
+    }
+
+    /// End a started game early, e.g. on a forfeit or inactivity.
+    fn abandon(&mut self, roomid: RoomId, reason: String) -> Result<(), &'static str> {
+        self.room_mut(roomid).state.abandon()?;
+        tracing::info!("abandoned: {reason}");
+        self.rooms[roomid].ended_at = Some(now());
+        self.archive(roomid);
+        self.log_activity(roomid, reason);
+        self.log_activity(roomid, "The game was abandoned".into());
+        self.broadcast_room(roomid);
+        Ok(())
+    }
+
+    /// Abandon all started games without moves for too long.
+    fn abandon_inactive_games(&mut self) {
+        let deadline = now().saturating_sub(self.limits.abandon_after);
+        let inactive: Vec<_> = self
+            .rooms
+            .values()
+            .filter(|room| matches!(room.room.state, RoomState::Started(_)))
+            // Correspondence rooms skip players who run out of time instead.
+            .filter(|room| room.room.correspondence.is_none())
+            .filter(|room| room.last_activity < deadline)
+            .map(|room| room.room.roomid)
+            .collect();
+        for roomid in inactive {
+            let minutes = self.limits.abandon_after / 60;
+            let _ = self.abandon(roomid, format!("No moves for {minutes} minutes"));
+        }
+    }
+
+    /// Abandon all started games of which a player lost their connection and
+    /// did not open the room again within the grace period. Players who left
+    /// the room themselves are covered by the inactivity timeout instead.
+    fn abandon_deserted_games(&mut self) {
+        let deadline = now().saturating_sub(self.limits.abandon_disconnected_after);
+        let deserted: Vec<_> = self
+            .rooms
+            .values()
+            .filter(|room| matches!(room.room.state, RoomState::Started(_)))
+            // Players of correspondence rooms are expected to come and go.
+            .filter(|room| room.room.correspondence.is_none())
+            .filter_map(|room| {
+                let roomid = room.room.roomid;
+                room.room
+                    .players
+                    .iter()
+                    .filter(|player| !room.bots.contains(player))
+                    .filter(|player| !self.seat_status(roomid, player).online)
+                    .find(|player| {
+                        room.disconnected
+                            .get(*player)
+                            .is_some_and(|&t| t < deadline)
+                    })
+                    .map(|player| (roomid, player.clone()))
+            })
+            .collect();
+        for (roomid, player) in deserted {
+            let minutes = self.limits.abandon_disconnected_after / 60;
+            let _ = self.abandon(
+                roomid,
+                format!("{player} did not come back within {minutes} minutes"),
+            );
+        }
+    }
+
+    /// Remove rooms that waited for players without activity for too long,
+    /// and rooms whose game ended long enough ago. The demo room is kept.
+    fn expire_rooms(&mut self) {
+        let now = now();
+        let expired: Vec<_> = self
+            .rooms
+            .values()
+            .filter(|room| self.demo != Some(room.room.roomid))
+            .filter(|room| match room.room.state {
+                RoomState::WaitingForPlayers { .. } => {
+                    room.last_activity + self.limits.expire_pending_after <= now
+                }
+                RoomState::Started(_) => false,
+                // Rooms stay while someone reviews their game.
+                RoomState::Ended(_) => {
+                    room.watchers.is_empty()
+                        && room.ended_at.unwrap_or(room.last_activity) + self.limits.keep_ended_for
+                            <= now
+                }
+            })
+            .map(|room| room.room.roomid)
+            .collect();
+        for roomid in expired {
+            tracing::info!(room = roomid.0, "expired");
+            let room = self.rooms.remove(&roomid).unwrap();
+            for clientid in room.watchers {
+                self.client_mut(clientid).roomid = None;
+                let Some(userid) = self.client(clientid).userid.clone() else {
+                    continue;
+                };
+                let client = self.client(clientid);
+                client
+                    .sink
+                    .send(Response::<Game>::Error(format!("Room {roomid} expired")));
+                client.sink.send(self.room_list(&userid));
+            }
+        }
+    }
+
+    /// Record the finished game of the room in the archive. Games of the
+    /// demo room are not kept.
+    fn archive(&mut self, roomid: RoomId) {
+        if self.demo == Some(roomid) {
+            return;
+        }
+        let room = &self.rooms[roomid];
+        let (RoomState::Ended(Some(game)), Some(ended_at)) = (&room.room.state, room.ended_at)
+        else {
+            return;
+        };
+        self.archive.push(HistoryEntry {
+            roomid,
+            settings: room.room.public_settings(),
+            players: room.room.players.clone(),
+            score: game.score(),
+            turns: game.num_moves(),
+            abandoned: game.is_abandoned(),
+            ended_at,
+        });
+    }
+
+    /// All finished games the user played in, most recent first.
+    fn history(&self, userid: &UserId) -> Vec<HistoryEntry<Game>> {
+        let mut history: Vec<_> = self
+            .archive
+            .iter()
+            .filter(|entry| entry.players.contains(userid))
+            .cloned()
+            .collect();
+        history.sort_by_key(|entry| std::cmp::Reverse(entry.ended_at));
+        history
+    }
+
+    /// Best score among the other finished games with the same settings and
+    /// number of players.
+    fn best_score(&self, roomid: RoomId) -> Option<usize> {
+        let room = self.room(roomid);
+        let settings = room.settings.to_string();
+        self.archive
+            .iter()
+            .filter(|entry| entry.roomid != roomid && !entry.abandoned)
+            .filter(|entry| entry.players.len() == room.players.len())
+            .filter(|entry| entry.settings.to_string() == settings)
+            .map(|entry| entry.score)
+            .max()
+    }
+
+    /// Scores of the archived games per settings and number of players.
+    fn leaderboard(&self) -> Vec<LeaderboardEntry<Game>> {
+        let mut groups: Vec<(String, Vec<&HistoryEntry<Game>>)> = vec![];
+        for entry in self.archive.iter().filter(|entry| !entry.abandoned) {
+            let key = format!("{} {}", entry.settings, entry.players.len());
+            match groups.iter_mut().find(|(k, _)| *k == key) {
+                Some((_, games)) => games.push(entry),
+                None => groups.push((key, vec![entry])),
+            }
+        }
+        groups.sort_by(|(a, _), (b, _)| a.cmp(b));
+        groups
+            .into_iter()
+            .map(|(_, games)| {
+                let n = games.len() as f64;
+                LeaderboardEntry {
+                    settings: games[0].settings.clone(),
+                    players: games[0].players.len(),
+                    games: games.len(),
+                    average_score: games.iter().map(|g| g.score).sum::<usize>() as f64 / n,
+                    best_score: games.iter().map(|g| g.score).max().unwrap(),
+                    average_turns: games.iter().map(|g| g.turns).sum::<usize>() as f64 / n,
+                }
+            })
+            .collect()
+    }
+
+    /// Tell the room when its game beat the best score so far.
+    fn announce_record(&mut self, roomid: RoomId) {
+        let RoomState::Ended(Some(game)) = &self.room(roomid).state else {
+            return;
+        };
+        if game.is_abandoned() {
+            return;
+        }
+        let score = game.score();
+        let best = self.best_score(roomid);
+        if score == 0 || best.is_some_and(|best| score <= best) {
+            return;
+        }
+        let room = self.room(roomid);
+        let mut entry = format!(
+            "New record for {} with {} players: {score}",
+            room.public_settings(),
+            room.players.len()
+        );
+        if let Some(best) = best {
+            entry += &format!(" (previous best {best})");
+        }
+        self.log_activity(roomid, entry);
+    }
+
+    /// Check that the client may enter the room: it is public, the user is
+    /// a member or already watches it, or the password is right.
+    fn check_password(
+        &self,
+        clientid: ClientId,
+        roomid: RoomId,
+        password: Option<&str>,
+    ) -> Result<(), &'static str> {
+        let room = &self.rooms[roomid];
+        let Some(room_password) = &room.password else {
+            return Ok(());
+        };
+        let client = self.client(clientid);
+        if client.roomid == Some(roomid)
+            || client
+                .userid
+                .as_ref()
+                .is_some_and(|userid| room.is_member(userid))
+        {
+            return Ok(());
+        }
+        match password {
+            None => Err("This room is private; pass its password"),
+            Some(password) if password != room_password => Err("Wrong password"),
+            Some(_) => Ok(()),
+        }
+    }
+
+    fn watch_room(&mut self, clientid: std::net::SocketAddr, roomid: RoomId) {
+        if self.client(clientid).roomid == Some(roomid) {
+            return;
+        }
+        self.leave_room(clientid);
+        // Catch up on the activity so far before joining the live feed.
+        self.send_feed(roomid, clientid);
+        let userid = self.client(clientid).userid.clone();
+        if let Some(userid) = &userid {
+            self.log_activity(roomid, format!("{userid} started watching"));
+        }
+        let returning = userid.filter(|userid| {
+            self.room(roomid).players.contains(userid) && !self.seat_status(roomid, userid).online
+        });
+        self.client_mut(clientid).roomid = Some(roomid);
+        self.watchers_mut(roomid).push(clientid);
+        // Show the other players that the player is back.
+        if let Some(userid) = returning {
+            self.rooms[roomid].disconnected.remove(&userid);
+            self.broadcast_room(roomid);
+        }
+    }
+
+    /// Hand the client a new token with which it can resume the login of
+    /// the user on another socket.
+    fn start_session(&mut self, clientid: ClientId, userid: &UserId) {
+        let token: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(32)
+            .map(char::from)
+            .collect();
+        let sessions = &mut self.users.get_mut(userid).unwrap().sessions;
+        sessions.push(Session {
+            token: token.clone(),
+            roomid: None,
+        });
+        if sessions.len() > MAX_SESSIONS {
+            sessions.remove(0);
+        }
+        self.client_mut(clientid).session = Some(token.clone());
+        self.client(clientid)
+            .sink
+            .send(Response::<Game>::Session(token));
+    }
+
+    /// The user and last watched room of the session with the token.
+    fn find_session(&self, token: &str) -> Option<(UserId, Option<RoomId>)> {
+        self.users.iter().find_map(|(userid, user)| {
+            let session = user
+                .sessions
+                .iter()
+                .find(|session| session.token == token)?;
+            Some((userid.clone(), session.roomid))
+        })
+    }
+
+    /// The session of the client, if it is logged in.
+    fn session_mut(&mut self, clientid: ClientId) -> Option<&mut Session> {
+        let Client {
+            userid, session, ..
+        } = self.clients.get(&clientid)?;
+        let token = session.as_ref()?;
+        self.users
+            .get_mut(userid.as_ref()?)?
+            .sessions
+            .iter_mut()
+            .find(|session| &session.token == token)
+    }
+
+    /// The room with the given id, unless it does not exist or has a
+    /// password.
+    fn public_room(&self, roomid: &str) -> Result<&ServerRoom<Game>, HttpError> {
+        roomid
+            .parse::<RoomId>()
+            .ok()
+            .and_then(|roomid| self.rooms.get(&roomid))
+            .filter(|room| room.password.is_none())
+            .ok_or(HttpError(StatusCode::NOT_FOUND, "No such room"))
+    }
+
+    /// Counts of rooms, users and connections, in the text format of
+    /// Prometheus.
+    fn metrics(&self) -> String {
+        let mut metrics = String::new();
+        for status in [RoomStatus::Pending, RoomStatus::Started, RoomStatus::Ended] {
+            let rooms = self
+                .rooms
+                .values()
+                .filter(|room| room.room.state.status() == status)
+                .count();
+            metrics += &format!("turnbased_rooms{{status=\"{status}\"}} {rooms}\n");
+        }
+        let online = self
+            .clients
+            .values()
+            .filter_map(|client| client.userid.as_ref())
+            .unique()
+            .count();
+        metrics += &format!("turnbased_users {}\n", self.users.len());
+        metrics += &format!("turnbased_users_online {online}\n");
+        metrics += &format!("turnbased_connections {}\n", self.clients.len());
+        metrics += &format!("turnbased_archived_games {}\n", self.archive.len());
+        metrics
+    }
+
+    /// Parse a line typed in the browser client like the terminal client
+    /// does, reading moves in the context of the game the client watches.
+    fn parse_line(&self, clientid: ClientId, line: &str) -> Result<Action<Game>, &'static str> {
+        if line.parse::<Game::ClientAction>().is_ok() {
+            return Err("This action is only available in the terminal client.");
+        }
+        let game = self
+            .client(clientid)
+            .roomid
+            .and_then(|roomid| self.room(roomid).game());
+        if let (Some(mov), Some(game)) = (line.trim().strip_prefix("check "), game) {
+            return Ok(Action::ProbeMove(game.parse_move(mov.trim())?));
+        }
+        if let (Some(mov), Some(game)) = (line.trim().strip_prefix("premove "), game) {
+            if mov.trim() == "off" {
+                return Ok(Action::Premove(None));
+            }
+            // Refer to cards by id, which still holds once it is their turn.
+            let mov = game.parse_move(mov.trim())?;
+            let userid = self.client(clientid).userid.clone().unwrap_or_default();
+            return Ok(Action::Premove(Some(game.pin_move(&userid, mov))));
+        }
+        let action = line.parse::<Action<Game>>();
+        if let (Ok(Action::MakeMove(_)) | Err(_), Some(game)) = (&action, game) {
+            if let Ok(mov) = game.parse_move(line) {
+                return Ok(Action::MakeMove(mov));
+            }
+        }
+        action
+    }
+
+    /// Forget the socket, which was closed or, with `timed_out`, stopped
+    /// answering pings.
+    fn disconnect(&mut self, clientid: std::net::SocketAddr, timed_out: bool) {
+        // Remember the room, so the session resumes watching it.
+        let roomid = self.client(clientid).roomid;
+        if let Some(session) = self.session_mut(clientid) {
+            session.roomid = roomid;
+        }
+        if timed_out {
+            tracing::info!("timed out");
+            self.leave_room_as(clientid, "lost connection");
+        } else {
+            tracing::info!("disconnected");
+            self.leave_room_as(clientid, "disconnected");
+        }
+        let Client { userid, .. } = self.clients.remove(&clientid).unwrap();
+        // Start the grace period of a player who no longer has the room open.
+        if let (Some(roomid), Some(userid)) = (roomid, &userid) {
+            if self.room(roomid).players.contains(userid)
+                && !self.seat_status(roomid, userid).online
+            {
+                self.rooms[roomid]
+                    .disconnected
+                    .insert(userid.clone(), now());
+            }
+        }
         if let Some(userid) = userid {
             self.users
                 .get_mut(&userid)
@@ -230,13 +2561,19 @@ impl<Game: GameT> ServerState<Game> {
     }
 
     fn connect(&mut self, clientid: std::net::SocketAddr, sink: Sink) {
-        eprintln!("{} connected", &clientid);
+        tracing::info!("connected");
         self.clients.insert(
             clientid,
             Client {
                 sink: sink.clone(),
                 userid: None,
                 roomid: None,
+                // The browser client is served by this server, so it speaks
+                // its protocol.
+                greeted: sink.text,
+                session: None,
+                action_times: VecDeque::new(),
+                last_seen: now(),
             },
         );
         sink.send(Response::<Game>::NotLoggedIn);
@@ -244,61 +2581,325 @@ impl<Game: GameT> ServerState<Game> {
 
     fn start_game(&mut self, userid: &UserId, roomid: RoomId) -> Result<(), &'static str> {
         let room = self.room_mut(roomid);
-        if !room.players.contains(&userid) {
-            Err("User did not join room")
-        } else {
-            Ok(room.start_game())
+        if !room.players.contains(userid) {
+            return Err("User did not join room");
         }
+        if !matches!(room.state, RoomState::WaitingForPlayers { .. }) {
+            return Ok(());
+        }
+        room.start_game()?;
+        self.start_turn(roomid);
+        let entry = match self.room(roomid).state.current_player() {
+            Some(first) => format!("{userid} started the game; {first} goes first"),
+            None => format!("{userid} started the game"),
+        };
+        self.log_activity(roomid, entry);
+        self.play_bots(roomid);
+        Ok(())
     }
 
     fn leave_room(&mut self, clientid: ClientId) {
+        self.leave_room_as(clientid, "left");
+    }
+
+    /// Stop watching the room, telling its watchers that the user `verb`.
+    fn leave_room_as(&mut self, clientid: ClientId, verb: &str) {
         if let Some(roomid) = self.clients.get(&clientid).unwrap().roomid {
             self.watchers_mut(roomid).retain(|x| x != &clientid);
             self.clients.get_mut(&clientid).unwrap().roomid = None;
+            if let Some(userid) = self.client(clientid).userid.clone() {
+                self.log_activity(roomid, format!("{userid} {verb}"));
+                // Show the other players that the player is gone.
+                if self.room(roomid).players.contains(&userid)
+                    && !self.seat_status(roomid, &userid).online
+                {
+                    self.rooms[roomid].last_seen.insert(userid, now());
+                    self.broadcast_room(roomid);
+                }
+            }
         }
     }
 
     fn logout(&mut self, clientid: ClientId) {
         self.leave_room(clientid);
-        // Disassociate the user from the client.
-        let userid = &mut self.clients.get_mut(&clientid).unwrap().userid;
+        // Disassociate the user from the client, and end its session.
+        let Client {
+            userid, session, ..
+        } = self.clients.get_mut(&clientid).unwrap();
+        let token = session.take();
         if let Some(loggedin_userid) = userid {
-            self.users
-                .get_mut(loggedin_userid)
-                .unwrap()
-                .sockets
-                .retain(|x| x != &clientid);
+            let user = self.users.get_mut(loggedin_userid).unwrap();
+            user.sockets.retain(|x| x != &clientid);
+            user.sessions
+                .retain(|session| Some(&session.token) != token.as_ref());
             *userid = None;
         }
     }
 }
 
 impl<Game: GameT> Server<Game> {
-    async fn start(address: &str) {
-        eprintln!("Listen on {address}");
-        let server = Server::<Game>::new();
+    async fn start(
+        address: &str,
+        limits: Limits,
+        notify_command: Option<String>,
+        demo: Option<Demo<Game::Settings>>,
+        state_file: Option<PathBuf>,
+        tls: Option<Tls>,
+    ) {
+        tracing::info!("listening on {address}");
+        let server = Server::<Game>::new(limits, notify_command, state_file);
+        tokio::spawn(server.clone().tick());
+        tokio::spawn(server.clone().run_turn_timers());
+        if let Some(demo) = demo {
+            let mut state = server.state.lock().unwrap();
+            if state.demo.is_none() {
+                state.create_demo_room(&demo);
+            }
+            drop(state);
+            tokio::spawn(server.clone().run_demo(demo));
+        }
+        let tls = tls.map(|tls| tls.acceptor());
+        let router = server.clone().router();
         let listener = TcpListener::bind(&address).await.unwrap();
-        while let Ok((stream, clientid)) = listener.accept().await {
-            tokio::spawn(server.clone().handle_connection(stream, clientid));
+        let shutdown = shutdown_signal();
+        pin_mut!(shutdown);
+        loop {
+            let (stream, clientid) = tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok(accepted) => accepted,
+                    Err(_) => break,
+                },
+                () = &mut shutdown => break,
+            };
+            let span = tracing::info_span!("connection", client = %clientid);
+            // Refuse what is refused anyway before spending time on it.
+            if let Err(limit) = server.state.lock().unwrap().open_connection(clientid) {
+                span.in_scope(|| tracing::warn!("refused connection: {limit}"));
+                continue;
+            }
+            let open = Arc::new(OpenConnection {
+                server: server.clone(),
+                clientid,
+            });
+            let router = router
+                .clone()
+                .layer(Extension(ConnectInfo(clientid)))
+                .layer(Extension(open));
+            let Some(tls) = tls.clone() else {
+                tokio::spawn(serve_connection(stream, router).instrument(span));
+                continue;
+            };
+            let connection = async move {
+                match tokio::time::timeout(HANDSHAKE_TIMEOUT, tls.accept(stream)).await {
+                    Ok(Ok(stream)) => serve_connection(stream, router).await,
+                    Ok(Err(err)) => tracing::info!("TLS handshake failed: {err}"),
+                    Err(_) => tracing::info!("TLS handshake timed out"),
+                }
+            };
+            tokio::spawn(connection.instrument(span));
+        }
+        server.shut_down().await;
+    }
+
+    /// Save the state, tell all clients that the server restarts and close
+    /// their sockets. Actions are refused from then on, so no move is lost.
+    async fn shut_down(self) {
+        tracing::info!("shutting down");
+        {
+            let mut state = self.state.lock().unwrap();
+            state.shutting_down = true;
+            state.save();
+            for client in state.clients.values() {
+                client.sink.send(Response::<Game>::ServerRestarting);
+                client.sink.close();
+            }
         }
+        // Give the sockets a moment to deliver the message and close.
+        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
     }
 
-    fn new() -> Self {
+    fn new(limits: Limits, notify_command: Option<String>, state_file: Option<PathBuf>) -> Self {
+        let mut state = ServerState {
+            users: Default::default(),
+            rooms: Default::default(),
+            archive: Default::default(),
+            clients: Default::default(),
+            limits,
+            notify_command,
+            demo: None,
+            state_file,
+            bans: HashMap::new(),
+            connections: HashMap::new(),
+            shutting_down: false,
+        };
+        state.load();
         Server {
-            state: Arc::new(Mutex::new(ServerState {
-                users: Default::default(),
-                rooms: Default::default(),
-                clients: Default::default(),
-            })),
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    /// Periodically send turn reminders, enforce time banks, abandon games
+    /// in which nobody moved for too long or that a player left by losing
+    /// their connection, remove idle and old rooms and save the state.
+    async fn tick(self) {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(60));
+        loop {
+            interval.tick().await;
+            let mut state = self.state.lock().unwrap();
+            state.send_reminders();
+            state.enforce_time_banks();
+            state.abandon_inactive_games();
+            state.abandon_deserted_games();
+            state.expire_rooms();
+            state.save();
         }
     }
 
-    async fn handle_connection(self, raw_stream: TcpStream, clientid: ClientId) {
-        let ws_stream = tokio_tungstenite::accept_async(raw_stream)
-            .await
-            .expect("Error during the websocket handshake occurred");
-        eprintln!("WebSocket connection established: {}", clientid);
+    /// Check the turn timers every second.
+    async fn run_turn_timers(self) {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(1));
+        loop {
+            interval.tick().await;
+            self.state.lock().unwrap().enforce_turn_timers();
+        }
+    }
+
+    /// Let the bots of the demo room move forever.
+    async fn run_demo(self, demo: Demo<Game::Settings>) {
+        let mut interval =
+            tokio::time::interval(std::time::Duration::from_secs(demo.move_secs.max(1)));
+        loop {
+            interval.tick().await;
+            self.state.lock().unwrap().step_demo(&demo);
+        }
+    }
+
+    /// Routes of the websockets of the terminal client, on any path, and of
+    /// the browser client, on `TEXT_PATH`. Besides the browser client, which
+    /// is served to plain requests on any other path, these read-only
+    /// resources can be fetched without a websocket session:
+    ///
+    /// - `/rooms`: the rooms without password, as in the room list.
+    /// - `/rooms/<id>`: the room as a spectator sees it.
+    /// - `/rooms/<id>/replay`: the game after each of its moves, as a
+    ///   spectator sees it until the game ends.
+    /// - `/health`: `ok` while the server runs.
+    /// - `/metrics`: counts of rooms, users and connections, in the text
+    ///   format of Prometheus.
+    fn router(self) -> Router {
+        Router::new()
+            .route("/health", get(|| async { "ok\n" }))
+            .route("/metrics", get(Self::get_metrics))
+            .route("/rooms", get(Self::get_rooms))
+            .route("/rooms/{roomid}", get(Self::get_room))
+            .route("/rooms/{roomid}/replay", get(Self::get_replay))
+            .route(TEXT_PATH, get(Self::open_text_socket))
+            .fallback(Self::open_socket_or_client)
+            .with_state(self)
+    }
+
+    async fn get_metrics(State(server): State<Self>) -> String {
+        server.state.lock().unwrap().metrics()
+    }
+
+    async fn get_rooms(State(server): State<Self>) -> Json<Vec<Room<Game>>> {
+        let state = server.state.lock().unwrap();
+        let rooms = state
+            .rooms
+            .values()
+            .filter(|room| room.password.is_none())
+            .map(|room| room.room.to_list_item())
+            .collect();
+        Json(rooms)
+    }
+
+    async fn get_room(
+        State(server): State<Self>,
+        Path(roomid): Path<String>,
+    ) -> Result<Json<Room<Game>>, HttpError> {
+        let state = server.state.lock().unwrap();
+        let room = state.public_room(&roomid)?;
+        Ok(Json(room.room.to_view(&UserId::new())))
+    }
+
+    async fn get_replay(
+        State(server): State<Self>,
+        Path(roomid): Path<String>,
+    ) -> Result<Json<Vec<Game>>, HttpError> {
+        let state = server.state.lock().unwrap();
+        let room = &state.public_room(&roomid)?.room;
+        let (RoomState::Started(Some(game)) | RoomState::Ended(Some(game))) = &room.state else {
+            return Err(HttpError(
+                StatusCode::NOT_FOUND,
+                "The game in this room has not started",
+            ));
+        };
+        let ended = matches!(room.state, RoomState::Ended(_));
+        (0..=game.num_moves())
+            .map(|turn| {
+                let game = game.replay(turn)?;
+                Ok(if ended {
+                    room.ended_view(&game)
+                } else {
+                    game.to_spectator_view(room.spectator_view)
+                })
+            })
+            .collect::<Result<_, _>>()
+            .map(Json)
+            .map_err(|err| HttpError(StatusCode::INTERNAL_SERVER_ERROR, err))
+    }
+
+    async fn open_text_socket(
+        State(server): State<Self>,
+        ConnectInfo(clientid): ConnectInfo<ClientId>,
+        Extension(open): Extension<Arc<OpenConnection<Game>>>,
+        ws: WebSocketUpgrade,
+    ) -> axum::response::Response {
+        server.upgrade(ws, clientid, true, open)
+    }
+
+    async fn open_socket_or_client(
+        State(server): State<Self>,
+        ConnectInfo(clientid): ConnectInfo<ClientId>,
+        Extension(open): Extension<Arc<OpenConnection<Game>>>,
+        method: Method,
+        ws: Result<WebSocketUpgrade, WebSocketUpgradeRejection>,
+    ) -> axum::response::Response {
+        match ws {
+            Ok(ws) => server.upgrade(ws, clientid, false, open),
+            Err(_) if method == Method::GET => Html(WEB_CLIENT).into_response(),
+            Err(_) => {
+                HttpError(StatusCode::METHOD_NOT_ALLOWED, "Only GET is supported").into_response()
+            }
+        }
+    }
+
+    /// Upgrade the connection to a websocket, served by `handle_socket`.
+    fn upgrade(
+        self,
+        ws: WebSocketUpgrade,
+        clientid: ClientId,
+        text: bool,
+        open: Arc<OpenConnection<Game>>,
+    ) -> axum::response::Response {
+        // The socket is served by a task of its own, outside the span of
+        // the connection.
+        let span = tracing::info_span!(parent: None, "connection", client = %clientid);
+        ws.on_upgrade(move |socket| {
+            self.handle_socket(socket, clientid, text, open)
+                .instrument(span)
+        })
+    }
 
+    /// Serve the websocket of a client until it closes. The connection stays
+    /// counted until then.
+    async fn handle_socket(
+        self,
+        ws_stream: WebSocket,
+        clientid: ClientId,
+        text: bool,
+        _open: Arc<OpenConnection<Game>>,
+    ) {
         // Write and read part of the websocket stream.
         let (ws_outgoing, ws_incoming) = ws_stream.split();
 
@@ -308,38 +2909,482 @@ impl<Game: GameT> Server<Game> {
         let receive_from_others = internal_stream.map(Ok).forward(ws_outgoing);
 
         // Wrap the internal sink to accept Action.
-        let sink = Sink(sink);
-        self.state.lock().unwrap().connect(clientid, sink);
+        let sink = Sink { sender: sink, text };
+        self.state.lock().unwrap().connect(clientid, sink.clone());
 
         // Process all incoming messages on this websocket.
-        let handle_incoming = ws_incoming.try_for_each(|msg| {
-            if !msg.is_binary() {
-                return future::ok(());
+        let server = &self;
+        let handle_incoming = ws_incoming.try_for_each(|msg| async move {
+            server.state.lock().unwrap().client_mut(clientid).last_seen = now();
+            if text {
+                if let Message::Text(line) = msg {
+                    server.handle_line(clientid, &line).await;
+                }
+                return Ok(());
             }
-            match serde_json::from_slice(&msg.into_data()) {
-                Ok(action) => self.handle_action(clientid, action),
+            let Message::Binary(data) = msg else {
+                return Ok(());
+            };
+            match serde_json::from_slice(&data) {
+                Ok(action) => server.handle_action(clientid, action).await,
                 Err(err) => {
-                    eprintln!("Failed to parse message as json: {:?}", err);
-                    return future::ok(());
+                    tracing::warn!("failed to parse message as json: {err}");
+                    let state = server.state.lock().unwrap();
+                    let response = Response::<Game>::Error(version_mismatch(None));
+                    state.client(clientid).sink.send(response);
                 }
             };
-            future::ok(())
+            Ok(())
         });
 
-        pin_mut!(handle_incoming, receive_from_others);
-        future::select(handle_incoming, receive_from_others).await;
+        // Ping the socket, until it stays silent for too long.
+        let heartbeat = async {
+            let limits = self.state.lock().unwrap().limits;
+            let mut interval =
+                tokio::time::interval(std::time::Duration::from_secs(limits.ping_interval.max(1)));
+            loop {
+                interval.tick().await;
+                let last_seen = self.state.lock().unwrap().client(clientid).last_seen;
+                if now().saturating_sub(last_seen) >= limits.connection_timeout {
+                    return;
+                }
+                sink.ping();
+            }
+        };
+
+        pin_mut!(handle_incoming, receive_from_others, heartbeat);
+        let timed_out = tokio::select! {
+            _ = handle_incoming => false,
+            _ = receive_from_others => false,
+            _ = heartbeat => true,
+        };
 
-        self.state.lock().unwrap().disconnect(clientid);
+        self.state.lock().unwrap().disconnect(clientid, timed_out);
     }
 
-    fn handle_action(&self, clientid: ClientId, action: Action<Game>) {
+    async fn handle_action(&self, clientid: ClientId, action: Action<Game>) {
+        let hashed = match self.hash_password(clientid, &action) {
+            Some(hashing) => hashing.await.unwrap(),
+            None => None,
+        };
         let server = &mut self.state.lock().unwrap();
-        if let Some(response) = server.handle_action(clientid, action) {
+        if let Some(response) = server.handle_action(clientid, action, hashed) {
             server.client(clientid).sink.send(response);
         }
     }
+
+    /// Handle a line typed in the browser client.
+    async fn handle_line(&self, clientid: ClientId, line: &str) {
+        let action = self.state.lock().unwrap().parse_line(clientid, line);
+        match action {
+            Ok(action) => self.handle_action(clientid, action).await,
+            Err(err) => {
+                let server = self.state.lock().unwrap();
+                server
+                    .client(clientid)
+                    .sink
+                    .send(Response::<Game>::Error(err.into()));
+            }
+        }
+    }
+
+    /// Start to hash the password of a `Login` or `Register` action. Hashing
+    /// takes a while, so it runs on a blocking thread without the state
+    /// locked.
+    fn hash_password(
+        &self,
+        clientid: ClientId,
+        action: &Action<Game>,
+    ) -> Option<JoinHandle<Option<Hashed>>> {
+        let (password, hash) = {
+            let state = self.state.lock().unwrap();
+            let client = state.client(clientid);
+            if !client.greeted {
+                return None;
+            }
+            match action {
+                Action::Login(userid, Some(password), _) => {
+                    let hash = state.users.get(userid)?.password.clone()?;
+                    (password.clone(), Some(hash))
+                }
+                Action::Register(password) if client.userid.is_some() => (password.clone(), None),
+                _ => return None,
+            }
+        };
+        Some(spawn_blocking(move || match hash {
+            Some(hash) => hash.matches(&password).then_some(Hashed::Matches(hash)),
+            None => Some(Hashed::New(PasswordHash::new(&password))),
+        }))
+    }
+}
+
+/// Resolves once the process receives SIGINT or SIGTERM.
+async fn shutdown_signal() {
+    let mut terminate = signal(SignalKind::terminate()).unwrap();
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        _ = terminate.recv() => {}
+    }
+}
+
+/// Serve the requests of a connection, including its upgrade to a
+/// websocket, with the routes of `Server::router`.
+async fn serve_connection<S>(stream: S, router: Router)
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let connection = http1::Builder::new()
+        .timer(TokioTimer::new())
+        .header_read_timeout(HANDSHAKE_TIMEOUT)
+        .serve_connection(TokioIo::new(stream), TowerToHyperService::new(router))
+        .with_upgrades();
+    if let Err(err) = connection.await {
+        tracing::debug!("connection failed: {err}");
+    }
+}
+
+/// A connection counted by `ServerState::open_connection`, until this is
+/// dropped.
+struct OpenConnection<Game: GameT> {
+    server: Server<Game>,
+    clientid: ClientId,
+}
+
+impl<Game: GameT> Drop for OpenConnection<Game> {
+    fn drop(&mut self) {
+        self.server
+            .state
+            .lock()
+            .unwrap()
+            .close_connection(self.clientid);
+    }
+}
+
+/// An error of an HTTP request, answered with its message as JSON, like
+/// `{"error": "..."}`.
+struct HttpError(StatusCode, &'static str);
+
+impl IntoResponse for HttpError {
+    fn into_response(self) -> axum::response::Response {
+        (self.0, Json(HashMap::from([("error", self.1)]))).into_response()
+    }
 }
 
-pub async fn start_server<Game: GameT>(address: &str) {
-    Server::<Game>::start(address).await;
+/// Explains that the client speaks `client_version` of the protocol, or one
+/// without handshake, which this server does not understand.
+fn version_mismatch(client_version: Option<u32>) -> String {
+    match client_version {
+        Some(version) if version > PROTOCOL_VERSION => format!(
+            "The client speaks protocol version {version}, but this server only \
+             version {PROTOCOL_VERSION}. Please use an older client."
+        ),
+        _ => format!(
+            "This server speaks protocol version {PROTOCOL_VERSION}, which the \
+             client does not. Please upgrade the client."
+        ),
+    }
+}
+
+/// Run the server. Offline players of correspondence rooms are notified of
+/// their turn with `notify_command`, see `ServerState::notify_command`.
+/// With `demo`, bots play in a room that anyone can watch. With
+/// `state_file`, users and rooms are loaded from the file on startup and
+/// saved to it every minute. With `tls`, clients connect over `wss://`
+/// and the browser client is served over `https://`. Logs go to the global
+/// `tracing` subscriber, e.g. the one of `logging::init`.
+pub async fn start_server<Game: GameT>(
+    address: &str,
+    limits: Limits,
+    notify_command: Option<String>,
+    demo: Option<Demo<Game::Settings>>,
+    state_file: Option<PathBuf>,
+    tls: Option<Tls>,
+) {
+    Server::<Game>::start(address, limits, notify_command, demo, state_file, tls).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_channel::mpsc::UnboundedReceiver;
+    use std::fmt::Display;
+    use std::str::FromStr;
+
+    /// Players take turns adding to a sum, until it reaches the goal.
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    struct Sum {
+        players: Vec<String>,
+        turn: usize,
+        sum: usize,
+        goal: Goal,
+        abandoned: bool,
+    }
+
+    #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+    struct Goal(usize);
+
+    #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+    struct Add(usize);
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    enum NoAction {}
+
+    impl Display for Goal {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "to {}", self.0)
+        }
+    }
+
+    impl FromStr for Goal {
+        type Err = &'static str;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            s.parse().map(Goal).map_err(|_| "Invalid goal")
+        }
+    }
+
+    impl FromStr for Add {
+        type Err = &'static str;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            s.parse().map(Add).map_err(|_| "Invalid number")
+        }
+    }
+
+    impl FromStr for NoAction {
+        type Err = &'static str;
+
+        fn from_str(_: &str) -> Result<Self, Self::Err> {
+            Err("No client actions")
+        }
+    }
+
+    impl Display for Sum {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{} of {}", self.sum, self.goal.0)
+        }
+    }
+
+    impl GameT for Sum {
+        type Settings = Goal;
+        type Move = Add;
+        type ClientAction = NoAction;
+
+        fn new(players: Vec<String>, goal: Goal) -> Result<Self, &'static str> {
+            Ok(Sum {
+                players,
+                turn: 0,
+                sum: 0,
+                goal,
+                abandoned: false,
+            })
+        }
+        fn make_move(&mut self, player: &str, Add(n): Add) -> Result<(), &'static str> {
+            if self.current_player().as_deref() != Some(player) {
+                return Err("Not your turn");
+            }
+            self.sum += n;
+            self.turn += 1;
+            Ok(())
+        }
+        fn do_client_action(&mut self, action: NoAction) {
+            match action {}
+        }
+        fn to_view(&self, _player: &str) -> Self {
+            self.clone()
+        }
+        fn to_spectator_view(&self, _view: SpectatorView) -> Self {
+            self.clone()
+        }
+        fn has_ended(&self) -> bool {
+            self.abandoned || self.sum >= self.goal.0
+        }
+        fn abandon(&mut self) {
+            self.abandoned = true;
+        }
+        fn is_abandoned(&self) -> bool {
+            self.abandoned
+        }
+        fn current_player(&self) -> Option<String> {
+            (!self.has_ended()).then(|| self.players[self.turn % self.players.len()].clone())
+        }
+        fn score(&self) -> usize {
+            self.sum
+        }
+        fn move_help() -> &'static str {
+            "<number>"
+        }
+    }
+
+    fn server(limits: Limits) -> Server<Sum> {
+        Server::new(limits, None, None)
+    }
+
+    /// Open a socket of the browser client, which needs no hello, from the
+    /// given port of localhost.
+    fn connect(state: &mut ServerState<Sum>, port: u16) -> (ClientId, UnboundedReceiver<Message>) {
+        let clientid = ([127, 0, 0, 1], port).into();
+        let (sender, receiver) = unbounded();
+        state.connect(clientid, Sink { sender, text: true });
+        (clientid, receiver)
+    }
+
+    /// The text sent to the socket so far.
+    fn received(receiver: &mut UnboundedReceiver<Message>) -> String {
+        let mut text = String::new();
+        while let Ok(Some(message)) = receiver.try_next() {
+            if let Message::Text(message) = message {
+                text += &message;
+            }
+        }
+        text
+    }
+
+    fn login(
+        state: &mut ServerState<Sum>,
+        clientid: ClientId,
+        userid: &str,
+    ) -> Option<Response<Sum>> {
+        let login = Action::Login(userid.into(), None, Default::default());
+        state.handle_action(clientid, login, None)
+    }
+
+    fn new_room(state: &mut ServerState<Sum>, clientid: ClientId) -> RoomId {
+        let new_room = Action::NewRoom {
+            min_players: 2,
+            max_players: 2,
+            turn_timer: None,
+            password: None,
+            seating: Default::default(),
+            hide_deck: false,
+            settings: Goal(10),
+        };
+        state.handle_action(clientid, new_room, None);
+        state.client(clientid).roomid.unwrap()
+    }
+
+    #[test]
+    fn seats_are_kept_from_other_logins() {
+        let server = server(Limits::default());
+        let state = &mut server.state.lock().unwrap();
+        let (alice, _receiver) = connect(state, 1000);
+        let (other, _other_receiver) = connect(state, 1001);
+        assert!(matches!(
+            login(state, alice, "alice"),
+            Some(Response::RoomList(..))
+        ));
+        let roomid = new_room(state, alice);
+        let Some(Response::Error(err)) = login(state, other, "alice") else {
+            panic!("the login was accepted");
+        };
+        assert!(err.contains(&format!("plays in room {roomid}")), "{err}");
+        assert!(state.client(other).userid.is_none());
+        // Users without a seat can log in anywhere.
+        assert!(matches!(
+            login(state, other, "bob"),
+            Some(Response::RoomList(..))
+        ));
+    }
+
+    #[tokio::test]
+    async fn registered_users_need_their_password() {
+        let server = server(Limits::default());
+        let (alice, mut receiver) = connect(&mut server.state.lock().unwrap(), 1000);
+        let (other, mut other_receiver) = connect(&mut server.state.lock().unwrap(), 1001);
+        login(&mut server.state.lock().unwrap(), alice, "alice");
+        server
+            .handle_action(alice, Action::Register("secret".into()))
+            .await;
+        assert!(received(&mut receiver).contains("Registered"));
+
+        let login = |password: Option<&str>| {
+            Action::Login("alice".into(), password.map(Into::into), Default::default())
+        };
+        server.handle_action(other, login(None)).await;
+        assert!(received(&mut other_receiver).contains("This user has a password"));
+        server.handle_action(other, login(Some("wrong"))).await;
+        assert!(received(&mut other_receiver).contains("Wrong password"));
+        assert!(server.state.lock().unwrap().client(other).userid.is_none());
+        server.handle_action(other, login(Some("secret"))).await;
+        assert!(received(&mut other_receiver).contains("Logged in as alice"));
+    }
+
+    #[test]
+    fn waiting_rooms_expire() {
+        let server = server(Limits {
+            expire_pending_after: 0,
+            ..Default::default()
+        });
+        let state = &mut server.state.lock().unwrap();
+        let (alice, mut receiver) = connect(state, 1000);
+        login(state, alice, "alice");
+        let roomid = new_room(state, alice);
+        received(&mut receiver);
+        state.expire_rooms();
+        assert!(!state.rooms.contains_key(&roomid));
+        assert!(state.client(alice).roomid.is_none());
+        assert!(received(&mut receiver).contains(&format!("Room {roomid} expired")));
+    }
+
+    #[test]
+    fn audit_log_hides_private_actions() {
+        let server = server(Limits::default());
+        let state = &mut server.state.lock().unwrap();
+        let (alice, _receiver) = connect(state, 1000);
+        login(state, alice, "alice");
+        let roomid = new_room(state, alice);
+        for action in [
+            Action::Chat("secret".into()),
+            Action::SetNote {
+                card_idx: 1,
+                text: "secret".into(),
+            },
+            Action::Premove(Some(Add(7))),
+            Action::ProbeMove(Add(7)),
+            Action::Notify(Some("secret@example.com".into())),
+        ] {
+            state.handle_action(alice, action, None);
+        }
+        let Some(Response::AuditLog(_, entries)) =
+            state.handle_action(alice, Action::AuditLog(roomid), None)
+        else {
+            panic!("no audit log");
+        };
+        let log = entries.iter().map(ToString::to_string).join("\n");
+        for action in ["Chat", "SetNote(1)", "Premove", "ProbeMove", "Notify"] {
+            assert!(log.contains(action), "{log}");
+        }
+        assert!(!log.contains("secret"), "{log}");
+        assert!(!log.contains("Add("), "{log}");
+        assert!(!log.contains("127.0.0.1"), "{log}");
+    }
+
+    #[test]
+    fn flooding_is_refused_then_banned() {
+        let server = server(Limits {
+            max_actions_per_minute: 2,
+            ..Default::default()
+        });
+        let state = &mut server.state.lock().unwrap();
+        let (clientid, _receiver) = connect(state, 1000);
+        let (other, _other_receiver) = connect(state, 1001);
+        for _ in 0..2 {
+            assert!(state.rate_limit(clientid).is_none());
+        }
+        for _ in 0..2 {
+            let limit = state.handle_action(clientid, Action::Leaderboard, None);
+            assert!(matches!(
+                limit,
+                Some(Response::LimitExceeded(LimitExceeded::ActionsPerMinute(2)))
+            ));
+        }
+        assert!(state.banned_until(clientid).is_none());
+        let limit = state.handle_action(clientid, Action::Leaderboard, None);
+        assert!(matches!(
+            limit,
+            Some(Response::LimitExceeded(LimitExceeded::Banned(_)))
+        ));
+        // The ban holds for every socket from the address.
+        assert!(state.banned_until(other).is_some());
+    }
 }
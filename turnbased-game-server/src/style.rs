@@ -0,0 +1,93 @@
+//! Terminal styles from `owo_colors`, or without the `display` feature,
+//! stand-ins that print text as it is. Games style their output through this
+//! module, so that they build without terminal dependencies, e.g. for bots.
+
+#[cfg(feature = "display")]
+pub use owo_colors::{OwoColorize, Style};
+
+#[cfg(not(feature = "display"))]
+pub use plain::{OwoColorize, Style};
+
+#[cfg(not(feature = "display"))]
+mod plain {
+    macro_rules! plain_styles {
+        ($($name:ident),*) => {
+            /// A style that leaves text as it is.
+            #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+            pub struct Style;
+
+            impl Style {
+                pub fn new() -> Self {
+                    Style
+                }
+                $(pub fn $name(self) -> Self {
+                    self
+                })*
+                pub fn fmt_prefix(&self, _f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                    Ok(())
+                }
+                pub fn fmt_suffix(&self, _f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                    Ok(())
+                }
+            }
+
+            /// Styling methods that leave text as it is.
+            pub trait OwoColorize {
+                fn style(&self, _style: Style) -> &Self {
+                    self
+                }
+                $(fn $name(&self) -> &Self {
+                    self
+                })*
+            }
+
+            impl<T: ?Sized> OwoColorize for T {}
+        };
+    }
+
+    plain_styles!(
+        bold,
+        dimmed,
+        italic,
+        underline,
+        black,
+        red,
+        green,
+        yellow,
+        blue,
+        magenta,
+        purple,
+        white,
+        bright_black,
+        bright_red,
+        bright_green,
+        bright_yellow,
+        bright_blue,
+        bright_magenta,
+        bright_white,
+        on_black,
+        on_red,
+        on_white,
+        on_bright_green,
+        on_bright_yellow
+    );
+}
+
+/// `s` without the terminal escape codes that games color their texts with.
+pub fn strip_styles(s: &str) -> String {
+    let mut stripped = String::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Skip the escape code up to and including its final letter.
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            stripped.push(c);
+        }
+    }
+    stripped
+}
@@ -0,0 +1,340 @@
+//! Full-screen terminal interface of the client: the board, the activity
+//! feed and chat, a status bar and an input line with shortcuts, drawn with
+//! ANSI escape codes.
+//!
+//! Everything the client writes to stderr is captured and shown in the feed
+//! panel, so the line-based output of the client and games needs no changes.
+
+use std::fs::File;
+use std::io::{Read, Write};
+use std::os::fd::FromRawFd;
+use std::sync::{Arc, Mutex};
+
+use futures_channel::mpsc::UnboundedSender;
+use owo_colors::OwoColorize;
+use tokio::io::AsyncReadExt;
+
+/// Number of feed lines kept for scrolling back.
+const MAX_MESSAGES: usize = 1000;
+
+/// Narrowest feed panel next to the board; narrower terminals stack them.
+const MIN_FEED_WIDTH: usize = 30;
+
+const SHORTCUTS: &str = "Alt-p play | Alt-d discard | Alt-h hint | Alt-l log | PgUp/PgDn scroll";
+
+/// What is shown on the screen.
+#[derive(Default)]
+pub struct Screen {
+    /// The room or replay, rendered as by the line client.
+    board: String,
+    status: String,
+    /// Captured output, oldest first.
+    messages: Vec<String>,
+    prompt: String,
+    input: String,
+    /// Number of messages scrolled back from the newest one.
+    scroll: usize,
+}
+
+impl Screen {
+    /// Show the board, status line and prompt, and redraw.
+    pub fn show(&mut self, board: String, status: String, prompt: &str) {
+        self.board = board;
+        self.status = status;
+        self.prompt = prompt.to_string();
+        self.draw();
+    }
+
+    fn push_message(&mut self, message: String) {
+        self.messages.push(message);
+        if self.messages.len() > MAX_MESSAGES {
+            self.messages.remove(0);
+        }
+        // Keep the scrolled back messages in place.
+        if self.scroll > 0 {
+            self.scroll += 1;
+        }
+    }
+
+    /// Redraw the whole screen.
+    fn draw(&self) {
+        let (rows, cols) = terminal_size();
+        let body = rows.saturating_sub(2);
+        let board: Vec<&str> = self.board.lines().collect();
+        let board_width = board.iter().map(|line| visible_width(line)).max();
+        let board_width = board_width.unwrap_or(0).min(cols);
+
+        let mut out = String::from("\x1b[H\x1b[2J");
+        // Board left of the feed if both fit, else above it.
+        let (feed_top, feed_left, feed_rows) = if board_width + 3 + MIN_FEED_WIDTH <= cols {
+            for (row, line) in board.iter().take(body).enumerate() {
+                out += &format!("\x1b[{};1H{}", row + 1, clip(line, board_width));
+            }
+            for row in 0..body {
+                out += &format!("\x1b[{};{}H{}", row + 1, board_width + 2, "│".dimmed());
+            }
+            (1, board_width + 4, body)
+        } else {
+            let board_rows = board.len().min(body * 2 / 3);
+            for (row, line) in board.iter().take(board_rows).enumerate() {
+                out += &format!("\x1b[{};1H{}", row + 1, clip(line, cols));
+            }
+            let separator = "─".repeat(cols);
+            out += &format!("\x1b[{};1H{}", board_rows + 1, separator.dimmed());
+            (board_rows + 2, 1, body.saturating_sub(board_rows + 1))
+        };
+        let feed_width = (cols + 1).saturating_sub(feed_left);
+
+        if feed_rows > 0 {
+            let title = format!("feed & chat  ({SHORTCUTS})");
+            out += &format!(
+                "\x1b[{feed_top};{feed_left}H{}",
+                clip(&title, feed_width).bold()
+            );
+            let end = self.messages.len().saturating_sub(self.scroll);
+            let start = end.saturating_sub(feed_rows - 1);
+            for (row, message) in self.messages[start..end].iter().enumerate() {
+                out += &format!(
+                    "\x1b[{};{feed_left}H{}",
+                    feed_top + 1 + row,
+                    clip(message, feed_width)
+                );
+            }
+        }
+
+        let status = clip(&self.status, cols);
+        let padding = " ".repeat(cols.saturating_sub(status.1));
+        out += &format!(
+            "\x1b[{};1H{}",
+            rows.saturating_sub(1),
+            format!("{}{padding}", status.0).reversed()
+        );
+        let input = format!("{}{}", self.prompt.bold(), self.input);
+        out += &format!("\x1b[{rows};1H{}", clip(&input, cols));
+
+        let mut stdout = std::io::stdout().lock();
+        let _ = stdout.write_all(out.as_bytes());
+        let _ = stdout.flush();
+    }
+}
+
+/// Number of characters of `s` that take up space, i.e. not counting
+/// escape codes.
+fn visible_width(s: &str) -> usize {
+    clip(s, usize::MAX).1
+}
+
+/// The prefix of `s` that takes up at most `width` columns, keeping its
+/// escape codes, and the number of columns it takes up.
+fn clip(s: &str, width: usize) -> Clipped {
+    let mut clipped = String::new();
+    let mut columns = 0;
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\x1b' => {
+                clipped.push(c);
+                // Copy the escape code up to and including its final letter.
+                for c in chars.by_ref() {
+                    clipped.push(c);
+                    if c.is_ascii_alphabetic() {
+                        break;
+                    }
+                }
+            }
+            '\t' | '\r' | '\x07' => {}
+            _ if columns == width => {}
+            _ => {
+                clipped.push(c);
+                columns += 1;
+            }
+        }
+    }
+    Clipped(clipped, columns)
+}
+
+struct Clipped(String, usize);
+
+impl std::fmt::Display for Clipped {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Styles must not leak into the next panel.
+        write!(f, "{}\x1b[0m", self.0)
+    }
+}
+
+/// Rows and columns of the terminal.
+fn terminal_size() -> (usize, usize) {
+    // SAFETY: `winsize` is plain data, filled in by the ioctl.
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    let ok = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } == 0;
+    if ok && size.ws_row > 0 && size.ws_col > 0 {
+        (size.ws_row as usize, size.ws_col as usize)
+    } else {
+        (24, 80)
+    }
+}
+
+/// The terminal in full-screen mode. Dropping it restores the terminal and
+/// stderr.
+pub struct Terminal {
+    pub screen: Arc<Mutex<Screen>>,
+    termios: libc::termios,
+    /// Duplicate of the original stderr.
+    stderr: libc::c_int,
+}
+
+impl Terminal {
+    /// Switch to the alternate screen in raw mode and capture stderr, or
+    /// return `None` if stdin or stdout is not a terminal.
+    pub fn enter() -> Option<Self> {
+        // SAFETY: plain libc calls on the standard file descriptors, with
+        // results checked before the descriptors are used.
+        unsafe {
+            if libc::isatty(libc::STDIN_FILENO) != 1 || libc::isatty(libc::STDOUT_FILENO) != 1 {
+                return None;
+            }
+            let mut termios: libc::termios = std::mem::zeroed();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut termios) != 0 {
+                return None;
+            }
+            let mut pipe = [0; 2];
+            if libc::pipe(pipe.as_mut_ptr()) != 0 {
+                return None;
+            }
+            let mut raw = termios;
+            libc::cfmakeraw(&mut raw);
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw);
+            let stderr = libc::dup(libc::STDERR_FILENO);
+            libc::dup2(pipe[1], libc::STDERR_FILENO);
+            libc::close(pipe[1]);
+
+            let screen = Arc::new(Mutex::new(Screen::default()));
+            let output = File::from_raw_fd(pipe[0]);
+            let captured = screen.clone();
+            std::thread::spawn(move || capture(output, captured));
+            print!("\x1b[?1049h");
+            screen.lock().unwrap().draw();
+            Some(Self {
+                screen,
+                termios,
+                stderr,
+            })
+        }
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        print!("\x1b[?1049l");
+        let _ = std::io::stdout().flush();
+        // SAFETY: restores the state saved in `enter`. Replacing stderr
+        // closes the pipe, which ends the capturing thread.
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.termios);
+            libc::dup2(self.stderr, libc::STDERR_FILENO);
+            libc::close(self.stderr);
+        }
+    }
+}
+
+/// Show every line written to the captured stderr in the feed panel.
+fn capture(mut output: File, screen: Arc<Mutex<Screen>>) {
+    let mut buf = [0; 4096];
+    let mut partial = vec![];
+    loop {
+        let n = match output.read(&mut buf) {
+            Ok(0) | Err(_) => return,
+            Ok(n) => n,
+        };
+        partial.extend_from_slice(&buf[..n]);
+        if buf[..n].contains(&7) {
+            // Ring the bell of the real terminal.
+            print!("\x07");
+        }
+        let mut screen = screen.lock().unwrap();
+        while let Some(newline) = partial.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = partial.drain(..=newline).collect();
+            let line = String::from_utf8_lossy(&line[..newline]).into_owned();
+            screen.push_message(line);
+        }
+        screen.draw();
+    }
+}
+
+/// Edit the input line with the keys typed, and send each entered line.
+/// Returns when the user quits with Ctrl-C, or Ctrl-D on an empty line.
+pub async fn read_keys(screen: Arc<Mutex<Screen>>, lines: UnboundedSender<String>) {
+    let mut stdin = tokio::io::stdin();
+    let mut buf = [0; 64];
+    // Bytes of a character that is not complete yet.
+    let mut pending = vec![];
+    loop {
+        let n = match stdin.read(&mut buf).await {
+            Ok(0) | Err(_) => return,
+            Ok(n) => n,
+        };
+        let mut screen = screen.lock().unwrap();
+        let mut bytes = buf[..n].iter().copied();
+        while let Some(byte) = bytes.next() {
+            match byte {
+                // Ctrl-C, or Ctrl-D on an empty line.
+                0x03 => return,
+                0x04 if screen.input.is_empty() => return,
+                b'\r' | b'\n' => {
+                    let line = std::mem::take(&mut screen.input);
+                    let echo = format!("{}{line}", screen.prompt.bold());
+                    screen.push_message(echo);
+                    screen.scroll = 0;
+                    if lines.unbounded_send(line).is_err() {
+                        return;
+                    }
+                }
+                // Backspace.
+                0x7f | 0x08 => {
+                    screen.input.pop();
+                }
+                // Ctrl-U.
+                0x15 => screen.input.clear(),
+                0x1b => {
+                    let page = terminal_size().0 / 2;
+                    match bytes.next() {
+                        // Escape alone.
+                        None => screen.input.clear(),
+                        Some(b'[') => match bytes.next() {
+                            Some(b'A') => screen.scroll += 1,
+                            Some(b'B') => screen.scroll = screen.scroll.saturating_sub(1),
+                            Some(b'5') => screen.scroll += page,
+                            Some(b'6') => screen.scroll = screen.scroll.saturating_sub(page),
+                            _ => {}
+                        },
+                        // Alt and a letter.
+                        Some(b'p') => screen.input = "play ".into(),
+                        Some(b'd') => screen.input = "discard ".into(),
+                        Some(b'h') => screen.input = "hint ".into(),
+                        Some(b'l') => screen.input = "log".into(),
+                        Some(_) => {}
+                    }
+                    // Skip the rest of the escape code, e.g. `~`.
+                    if bytes.clone().next() == Some(b'~') {
+                        bytes.next();
+                    }
+                    let max_scroll = screen.messages.len().saturating_sub(1);
+                    screen.scroll = screen.scroll.min(max_scroll);
+                }
+                0x00..=0x1f => {}
+                _ => {
+                    pending.push(byte);
+                    match std::str::from_utf8(&pending) {
+                        Ok(s) => {
+                            screen.input.push_str(s);
+                            pending.clear();
+                        }
+                        Err(err) if err.error_len().is_some() => pending.clear(),
+                        Err(_) => {}
+                    }
+                }
+            }
+        }
+        screen.draw();
+    }
+}
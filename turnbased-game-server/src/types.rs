@@ -1,13 +1,23 @@
+use crate::style::OwoColorize;
 use crate::GameT;
 use itertools::Itertools;
-use owo_colors::OwoColorize;
 use serde::{Deserialize, Serialize};
-use std::{fmt::Display, str::FromStr};
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Version of the messages between client and server, to be bumped whenever
+/// `Action` or `Response` change shape. `Action::Hello` and `Response::Error`
+/// keep their shape, so peers of different versions can still tell so.
+pub const PROTOCOL_VERSION: u32 = 17;
 
 // TODO: Separate Player id and name. For now the name is the id.
 pub type UserId = String;
 
-#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct RoomId(pub usize);
 
 impl Display for RoomId {
@@ -38,6 +48,16 @@ pub enum RoomState<Game: GameT> {
     Ended(Option<Game>),
 }
 
+impl<Game: GameT> RoomState<Game> {
+    pub fn status(&self) -> RoomStatus {
+        match self {
+            RoomState::WaitingForPlayers { .. } => RoomStatus::Pending,
+            RoomState::Started(_) => RoomStatus::Started,
+            RoomState::Ended(_) => RoomStatus::Ended,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(bound = "")]
 pub struct Room<Game: GameT> {
@@ -47,6 +67,49 @@ pub struct Room<Game: GameT> {
     /// be different from in-game order.
     pub players: Vec<UserId>,
     pub state: RoomState<Game>,
+    /// Sequence number of the last broadcast for this room. Increases by one
+    /// with every room update and feed entry sent to its watchers.
+    pub seq: u64,
+    /// How fast moves are made in this room.
+    #[serde(default)]
+    pub pace: Pace,
+    /// Time banks of the room, if it is meant for correspondence play.
+    #[serde(default)]
+    pub correspondence: Option<Correspondence>,
+    /// What watchers who do not play in the room see of the game.
+    #[serde(default)]
+    pub spectator_view: SpectatorView,
+    /// Time limit of each turn, if the room has one.
+    #[serde(default)]
+    pub turn_timer: Option<TurnTimer>,
+    /// Unix timestamp at which the turn of the current player runs out.
+    #[serde(default)]
+    pub turn_deadline: Option<u64>,
+    /// Players who agreed to take back the last move, in the order they did.
+    #[serde(default)]
+    pub undo_votes: Vec<UserId>,
+    /// Whether only its members and users with its password may watch or
+    /// join the room.
+    #[serde(default)]
+    pub private: bool,
+    /// How players are seated when the game starts.
+    #[serde(default)]
+    pub seating: Seating,
+    /// Whether the cards left in the deck stay hidden after the game ends.
+    #[serde(default)]
+    pub hide_deck: bool,
+    /// Move up to which the players review the finished game together, if
+    /// they do.
+    #[serde(default)]
+    pub review: Option<usize>,
+    /// Connection status of each player other than bots, filled in by the
+    /// server for each view.
+    #[serde(default)]
+    pub seats: BTreeMap<UserId, SeatStatus>,
+    /// Notes of the viewing player on the cards in their hand, by position,
+    /// filled in by the server for each view.
+    #[serde(default)]
+    pub notes: BTreeMap<usize, String>,
 }
 
 impl<Game: GameT> Display for Room<Game> {
@@ -57,13 +120,16 @@ impl<Game: GameT> Display for Room<Game> {
             settings,
             players,
             state,
+            pace,
+            correspondence,
+            spectator_view,
+            turn_timer,
+            private,
+            seating,
+            ..
         } = &self;
 
-        let status = match state {
-            WaitingForPlayers { .. } => "pending",
-            Started(_) => "started",
-            Ended(_) => "ended",
-        };
+        let status = state.status().to_string();
         match state {
             RoomState::WaitingForPlayers {
                 min_players,
@@ -74,50 +140,867 @@ impl<Game: GameT> Display for Room<Game> {
                     "{} status: {status:7} settings: {settings:<10} players: {min_players}-{max_players}  {}",
                     format!("Room {roomid}:").bold(),
                     players.join(", ")
-                )
+                )?;
+                if let Some(correspondence) = correspondence {
+                    write!(f, "  correspondence: {correspondence}")?;
+                }
+                if *spectator_view != SpectatorView::default() {
+                    write!(f, "  spectators: {spectator_view}")?;
+                }
+                if *seating != Seating::default() {
+                    write!(f, "  seats: {seating}")?;
+                }
+                if let Some(turn_timer) = turn_timer {
+                    write!(f, "  timer: {turn_timer}")?;
+                }
+                if *private {
+                    write!(f, "  private")?;
+                }
+                if let Some(offline) = self.offline_players() {
+                    write!(f, "  {offline}")?;
+                }
+                Ok(())
             }
             Started(None) | Ended(None) => {
                 write!(
                     f,
                     "{roomid}: status: {status:7} settings: {settings:<10}     players: {}",
                     players.join(", ")
+                )?;
+                if pace.moves > 0 {
+                    write!(f, "  pace: {pace}")?;
+                }
+                if let Some(correspondence) = correspondence {
+                    write!(f, "  correspondence: {correspondence}")?;
+                }
+                if *private {
+                    write!(f, "  private")?;
+                }
+                Ok(())
+            }
+            Started(Some(g)) => {
+                write!(f, "{}", g)?;
+                if let Some(notes) = self.note_list() {
+                    write!(f, "\n{notes}")?;
+                }
+                if let Some(offline) = self.offline_players() {
+                    write!(f, "\n{offline}")?;
+                }
+                Ok(())
+            }
+            Ended(Some(g)) => {
+                let (turn, game) = match self.review.map(|turn| (turn, g.replay(turn))) {
+                    Some((turn, Ok(game))) => (Some(turn), game),
+                    _ => (None, g.clone()),
+                };
+                write!(f, "{}", game)?;
+                if !self.hide_deck {
+                    if let Some(hidden) = game.hidden_cards() {
+                        writeln!(f, "{hidden}")?;
+                    }
+                }
+                if let Some(turn) = turn {
+                    write!(
+                        f,
+                        "{}",
+                        format!("[review | move {turn}/{}]", g.num_moves()).bold()
+                    )?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl<Game: GameT> Room<Game> {
+    /// The notes of the viewer on their cards, if any.
+    fn note_list(&self) -> Option<String> {
+        let notes = self
+            .notes
+            .iter()
+            .map(|(card_idx, text)| format!("{card_idx}: {text}"))
+            .join("  ");
+        (!notes.is_empty()).then(|| format!("{} {notes}", "notes:".bold()))
+    }
+
+    /// The players who do not have the room open, if any, and for how long.
+    fn offline_players(&self) -> Option<String> {
+        let now = now();
+        let offline = self
+            .seats
+            .iter()
+            .filter(|(_, seat)| !seat.online)
+            .map(|(userid, seat)| match seat.last_seen {
+                Some(last_seen) => format!(
+                    "{userid} for {}",
+                    format_duration(now.saturating_sub(last_seen))
+                ),
+                None => userid.clone(),
+            })
+            .join(", ");
+        (!offline.is_empty()).then(|| format!("{} {offline}", "offline:".red()))
+    }
+
+    /// Pass the accessibility preferences of the viewer to the game.
+    pub fn with_accessibility(mut self, accessibility: Accessibility) -> Self {
+        if let RoomState::Started(Some(game)) | RoomState::Ended(Some(game)) = &mut self.state {
+            game.set_accessibility(accessibility);
+        }
+        self
+    }
+
+    /// The game of the room, once it started.
+    pub fn game(&self) -> Option<&Game> {
+        match &self.state {
+            RoomState::Started(game) | RoomState::Ended(game) => game.as_ref(),
+            RoomState::WaitingForPlayers { .. } => None,
+        }
+    }
+
+    pub fn game_mut(&mut self) -> Option<&mut Game> {
+        match &mut self.state {
+            RoomState::Started(game) | RoomState::Ended(game) => game.as_mut(),
+            RoomState::WaitingForPlayers { .. } => None,
+        }
+    }
+}
+
+/// A finished game, as kept in the archive of the server and listed in the
+/// history of a user.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(bound = "")]
+pub struct HistoryEntry<Game: GameT> {
+    pub roomid: RoomId,
+    pub settings: Game::Settings,
+    pub players: Vec<UserId>,
+    pub score: usize,
+    /// Number of moves made in the game.
+    #[serde(default)]
+    pub turns: usize,
+    /// The game was stopped before it ended, so the score does not count.
+    pub abandoned: bool,
+    /// Seconds since the unix epoch at which the game ended.
+    pub ended_at: u64,
+}
+
+impl<Game: GameT> Display for HistoryEntry<Game> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} ended: {} settings: {:<10} score: {}  turns: {:3}  players: {}  ({})",
+            format!("Room {}:", self.roomid).bold(),
+            format_timestamp(self.ended_at),
+            self.settings,
+            if self.abandoned {
+                "abandoned".red().to_string()
+            } else {
+                format!("{:2}", self.score.bold())
+            },
+            self.turns,
+            self.players.join(", "),
+            format!("watch {}", self.roomid).italic(),
+        )
+    }
+}
+
+/// Scores of the finished games with the same settings and number of
+/// players. Abandoned games do not count.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(bound = "")]
+pub struct LeaderboardEntry<Game: GameT> {
+    pub settings: Game::Settings,
+    pub players: usize,
+    pub games: usize,
+    pub average_score: f64,
+    pub best_score: usize,
+    /// Average number of moves per game.
+    pub average_turns: f64,
+}
+
+impl<Game: GameT> Display for LeaderboardEntry<Game> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "settings: {:<10} players: {}  games: {:3}  average: {}  best: {:2}  turns: {:5.1}",
+            self.settings,
+            self.players,
+            self.games,
+            format!("{:5.2}", self.average_score).bold(),
+            self.best_score,
+            self.average_turns,
+        )
+    }
+}
+
+/// An action received by the server, as recorded in the audit log of a room.
+/// Players can read it, so the address of the client is only in the server
+/// logs.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AuditEntry {
+    /// Unix timestamp at which the action was received.
+    pub time: u64,
+    pub userid: Option<UserId>,
+    pub action: String,
+    /// The reason the action was rejected, if it was.
+    pub error: Option<String>,
+}
+
+impl Display for AuditEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {}: {}",
+            format_timestamp(self.time),
+            self.userid.as_deref().unwrap_or("not logged in"),
+            self.action
+        )?;
+        match &self.error {
+            Some(err) => write!(f, " -> {}", err.red()),
+            None => write!(f, " -> {}", "ok".green()),
+        }
+    }
+}
+
+/// A server limit that caused an action or connection to be refused.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub enum LimitExceeded {
+    /// Maximum number of rooms that have not ended.
+    Rooms(usize),
+    /// Maximum number of rooms that have not ended created by one user.
+    RoomsPerUser(usize),
+    /// Maximum number of open sockets from one IP address.
+    ConnectionsPerIp(usize),
+    /// Maximum number of watchers of a room that are not players in it.
+    SpectatorsPerRoom(usize),
+    /// Maximum number of actions one socket may send per minute.
+    ActionsPerMinute(usize),
+    /// The address of the client flooded the server, and is refused until
+    /// the given unix timestamp.
+    Banned(u64),
+}
+
+impl Display for LimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LimitExceeded::Rooms(n) => write!(f, "The server is full ({n} open rooms)"),
+            LimitExceeded::RoomsPerUser(n) => {
+                write!(f, "You already created {n} open rooms")
+            }
+            LimitExceeded::ConnectionsPerIp(n) => {
+                write!(f, "Too many connections from your address (max {n})")
+            }
+            LimitExceeded::SpectatorsPerRoom(n) => {
+                write!(f, "The room already has {n} spectators")
+            }
+            LimitExceeded::ActionsPerMinute(n) => {
+                write!(f, "Too many actions; slow down (max {n} per minute)")
+            }
+            LimitExceeded::Banned(until) => {
+                write!(
+                    f,
+                    "Your address sent too many actions and is banned until {}",
+                    format_timestamp(*until)
                 )
             }
-            Started(Some(g)) | Ended(Some(g)) => {
-                write!(f, "{}", g)
+        }
+    }
+}
+
+/// Accessibility preferences of a user, passed along with their views of
+/// the game so every renderer can honor them.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Accessibility {
+    /// Mark colors with symbols, so they can be told apart without color.
+    pub symbols: bool,
+    /// Do not ring the bell or flash alerts.
+    pub no_flash: bool,
+    /// Leave more space between the parts of the board.
+    pub wide: bool,
+}
+
+impl Accessibility {
+    fn set(&mut self, flag: &str) -> Result<(), &'static str> {
+        match flag {
+            "symbols" => self.symbols = true,
+            "no-flash" => self.no_flash = true,
+            "wide" => self.wide = true,
+            _ => return Err("accessibility flags are symbols, no-flash and wide"),
+        }
+        Ok(())
+    }
+}
+
+impl Display for Accessibility {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let flags = [
+            (self.symbols, "symbols"),
+            (self.no_flash, "no-flash"),
+            (self.wide, "wide"),
+        ];
+        let flags: Vec<_> = flags
+            .into_iter()
+            .filter(|(on, _)| *on)
+            .map(|(_, flag)| flag)
+            .collect();
+        write!(f, "{}", flags.join(" "))
+    }
+}
+
+/// What spectators of a game in progress see. The deck is always hidden.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SpectatorView {
+    /// All hands are visible, with what their owners know about them.
+    #[default]
+    Hands,
+    /// Hands are hidden; only what their owners know about them is visible.
+    Hidden,
+}
+
+impl FromStr for SpectatorView {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hands" => Ok(SpectatorView::Hands),
+            "hidden" => Ok(SpectatorView::Hidden),
+            _ => Err("spectator view must be hands or hidden"),
+        }
+    }
+}
+
+impl Display for SpectatorView {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SpectatorView::Hands => write!(f, "hands"),
+            SpectatorView::Hidden => write!(f, "hidden"),
+        }
+    }
+}
+
+/// How the players of a room are seated when its game starts. With fixed
+/// seating the first player starts.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Seating {
+    /// The game shuffles the players and picks who starts.
+    #[default]
+    Random,
+    /// Players sit in the order they joined.
+    Join,
+    /// The host orders the players, who sit in the order they joined until
+    /// then.
+    Host,
+}
+
+impl FromStr for Seating {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "random" => Ok(Seating::Random),
+            "join" => Ok(Seating::Join),
+            "host" => Ok(Seating::Host),
+            _ => Err("seating must be random, join or host"),
+        }
+    }
+}
+
+impl Display for Seating {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Seating::Random => write!(f, "random"),
+            Seating::Join => write!(f, "join order"),
+            Seating::Host => write!(f, "set by host"),
+        }
+    }
+}
+
+/// A step of the review of a finished game.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReviewStep {
+    /// Go to the position after this many moves.
+    Move(usize),
+    Next,
+    Previous,
+    /// Stop reviewing and show the final position.
+    End,
+}
+
+impl FromStr for ReviewStep {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "next" | "n" => Ok(ReviewStep::Next),
+            "prev" | "p" => Ok(ReviewStep::Previous),
+            "end" => Ok(ReviewStep::End),
+            turn => turn
+                .parse()
+                .map(ReviewStep::Move)
+                .map_err(|_| "review <move>|next|prev|end"),
+        }
+    }
+}
+
+/// Whether a room is waiting for players, playing or finished.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoomStatus {
+    Pending,
+    Started,
+    Ended,
+}
+
+impl FromStr for RoomStatus {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(RoomStatus::Pending),
+            "started" => Ok(RoomStatus::Started),
+            "ended" => Ok(RoomStatus::Ended),
+            _ => Err("room status must be pending, started or ended"),
+        }
+    }
+}
+
+impl Display for RoomStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RoomStatus::Pending => write!(f, "pending"),
+            RoomStatus::Started => write!(f, "started"),
+            RoomStatus::Ended => write!(f, "ended"),
+        }
+    }
+}
+
+/// Which page of the room list a `Response::RoomList` holds.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RoomListPage {
+    /// Index of the page, starting at 0.
+    pub page: usize,
+    pub pages: usize,
+    /// Number of rooms that match the filters, on all pages.
+    pub rooms: usize,
+}
+
+/// Who may see that a user is online and which room they are in.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PresenceSharing {
+    /// Users who played with this user see whether they are online and in
+    /// which room.
+    #[default]
+    Room,
+    /// Users who played with this user only see whether they are online.
+    Online,
+    /// Nobody sees this user in their lobby.
+    Off,
+}
+
+impl FromStr for PresenceSharing {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "room" => Ok(PresenceSharing::Room),
+            "online" => Ok(PresenceSharing::Online),
+            "off" => Ok(PresenceSharing::Off),
+            _ => Err("presence must be room, online or off"),
+        }
+    }
+}
+
+impl Display for PresenceSharing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PresenceSharing::Room => {
+                write!(f, "Teammates see whether you are online and your room")
+            }
+            PresenceSharing::Online => write!(f, "Teammates see whether you are online"),
+            PresenceSharing::Off => write!(f, "Teammates do not see whether you are online"),
+        }
+    }
+}
+
+/// Whether a user the viewer played with is online, as far as they share it.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Presence {
+    pub userid: UserId,
+    pub online: bool,
+    /// The room the user is watching, if they share it.
+    pub roomid: Option<RoomId>,
+}
+
+impl Display for Presence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.online, self.roomid) {
+            (true, Some(roomid)) => {
+                write!(f, "{}: {} in room {roomid}", self.userid, "online".green())
             }
+            (true, None) => write!(f, "{}: {}", self.userid, "online".green()),
+            (false, _) => write!(f, "{}: offline", self.userid),
         }
     }
 }
 
+/// Whether a player of a room has it open, so others know whether to wait
+/// for them.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+pub struct SeatStatus {
+    pub online: bool,
+    /// Unix timestamp at which the player was last heard from while they had
+    /// the room open, if ever.
+    pub last_seen: Option<u64>,
+}
+
+/// What happened to the premove of a player.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum PremoveStatus {
+    /// It is made once it is the player's turn.
+    Queued,
+    /// The player cancelled it.
+    Cancelled,
+    /// A move touched what it refers to, so it was cancelled.
+    Touched,
+    /// It was refused when it was the player's turn.
+    Refused(String),
+}
+
+impl Display for PremoveStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PremoveStatus::Queued => write!(f, "Your premove is made once it is your turn"),
+            PremoveStatus::Cancelled => write!(f, "Your premove was cancelled"),
+            PremoveStatus::Touched => write!(
+                f,
+                "{}",
+                "Your premove was cancelled because the last move touched its card".bold()
+            ),
+            PremoveStatus::Refused(err) => {
+                write!(f, "Your premove was refused: {}", err.bold())
+            }
+        }
+    }
+}
+
+/// Refusal of a move in a room whose game has already ended.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct GameFinished {
+    pub roomid: RoomId,
+    pub score: usize,
+    pub abandoned: bool,
+}
+
+impl Display for GameFinished {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.abandoned {
+            write!(f, "The game was abandoned")?;
+        } else {
+            write!(f, "The game has finished with score {}", self.score)?;
+        }
+        write!(f, "; replay it with `watch {}`", self.roomid)
+    }
+}
+
+/// Seconds since the unix epoch.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// Format a unix timestamp as `YYYY-MM-DD HH:MM` in UTC.
+pub fn format_timestamp(timestamp: u64) -> String {
+    let (days, secs) = (timestamp / 86400, timestamp % 86400);
+    // Civil-from-days, see http://howardhinnant.github.io/date_algorithms.html.
+    let z = days + 719468;
+    let era = z / 146097;
+    let doe = z % 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}",
+        secs / 3600,
+        secs / 60 % 60
+    )
+}
+
+/// When to remind a player that it is their turn, for rooms where players
+/// do not wait for each other.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReminderPolicy {
+    /// Seconds into a turn after which, and every time again, the player is
+    /// notified.
+    pub remind_after: u64,
+    /// Also post the reminder in the activity feed.
+    pub chat: bool,
+    /// Seconds into a turn after which the host may skip or substitute the player.
+    pub offer_after: Option<u64>,
+}
+
+impl Display for ReminderPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "every {}", format_duration(self.remind_after))?;
+        if self.chat {
+            write!(f, " in chat")?;
+        }
+        if let Some(offer_after) = self.offer_after {
+            write!(
+                f,
+                ", skip or substitute after {}",
+                format_duration(offer_after)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Move cadence of a room, so players can tell real-time games from games
+/// where moves take a day.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Pace {
+    /// Number of moves made so far.
+    pub moves: usize,
+    /// Median number of seconds between consecutive moves.
+    pub median_secs: u64,
+}
+
+impl Pace {
+    /// Moves at most this far apart are considered real-time play.
+    pub const LIVE_SECS: u64 = 5 * 60;
+
+    pub fn new(move_secs: &[u64]) -> Self {
+        let mut sorted = move_secs.to_vec();
+        sorted.sort_unstable();
+        Self {
+            moves: sorted.len(),
+            median_secs: sorted.get(sorted.len() / 2).copied().unwrap_or(0),
+        }
+    }
+
+    pub fn is_live(&self) -> bool {
+        self.median_secs <= Self::LIVE_SECS
+    }
+}
+
+impl Display for Pace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = if self.is_live() {
+            "live".green().to_string()
+        } else {
+            "async".yellow().to_string()
+        };
+        write!(f, "{kind} (~{}/move)", format_duration(self.median_secs))
+    }
+}
+
+/// What the server does for a player whose turn timer runs out.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimeoutMove {
+    /// The player loses their turn, at a cost decided by the game.
+    #[default]
+    Skip,
+    /// The player makes the default discard of the game.
+    Discard,
+}
+
+/// Time limit of every turn of a room, so that public games cannot be
+/// stalled forever.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TurnTimer {
+    /// Seconds each turn may take.
+    pub secs: u64,
+    pub on_timeout: TimeoutMove,
+}
+
+impl Display for TurnTimer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let on_timeout = match self.on_timeout {
+            TimeoutMove::Skip => "skip",
+            TimeoutMove::Discard => "discard",
+        };
+        write!(f, "{}/turn, then {on_timeout}", format_duration(self.secs))
+    }
+}
+
+/// Time limits of a room for correspondence play, where a move may take days.
+/// Players are not abandoned for inactivity; instead, a player whose move
+/// takes longer than `per_move` spends their time bank, and the turn of a
+/// player with an empty bank is skipped.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Correspondence {
+    /// Seconds each move may take without spending the time bank.
+    pub per_move: u64,
+    /// Seconds each player may spend beyond `per_move` over the whole game.
+    pub bank: u64,
+}
+
+impl Display for Correspondence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}/move + {} bank",
+            format_duration(self.per_move),
+            format_duration(self.bank)
+        )
+    }
+}
+
+/// Parse a number of hours, which may be fractional, into seconds.
+fn parse_hours(s: Option<&str>) -> Result<u64, &'static str> {
+    let hours: f64 = s
+        .ok_or("missing number of hours")?
+        .parse()
+        .map_err(|_| "Could not parse number of hours")?;
+    if !hours.is_finite() || hours * 3600.0 < 1.0 {
+        return Err("Number of hours must be at least a second");
+    }
+    Ok((hours * 3600.0) as u64)
+}
+
+/// Format a number of seconds as hours and minutes.
+pub fn format_duration(secs: u64) -> String {
+    match (secs / 3600, secs / 60 % 60) {
+        (0, 0) => format!("{secs}s"),
+        (0, minutes) => format!("{minutes}m"),
+        (hours, 0) => format!("{hours}h"),
+        (hours, minutes) => format!("{hours}h{minutes:02}m"),
+    }
+}
+
 /// An action that can be sent over an incoming websocket.
 #[derive(Serialize, Deserialize, Debug)]
 pub enum Action<Game: GameT> {
-    /// Which user is using the socket.
-    Login(UserId),
+    /// First message on every socket, with the protocol version of the client.
+    Hello { protocol_version: u32 },
+    /// Which user is using the socket, with their password if they
+    /// registered, and their accessibility preferences.
+    Login(UserId, Option<String>, Accessibility),
+    /// Protect the name of the logged-in user with a password, which later
+    /// logins need. Other sockets and sessions of the user are logged out.
+    Register(String),
+    /// Log in again with the session token handed out at an earlier login,
+    /// e.g. after a reconnect, and watch the room the session was watching.
+    Resume(String),
     /// User stopped used the socket.
     Logout,
 
-    /// View a room and subscribe to updates.
-    WatchRoom(RoomId),
+    /// View a room and subscribe to updates, with the password of the room
+    /// if it is private.
+    WatchRoom(RoomId, Option<String>),
     /// Stop viewing a room. Tells the server to stop sending updates for the
     /// viewed room.
     LeaveRoom,
+    /// Resend the feed and view of the current room, e.g. after missing
+    /// updates.
+    Resync,
 
     /// Create a new room.
     NewRoom {
         min_players: usize,
         max_players: usize,
+        /// Time limit of each turn, if any.
+        turn_timer: Option<TurnTimer>,
+        /// Password that makes the room private, if any.
+        password: Option<String>,
+        /// How players are seated when the game starts.
+        #[serde(default)]
+        seating: Seating,
+        /// Keep the cards left in the deck hidden after the game ends.
+        #[serde(default)]
+        hide_deck: bool,
         settings: Game::Settings,
     },
-    /// Join the given (or current) room if it is waiting for players.
-    JoinRoom(Option<RoomId>),
+    /// Join the given (or current) room if it is waiting for players, with
+    /// the password of the room if it is private.
+    JoinRoom(Option<RoomId>, Option<String>),
 
+    /// Change the settings of the current room while it is waiting for
+    /// players. Host only.
+    UpdateSettings(Game::Settings),
+    /// Seat the players of the current room in this order, if the host sets
+    /// the seating. Host only.
+    SeatOrder(Vec<UserId>),
     /// Start the game in the current room.
     StartGame,
+    /// Step through the finished game of the current room, together with
+    /// everyone who watches it. Players only.
+    Review(ReviewStep),
+    /// Add a computer player to the current room while it is waiting for
+    /// players. Host only.
+    AddBot,
+    /// Give up the game in the current room, which ends it as abandoned.
+    Forfeit,
+    /// Set or disable turn reminders for the current room. Host only.
+    Reminders(Option<ReminderPolicy>),
+    /// Make the current room a correspondence room with the given time
+    /// banks, or a regular room. Host only.
+    Correspondence(Option<Correspondence>),
+    /// Let the server move for the current player once the reminder policy
+    /// allows it. Host only.
+    SkipTurn,
+    /// Give the seat of the current player to another user once the reminder
+    /// policy allows it. Host only.
+    Substitute(UserId),
+    /// Vote to give the seat of an absent player of the current room to a
+    /// bot. Once a majority of the other players voted, a bot takes over, or
+    /// the game is abandoned if the game does not support that.
+    VoteKick(UserId),
 
     /// Make a move in the current room.
     MakeMove(Game::Move),
+    /// Check whether a move in the current room would be accepted, without
+    /// making it.
+    ProbeMove(Game::Move),
+    /// Queue a move in the current room, made once it is the player's turn,
+    /// or with `None` cancel it.
+    Premove(Option<Game::Move>),
+    /// Note something on the card at this 1-based position in the player's
+    /// hand, or with an empty text remove the note. Only the player sees it,
+    /// and it stays with the card when the card moves.
+    SetNote { card_idx: usize, text: String },
+    /// Ask or agree to take back the last move of the current room, or with
+    /// `false` refuse to. The move is taken back once all players agree.
+    Undo(bool),
+
+    /// Send a chat message to the activity feed of the current room.
+    /// Messages of spectators are hidden from players while the game is in
+    /// progress, unless spectator chat is shared.
+    Chat(String),
+    /// Whether players of the current room see the chat of its spectators.
+    ShareSpectatorChat(bool),
+    /// What spectators of the current room see of the game, set before it
+    /// starts. Host only.
+    SpectatorView(SpectatorView),
+    /// What users who played with this user see of their presence.
+    SharePresence(PresenceSharing),
+    /// Pause the clocks of this user in correspondence rooms for the given
+    /// number of seconds, or end the vacation.
+    Vacation(Option<u64>),
+    /// Where to notify this user of their turn in correspondence rooms while
+    /// they are offline, e.g. an email address or webhook url.
+    Notify(Option<String>),
+    /// List a page of the rooms, optionally only (or no) correspondence
+    /// rooms, only rooms with the given status, or only rooms the user plays
+    /// in. Pages start at 0.
+    ListRooms {
+        correspondence: Option<bool>,
+        status: Option<RoomStatus>,
+        involving_user: bool,
+        page: usize,
+    },
+
+    /// List the finished games of the given (or current) user.
+    History(Option<UserId>),
+
+    /// Show the average scores of finished games per settings and number of
+    /// players.
+    Leaderboard,
+
+    /// Show all actions received for the given room.
+    AuditLog(RoomId),
 }
 
 impl<Game: GameT> FromStr for Action<Game> {
@@ -127,10 +1010,30 @@ impl<Game: GameT> FromStr for Action<Game> {
         use Action::*;
         let mut tokens = s.split_ascii_whitespace();
         let mov = match tokens.next().ok_or("Empty string")? {
-            "login" => Login(tokens.next().ok_or("missing user id")?.into()),
+            "login" => {
+                let userid = tokens.next().ok_or("missing user id")?.into();
+                let password = match tokens.clone().next() {
+                    Some("password") => {
+                        tokens.next();
+                        Some(tokens.next().ok_or("missing password")?.into())
+                    }
+                    _ => None,
+                };
+                let mut accessibility = Accessibility::default();
+                for flag in tokens.by_ref() {
+                    accessibility.set(flag)?;
+                }
+                Login(userid, password, accessibility)
+            }
+            "register" => Register(tokens.next().ok_or("missing password")?.into()),
+            "resume" => Resume(tokens.next().ok_or("missing session token")?.into()),
             "logout" => Logout,
-            "watch" => WatchRoom(tokens.next().ok_or("missing room id")?.parse()?),
+            "watch" => WatchRoom(
+                tokens.next().ok_or("missing room id")?.parse()?,
+                tokens.next().map(|password| password.into()),
+            ),
             "leave" => LeaveRoom,
+            "resync" => Resync,
             "new" => NewRoom {
                 min_players: tokens
                     .next()
@@ -142,6 +1045,56 @@ impl<Game: GameT> FromStr for Action<Game> {
                     .ok_or("missing max players")?
                     .parse()
                     .map_err(|_| "failed to parse max_players")?,
+                turn_timer: match tokens.clone().next() {
+                    Some("timer") => {
+                        tokens.next();
+                        let secs = tokens
+                            .next()
+                            .ok_or("missing number of seconds")?
+                            .parse()
+                            .map_err(|_| "Could not parse number of seconds")?;
+                        if secs == 0 {
+                            return Err("Number of seconds must be positive");
+                        }
+                        let on_timeout = match tokens.clone().next() {
+                            Some("skip") => {
+                                tokens.next();
+                                TimeoutMove::Skip
+                            }
+                            Some("discard") => {
+                                tokens.next();
+                                TimeoutMove::Discard
+                            }
+                            _ => TimeoutMove::default(),
+                        };
+                        Some(TurnTimer { secs, on_timeout })
+                    }
+                    _ => None,
+                },
+                password: match tokens.clone().next() {
+                    Some("password") => {
+                        tokens.next();
+                        Some(tokens.next().ok_or("missing password")?.into())
+                    }
+                    _ => None,
+                },
+                seating: match tokens.clone().next() {
+                    Some("seats") => {
+                        tokens.next();
+                        tokens
+                            .next()
+                            .ok_or("missing random, join or host")?
+                            .parse()?
+                    }
+                    _ => Seating::default(),
+                },
+                hide_deck: match tokens.clone().next() {
+                    Some("hide-deck") => {
+                        tokens.next();
+                        true
+                    }
+                    _ => false,
+                },
                 settings: {
                     let s = Itertools::intersperse(tokens, " ")
                         .collect::<String>()
@@ -151,8 +1104,159 @@ impl<Game: GameT> FromStr for Action<Game> {
                     s
                 },
             },
-            "join" => JoinRoom(tokens.next().map(|id| id.parse()).transpose()?),
+            "join" => JoinRoom(
+                tokens.next().map(|id| id.parse()).transpose()?,
+                tokens.next().map(|password| password.into()),
+            ),
             "start" => StartGame,
+            "review" => Review(
+                tokens
+                    .next()
+                    .ok_or("review <move>|next|prev|end")?
+                    .parse()?,
+            ),
+            "forfeit" => Forfeit,
+            "reminders" => match tokens.clone().next() {
+                Some("off") => {
+                    tokens.next();
+                    Reminders(None)
+                }
+                hours => {
+                    tokens.next();
+                    let remind_after = parse_hours(hours)?;
+                    let chat = tokens.clone().next() == Some("chat");
+                    if chat {
+                        tokens.next();
+                    }
+                    let offer_after = match tokens.next() {
+                        Some("offer") => Some(parse_hours(tokens.next())?),
+                        Some(_) => return Err("Expected chat or offer <hours>"),
+                        None => None,
+                    };
+                    Reminders(Some(ReminderPolicy {
+                        remind_after,
+                        chat,
+                        offer_after,
+                    }))
+                }
+            },
+            "correspondence" => match tokens.next() {
+                Some("off") => Correspondence(None),
+                per_move => Correspondence(Some(crate::types::Correspondence {
+                    per_move: parse_hours(per_move)?,
+                    bank: parse_hours(tokens.next())?,
+                })),
+            },
+            "vacation" => match tokens.next() {
+                Some("off") => Vacation(None),
+                hours => Vacation(Some(parse_hours(hours)?)),
+            },
+            "notify" => match tokens.next().ok_or("missing address or off")? {
+                "off" => Notify(None),
+                address => Notify(Some(address.into())),
+            },
+            "list" => {
+                let (mut correspondence, mut status, mut involving_user, mut page) =
+                    (None, None, false, 0);
+                while let Some(token) = tokens.next() {
+                    match token {
+                        "correspondence" => correspondence = Some(true),
+                        "live" => correspondence = Some(false),
+                        "mine" => involving_user = true,
+                        "page" => {
+                            page = tokens
+                                .next()
+                                .ok_or("missing page number")?
+                                .parse::<usize>()
+                                .map_err(|_| "Could not parse page number")?
+                                .checked_sub(1)
+                                .ok_or("Pages start at 1")?;
+                        }
+                        status_token => {
+                            status = Some(status_token.parse().map_err(|_| {
+                                "Expected correspondence, live, pending, started, ended, mine or page <n>"
+                            })?)
+                        }
+                    }
+                }
+                ListRooms {
+                    correspondence,
+                    status,
+                    involving_user,
+                    page,
+                }
+            }
+            "skip" => SkipTurn,
+            "undo" => match tokens.next() {
+                None => Undo(true),
+                Some("no") => Undo(false),
+                Some(_) => return Err("Expected no"),
+            },
+            "bot" => AddBot,
+            "substitute" => Substitute(tokens.next().ok_or("missing user id")?.into()),
+            "kick" => VoteKick(tokens.next().ok_or("missing user id")?.into()),
+            "audit" => AuditLog(tokens.next().ok_or("missing room id")?.parse()?),
+            "history" => History(tokens.next().map(|user| user.into())),
+            "leaderboard" => Leaderboard,
+            "premove" => {
+                let mov = s.trim_start().trim_start_matches("premove").trim();
+                tokens = "".split_ascii_whitespace();
+                match mov {
+                    "off" => Premove(None),
+                    mov => Premove(Some(mov.parse()?)),
+                }
+            }
+            "seats" => {
+                let order: Vec<UserId> = tokens.map(|player| player.into()).collect();
+                if order.is_empty() {
+                    return Err("missing players");
+                }
+                tokens = "".split_ascii_whitespace();
+                SeatOrder(order)
+            }
+            "settings" => {
+                let settings = Itertools::intersperse(tokens, " ")
+                    .collect::<String>()
+                    .parse()
+                    .map_err(|_| "Could not parse settings")?;
+                tokens = "".split_ascii_whitespace();
+                UpdateSettings(settings)
+            }
+            "check" => {
+                let mov = s.trim_start().trim_start_matches("check").trim();
+                tokens = "".split_ascii_whitespace();
+                ProbeMove(mov.parse()?)
+            }
+            "note" => {
+                let card_idx = tokens
+                    .next()
+                    .ok_or("missing card index")?
+                    .parse()
+                    .map_err(|_| "Could not parse card index")?;
+                let text = Itertools::intersperse(tokens, " ").collect::<String>();
+                tokens = "".split_ascii_whitespace();
+                SetNote { card_idx, text }
+            }
+            "chat" => {
+                let message = Itertools::intersperse(tokens, " ").collect::<String>();
+                if message.is_empty() {
+                    return Err("missing message");
+                }
+                tokens = "".split_ascii_whitespace();
+                Chat(message)
+            }
+            "spectator-chat" => match tokens.next().ok_or("missing shared or private")? {
+                "shared" => ShareSpectatorChat(true),
+                "private" => ShareSpectatorChat(false),
+                _ => return Err("spectator chat must be shared or private"),
+            },
+            "spectators" => SpectatorView(tokens.next().ok_or("missing hands or hidden")?.parse()?),
+            "presence" => SharePresence(
+                tokens
+                    .next()
+                    .ok_or("missing room, online or off")?
+                    .parse()?,
+            ),
             _ => MakeMove(s.parse()?),
         };
         if !matches!(mov, MakeMove(_)) && tokens.next().is_some() {
@@ -168,9 +1272,46 @@ pub enum Response<Game: GameT> {
     NotLoggedIn,
     /// Username of currently logged in user.
     LoggedIn(UserId),
-    RoomList(Vec<Room<Game>>),
+    /// Token with which the session can be resumed on another socket.
+    Session(String),
+    /// A page of the rooms, and the presence of users who played with the
+    /// logged in user.
+    RoomList(Vec<Room<Game>>, Vec<Presence>, RoomListPage),
+    /// The presence sharing of the logged in user changed.
+    PresenceSharing(PresenceSharing),
+    /// The logged in user is on vacation until the given unix timestamp, or
+    /// not at all.
+    Vacation(Option<u64>),
+    /// Where the logged in user is notified of their turn while offline.
+    Notify(Option<String>),
+    /// The logged in user registered a password.
+    Registered,
     Room(Room<Game>),
+    /// New entries of the activity feed of a room. Broadcasts to watchers
+    /// carry the next sequence number of the room; entries a watcher may not
+    /// see are sent as an empty list to keep the numbers contiguous.
+    Feed {
+        roomid: RoomId,
+        seq: u64,
+        entries: Vec<String>,
+    },
+    /// Finished games of a user, most recent first.
+    History(UserId, Vec<HistoryEntry<Game>>),
+    Leaderboard(Vec<LeaderboardEntry<Game>>),
+    AuditLog(RoomId, Vec<AuditEntry>),
     Error(String),
+    /// The action was refused because a server limit was reached.
+    LimitExceeded(LimitExceeded),
+    /// A move was made after the game ended.
+    GameFinished(GameFinished),
+    /// Whether the probed move would be accepted, with the warnings about
+    /// it, or why it would be refused.
+    MoveProbe(Result<Vec<String>, String>),
+    /// The premove of the logged in user in a room changed.
+    Premove(RoomId, PremoveStatus),
+    /// The server is shutting down, after saving all rooms, and closes the
+    /// socket.
+    ServerRestarting,
 }
 
 impl<Game: GameT> Display for Response<Game> {
@@ -178,8 +1319,32 @@ impl<Game: GameT> Display for Response<Game> {
         match self {
             Response::NotLoggedIn => writeln!(f, "Please log in: {}", "login <username>".bold()),
             Response::LoggedIn(user) => writeln!(f, "Logged in as {user}"),
+            // The token is not for the eyes of the user.
+            Response::Session(_) => Ok(()),
             Response::Error(err) => writeln!(f, "Error: {}", err.bold()),
-            Response::RoomList(rooms) => {
+            Response::LimitExceeded(limit) => {
+                writeln!(f, "Error: {}", limit.to_string().bold())
+            }
+            Response::GameFinished(finished) => {
+                writeln!(f, "Error: {}", finished.to_string().bold())
+            }
+            Response::MoveProbe(Ok(warnings)) => {
+                writeln!(f, "The move is allowed")?;
+                for warning in warnings {
+                    writeln!(f, " Warning: {}", warning.bold())?;
+                }
+                Ok(())
+            }
+            Response::MoveProbe(Err(err)) => {
+                writeln!(f, "The move is not allowed: {}", err.bold())
+            }
+            Response::Premove(roomid, status) => writeln!(f, "Room {roomid}: {status}"),
+            Response::ServerRestarting => writeln!(
+                f,
+                "{}",
+                "The server is restarting; your games are saved".bold()
+            ),
+            Response::RoomList(rooms, teammates, page) => {
                 writeln!(f, "{}", "Lobby:".bold())?;
                 if rooms.is_empty() {
                     writeln!(f, " No active rooms")?;
@@ -188,9 +1353,76 @@ impl<Game: GameT> Display for Response<Game> {
                         writeln!(f, " {room}")?;
                     }
                 }
+                if page.pages > 1 {
+                    writeln!(
+                        f,
+                        " Page {}/{} of {} rooms; see more with {}",
+                        page.page + 1,
+                        page.pages,
+                        page.rooms,
+                        "list page <n>".bold()
+                    )?;
+                }
+                if !teammates.is_empty() {
+                    writeln!(f, "{}", "Teammates:".bold())?;
+                    for presence in teammates {
+                        writeln!(f, " {presence}")?;
+                    }
+                }
                 Ok(())
             }
+            Response::PresenceSharing(sharing) => writeln!(f, "{sharing}"),
+            Response::Vacation(Some(until)) => writeln!(
+                f,
+                "On vacation until {}; your correspondence clocks are paused",
+                format_timestamp(*until)
+            ),
+            Response::Vacation(None) => writeln!(f, "Not on vacation"),
+            Response::Notify(Some(address)) => {
+                writeln!(f, "Turn notifications are sent to {address} while offline")
+            }
+            Response::Notify(None) => writeln!(f, "Turn notifications are off"),
+            Response::Registered => writeln!(
+                f,
+                "Registered; log in with {} from now on",
+                "login <username> password <password>".bold()
+            ),
             Response::Room(room) => writeln!(f, "{room}"),
+            Response::History(user, games) => {
+                writeln!(f, "{}", format!("History of {user}:").bold())?;
+                if games.is_empty() {
+                    writeln!(f, " No finished games")?;
+                } else {
+                    for game in games {
+                        writeln!(f, " {game}")?;
+                    }
+                }
+                Ok(())
+            }
+            Response::Leaderboard(entries) => {
+                writeln!(f, "{}", "Leaderboard:".bold())?;
+                if entries.is_empty() {
+                    writeln!(f, " No finished games")?;
+                } else {
+                    for entry in entries {
+                        writeln!(f, " {entry}")?;
+                    }
+                }
+                Ok(())
+            }
+            Response::AuditLog(roomid, entries) => {
+                writeln!(f, "{}", format!("Audit log of room {roomid}:").bold())?;
+                for entry in entries {
+                    writeln!(f, " {entry}")?;
+                }
+                Ok(())
+            }
+            Response::Feed { entries, .. } => {
+                for entry in entries {
+                    writeln!(f, "{} {entry}", ">".bold())?;
+                }
+                Ok(())
+            }
         }
     }
 }
@@ -198,24 +1430,117 @@ impl<Game: GameT> Display for Response<Game> {
 // server-only implementations
 
 impl<Game: GameT> RoomState<Game> {
-    pub fn make_move(&mut self, userid: &String, mov: Game::Move) -> Result<(), &'static str> {
+    pub fn make_move(&mut self, userid: &str, mov: Game::Move) -> Result<(), &'static str> {
+        match self {
+            RoomState::WaitingForPlayers { .. } => Err("Game did not start yet"),
+            RoomState::Started(g) => {
+                let game = g.as_mut().unwrap();
+                game.make_move(userid, mov)?;
+                if game.has_ended() {
+                    *self = RoomState::Ended(g.take());
+                }
+                Ok(())
+            }
+            RoomState::Ended(_) => Err("Game already finished"),
+        }
+    }
+
+    pub fn skip_turn(&mut self) -> Result<(), &'static str> {
+        match self {
+            RoomState::WaitingForPlayers { .. } => Err("Game did not start yet"),
+            RoomState::Started(g) => {
+                let game = g.as_mut().unwrap();
+                game.skip_turn()?;
+                if game.has_ended() {
+                    *self = RoomState::Ended(g.take());
+                }
+                Ok(())
+            }
+            RoomState::Ended(_) => Err("Game already finished"),
+        }
+    }
+
+    /// Take back the last move of a started game.
+    pub fn undo(&mut self) -> Result<(), &'static str> {
+        match self {
+            RoomState::WaitingForPlayers { .. } => Err("Game did not start yet"),
+            RoomState::Started(g) => g.as_mut().unwrap().undo(),
+            RoomState::Ended(_) => Err("Game already finished"),
+        }
+    }
+
+    /// Move for the current player, whose turn timer ran out.
+    pub fn time_out(&mut self, on_timeout: TimeoutMove) -> Result<(), &'static str> {
+        match self {
+            RoomState::WaitingForPlayers { .. } => Err("Game did not start yet"),
+            RoomState::Started(g) => {
+                let game = g.as_mut().unwrap();
+                game.time_out(on_timeout)?;
+                if game.has_ended() {
+                    *self = RoomState::Ended(g.take());
+                }
+                Ok(())
+            }
+            RoomState::Ended(_) => Err("Game already finished"),
+        }
+    }
+
+    /// The player to move in a started game.
+    pub fn current_player(&self) -> Option<String> {
+        match self {
+            RoomState::Started(Some(g)) => g.current_player(),
+            _ => None,
+        }
+    }
+
+    /// Stop a started game early.
+    pub fn abandon(&mut self) -> Result<(), &'static str> {
         match self {
             RoomState::WaitingForPlayers { .. } => Err("Game did not start yet"),
             RoomState::Started(g) => {
-                let g = g.as_mut().unwrap();
-                g.make_move(userid, mov)
+                g.as_mut().unwrap().abandon();
+                *self = RoomState::Ended(g.take());
+                Ok(())
             }
             RoomState::Ended(_) => Err("Game already finished"),
         }
     }
+
+    pub fn last_move_description(&self) -> Option<String> {
+        match self {
+            RoomState::Started(Some(g)) | RoomState::Ended(Some(g)) => g.last_move_description(),
+            _ => None,
+        }
+    }
 }
 
 impl<Game: GameT> Room<Game> {
+    /// The settings as everyone may see them: complete once the game ended.
+    pub fn public_settings(&self) -> Game::Settings {
+        match self.state {
+            RoomState::Ended(_) if !self.hide_deck => self.settings.clone(),
+            _ => Game::public_settings(&self.settings),
+        }
+    }
+
     pub fn to_list_item(&self) -> Self {
         Self {
             roomid: self.roomid,
-            settings: self.settings.clone(),
+            settings: self.public_settings(),
             players: self.players.clone(),
+            seq: self.seq,
+            pace: self.pace,
+            correspondence: self.correspondence,
+            spectator_view: self.spectator_view,
+            turn_timer: self.turn_timer,
+            turn_deadline: self.turn_deadline,
+            undo_votes: self.undo_votes.clone(),
+            private: self.private,
+            seating: self.seating,
+            hide_deck: self.hide_deck,
+            review: self.review,
+            seats: self.seats.clone(),
+            notes: self.notes.clone(),
             state: match &self.state {
                 RoomState::Started(_) => RoomState::Started(None),
                 RoomState::Ended(_) => RoomState::Ended(None),
@@ -223,23 +1548,62 @@ impl<Game: GameT> Room<Game> {
             },
         }
     }
+    /// The room as seen by the user: players get their own view of the game,
+    /// everyone else the spectator view of the room.
     pub fn to_view(&self, userid: &UserId) -> Self {
+        let view = |g: &Game| {
+            if self.players.contains(userid) {
+                g.to_view(userid)
+            } else {
+                g.to_spectator_view(self.spectator_view)
+            }
+        };
         Self {
             roomid: self.roomid,
-            settings: self.settings.clone(),
+            settings: self.public_settings(),
             players: self.players.clone(),
+            seq: self.seq,
+            pace: self.pace,
+            correspondence: self.correspondence,
+            spectator_view: self.spectator_view,
+            turn_timer: self.turn_timer,
+            turn_deadline: self.turn_deadline,
+            undo_votes: self.undo_votes.clone(),
+            private: self.private,
+            seating: self.seating,
+            hide_deck: self.hide_deck,
+            review: self.review,
+            seats: self.seats.clone(),
+            notes: self.notes.clone(),
             state: match &self.state {
-                RoomState::Started(g) => RoomState::Started(g.as_ref().map(|g| g.to_view(userid))),
+                RoomState::Started(g) => RoomState::Started(g.as_ref().map(view)),
+                RoomState::Ended(g) => RoomState::Ended(g.as_ref().map(|g| self.ended_view(g))),
                 s => s.clone(),
             },
         }
     }
 
-    pub fn start_game(&mut self) {
-        let RoomState::WaitingForPlayers {..} = self.state else {
-            return;
+    /// The game after it ended, without the cards that stay hidden if the
+    /// room hides its deck.
+    pub fn ended_view(&self, game: &Game) -> Game {
+        let mut game = game.clone();
+        if self.hide_deck {
+            game.forget_hidden_cards();
+        }
+        game
+    }
+
+    pub fn start_game(&mut self) -> Result<(), &'static str> {
+        let RoomState::WaitingForPlayers { .. } = self.state else {
+            return Ok(());
+        };
+        let game = match self.seating {
+            Seating::Random => Game::new(self.players.clone(), self.settings.clone())?,
+            Seating::Join | Seating::Host => {
+                Game::new_in_order(self.players.clone(), self.settings.clone())?
+            }
         };
-        self.state =
-            RoomState::Started(Some(Game::new(self.players.clone(), self.settings.clone())));
+        self.state = RoomState::Started(Some(game));
+        Ok(())
     }
 }